@@ -2,28 +2,110 @@ use actix_web::error::ErrorUnauthorized;
 use actix_web::Error;
 use chrono::{Duration, Utc};
 use jsonwebtoken::{self, DecodingKey, EncodingKey, Header, Validation};
+use rand::{distributions::Alphanumeric, Rng};
 use serde::{Deserialize, Serialize};
 
+use crate::db::{handles, models::Token};
 use crate::utils::GlobalSettings;
+use ffplayout_lib::utils::access::{is_revoked, TokenState};
 
-// Token lifetime
-const JWT_EXPIRATION_DAYS: i64 = 7;
+// Token lifetimes
+const ACCESS_EXPIRATION_MINUTES: i64 = 60;
+const REFRESH_EXPIRATION_DAYS: i64 = 30;
+
+// API tokens without expiration date get this lifetime
+const API_EXPIRATION_YEARS: i64 = 100;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    #[default]
+    Access,
+    Refresh,
+    Api,
+}
+
+impl TokenKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenKind::Access => "access",
+            TokenKind::Refresh => "refresh",
+            TokenKind::Api => "api",
+        }
+    }
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct Claims {
     pub id: i32,
     pub username: String,
     pub role: String,
-    exp: i64,
+    #[serde(default)]
+    pub jti: String,
+    #[serde(default)]
+    pub kind: TokenKind,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub iat: i64,
+    pub exp: i64,
+}
+
+fn token_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
 }
 
 impl Claims {
+    /// Claims for a short living access token.
     pub fn new(id: i32, username: String, role: String) -> Self {
         Self {
             id,
             username,
             role,
-            exp: (Utc::now() + Duration::days(JWT_EXPIRATION_DAYS)).timestamp(),
+            jti: token_id(),
+            kind: TokenKind::Access,
+            scope: None,
+            iat: Utc::now().timestamp(),
+            exp: (Utc::now() + Duration::minutes(ACCESS_EXPIRATION_MINUTES)).timestamp(),
+        }
+    }
+
+    /// Claims for a refresh token, which can only be used to get a new access token.
+    pub fn refresh(id: i32, username: String, role: String) -> Self {
+        Self {
+            kind: TokenKind::Refresh,
+            exp: (Utc::now() + Duration::days(REFRESH_EXPIRATION_DAYS)).timestamp(),
+            ..Self::new(id, username, role)
+        }
+    }
+
+    /// Claims for a named API token, with scope `read` or `write`.
+    pub fn api(id: i32, username: String, role: String, scope: &str, days: Option<i64>) -> Self {
+        let lifetime = days.map_or(Duration::days(API_EXPIRATION_YEARS * 365), Duration::days);
+
+        Self {
+            kind: TokenKind::Api,
+            scope: Some(scope.to_string()),
+            exp: (Utc::now() + lifetime).timestamp(),
+            ..Self::new(id, username, role)
+        }
+    }
+
+    /// Database entry for refresh and API tokens.
+    pub fn to_token(&self, name: &str, expires: Option<i64>) -> Token {
+        Token {
+            id: 0,
+            jti: self.jti.clone(),
+            user_id: self.id,
+            kind: self.kind.as_str().to_string(),
+            name: name.to_string(),
+            scope: self.scope.clone().unwrap_or_else(|| "write".to_string()),
+            created: Utc::now().timestamp(),
+            expires,
         }
     }
 }
@@ -44,3 +126,50 @@ pub async fn decode_jwt(token: &str) -> Result<Claims, Error> {
         .map(|data| data.claims)
         .map_err(|e| ErrorUnauthorized(e.to_string()))
 }
+
+/// Check if the token is still valid on server side.
+///
+/// - access tokens must not be in the revocation list
+/// - refresh and API tokens must exist in the database
+/// - the token is not older than the last password or role change from the user
+pub async fn check_revocation(claims: &Claims, valid_after: i64) -> Result<(), Error> {
+    let state = match claims.kind {
+        TokenKind::Access => TokenState {
+            revoked: handles::is_revoked(&claims.jti).await.unwrap_or(true),
+            ..Default::default()
+        },
+        TokenKind::Refresh | TokenKind::Api => TokenState {
+            stored: handles::select_token(&claims.jti).await.is_ok(),
+            ..Default::default()
+        },
+    };
+
+    if is_revoked(
+        claims.kind.as_str(),
+        claims.iat,
+        &TokenState {
+            valid_after,
+            ..state
+        },
+    ) {
+        return Err(ErrorUnauthorized("Token is revoked!"));
+    }
+
+    Ok(())
+}
+
+/// Create new access and refresh token, the refresh token gets stored in database.
+pub async fn create_token_pair(
+    id: i32,
+    username: String,
+    role: String,
+) -> Result<(String, String), Error> {
+    let access = create_jwt(Claims::new(id, username.clone(), role.clone()))?;
+    let refresh_claims = Claims::refresh(id, username, role);
+
+    handles::insert_token(&refresh_claims.to_token("", Some(refresh_claims.exp)))
+        .await
+        .map_err(|e| ErrorUnauthorized(e.to_string()))?;
+
+    Ok((access, create_jwt(refresh_claims)?))
+}
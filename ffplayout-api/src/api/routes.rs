@@ -7,36 +7,67 @@
 /// ```
 ///
 /// For all endpoints an (Bearer) authentication is required.\
-/// `{id}` represent the channel id, and at default is 1.
+/// `{id}` represent the channel id, and at default is 1.\
+/// Users need a permission for the channel (viewer, editor, operator or admin), admins can access all channels.
 use std::{collections::HashMap, env, fs, path::Path};
 
 use actix_multipart::Multipart;
-use actix_web::{delete, get, http::StatusCode, patch, post, put, web, HttpResponse, Responder};
-use actix_web_grants::{permissions::AuthDetails, proc_macro::has_any_role};
-use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHash, SaltString},
-    Argon2, PasswordHasher, PasswordVerifier,
+use actix_web::{
+    delete, get,
+    http::{
+        header::{ETAG, IF_MATCH},
+        StatusCode,
+    },
+    patch, post, put, web, HttpRequest, HttpResponse, Responder,
 };
+use actix_web_grants::proc_macro::has_any_role;
+use argon2::{password_hash::PasswordHash, Argon2, PasswordVerifier};
+use chrono::Local;
 use serde::{Deserialize, Serialize};
 use simplelog::*;
 
-use crate::auth::{create_jwt, Claims};
+use crate::auth::{create_jwt, create_token_pair, decode_jwt, Claims, TokenKind};
 use crate::db::{
     handles,
-    models::{Channel, LoginUser, TextPreset, User},
+    models::{
+        AuditFilter, Channel, JobFilter, LoginUser, MediaFilter, TextPreset, User, UserChannel,
+        UserFields,
+    },
 };
 use crate::utils::{
+    audit::{audit, summarize},
     channels::{create_channel, delete_channel},
-    control::{control_service, control_state, media_info, send_message, Process},
+    check_channel,
+    control::{control_service, control_state, media_info, send_message, service_info, Process},
     errors::ServiceError,
     files::{
-        browser, create_directory, remove_file_or_folder, rename_file, upload, MoveObject,
-        PathObject,
+        browser, create_directory, norm_abs_path, remove_file_or_folder, rename_file, upload,
+        MoveObject, PathObject, RemoveObject, RenameObject,
     },
-    playlist::{delete_playlist, generate_playlist, read_playlist, write_playlist},
-    playout_config, read_log_file, read_playout_config, Role,
+    jobs::{cancel_job, get_job, submit_job, JobTask},
+    library::{library_tags, update_media, MediaUpdate},
+    log_file::{read_log_file, tail_log_file, LogFilter},
+    overview::channel_overview,
+    playlist::{
+        copy_playlist, delete_playlist, diff_revisions, list_revisions, playlist_references,
+        read_playlist, restore_revision, CopyRequest,
+    },
+    playlist_edit::{edit_playlist, read_timing, save_checked},
+    playout_config, read_playout_config,
+    thumbnails::{thumbnail, ThumbQuery},
+    trash::{list_trash, purge_trash, restore_trash},
+    upload::{cancel_upload, create_upload, upload_chunk, upload_status, UploadRequest},
+    users::{change_user, list_users, remove_user, save_user, set_password, UserUpdate},
+    ChannelRole, Role,
+};
+use ffplayout_lib::utils::{
+    access::redact_secrets,
+    epg::xmltv,
+    export::{export_playlist, ExportFormat},
+    playlist_copy::date_range,
+    playlist_edit::{playlist_etag, PlaylistEdit},
+    JsonPlaylist, Media, PlayoutConfig,
 };
-use ffplayout_lib::utils::{import::import_file, JsonPlaylist, PlayoutConfig};
 
 #[derive(Serialize)]
 struct ResponseObj<T> {
@@ -51,6 +82,40 @@ struct UserObj<T> {
     user: Option<T>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RefreshObj {
+    refresh_token: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TokenPair {
+    token: String,
+    refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenObj {
+    #[serde(default)]
+    name: String,
+    #[serde(default = "default_scope")]
+    scope: String,
+    #[serde(default)]
+    user_id: Option<i32>,
+    #[serde(default)]
+    days: Option<i64>,
+}
+
+fn default_scope() -> String {
+    "write".to_string()
+}
+
+#[derive(Debug, Serialize)]
+struct ApiToken {
+    name: String,
+    scope: String,
+    token: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct DateObj {
     #[serde(default)]
@@ -63,6 +128,45 @@ struct FileObj {
     path: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct InsertObj {
+    #[serde(default)]
+    index: Option<usize>,
+    clip: Media,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TrimObj {
+    #[serde(default, rename = "in")]
+    seek: Option<f64>,
+    #[serde(default)]
+    out: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ItemMoveObj {
+    to: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportObj {
+    format: ExportFormat,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EpgObj {
+    #[serde(default)]
+    from: Option<String>,
+    #[serde(default)]
+    to: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DiffObj {
+    from: i32,
+    to: i32,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ImportObj {
     #[serde(default)]
@@ -98,6 +202,16 @@ pub async fn login(credentials: web::Json<User>) -> impl Responder {
             user.password = "".into();
             user.salt = None;
 
+            if user.disabled {
+                error!("Login {} failed! Account is disabled", credentials.username);
+                return web::Json(UserObj {
+                    message: "Account is disabled!".into(),
+                    user: None,
+                })
+                .customize()
+                .with_status(StatusCode::FORBIDDEN);
+            }
+
             if Argon2::default()
                 .verify_password(credentials.password.as_bytes(), &hash)
                 .is_ok()
@@ -105,10 +219,11 @@ pub async fn login(credentials: web::Json<User>) -> impl Responder {
                 let role = handles::select_role(&user.role_id.unwrap_or_default())
                     .await
                     .unwrap_or_else(|_| "guest".to_string());
-                let claims = Claims::new(user.id, user.username.clone(), role.clone());
-
-                if let Ok(token) = create_jwt(claims) {
+                if let Ok((token, refresh_token)) =
+                    create_token_pair(user.id, user.username.clone(), role.clone()).await
+                {
                     user.token = Some(token);
+                    user.refresh_token = Some(refresh_token);
                 };
 
                 info!("user {} login, with role: {role}", credentials.username);
@@ -141,6 +256,60 @@ pub async fn login(credentials: web::Json<User>) -> impl Responder {
     }
 }
 
+/// **Refresh Token**
+///
+/// The access token from login is valid for one hour, with the refresh token a new pair of tokens can be requested.
+/// Every refresh token can only be used once.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/auth/refresh/ -H "Content-Type: application/json" \
+/// -d '{ "refresh_token": "<REFRESH TOKEN>" }'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
+///     "token": "<TOKEN>",
+///     "refresh_token": "<REFRESH TOKEN>"
+/// }
+/// ```
+#[post("/auth/refresh/")]
+pub async fn refresh(data: web::Json<RefreshObj>) -> Result<impl Responder, ServiceError> {
+    let claims = decode_jwt(&data.refresh_token)
+        .await
+        .map_err(|_| ServiceError::Unauthorized)?;
+
+    if claims.kind != TokenKind::Refresh {
+        return Err(ServiceError::Unauthorized);
+    }
+
+    // only the request which deletes the token gets a new pair
+    if handles::delete_token_by_jti(&claims.jti)
+        .await?
+        .rows_affected()
+        != 1
+    {
+        return Err(ServiceError::Unauthorized);
+    }
+
+    let user = handles::select_user_by_id(&claims.id).await?;
+
+    if user.disabled {
+        return Err(ServiceError::Unauthorized);
+    }
+
+    let role = handles::select_role(&user.role_id.unwrap_or_default())
+        .await
+        .unwrap_or_else(|_| "guest".to_string());
+    let (token, refresh_token) = create_token_pair(user.id, user.username, role).await?;
+
+    Ok(web::Json(TokenPair {
+        token,
+        refresh_token,
+    }))
+}
+
 /// From here on all request **must** contain the authorization header:\
 /// `"Authorization: Bearer <TOKEN>"`
 
@@ -168,6 +337,8 @@ async fn get_user(user: web::ReqData<LoginUser>) -> Result<impl Responder, Servi
 /// curl -X PUT http://127.0.0.1:8787/api/user/1 -H 'Content-Type: application/json' \
 /// -d '{"mail": "<MAIL>", "password": "<PASS>"}' -H 'Authorization: <TOKEN>'
 /// ```
+///
+/// A password change revokes all tokens from the user, so a new login is needed.
 #[put("/user/{id}")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
 async fn update_user(
@@ -176,26 +347,22 @@ async fn update_user(
     data: web::Json<User>,
 ) -> Result<impl Responder, ServiceError> {
     if id.into_inner() == user.id {
-        let mut fields = String::new();
+        let mut fields = UserFields::default();
+        let mut changes = vec![];
 
         if let Some(mail) = data.mail.clone() {
-            fields.push_str(format!("mail = '{mail}'").as_str());
+            fields.mail = Some(mail);
+            changes.push("mail");
         }
 
         if !data.password.is_empty() {
-            if !fields.is_empty() {
-                fields.push_str(", ");
-            }
-
-            let salt = SaltString::generate(&mut OsRng);
-            let password_hash = Argon2::default()
-                .hash_password(data.password.clone().as_bytes(), &salt)
-                .unwrap();
-
-            fields.push_str(format!("password = '{}', salt = '{salt}'", password_hash).as_str());
+            set_password(&mut fields, &data.password);
+            changes.push("password");
         }
 
-        if handles::update_user(user.id, fields).await.is_ok() {
+        if save_user(user.id, &fields).await.is_ok() {
+            audit(&user, None, "user.update", changes.join(", ")).await;
+
             return Ok("Update Success");
         };
 
@@ -209,14 +376,37 @@ async fn update_user(
 ///
 /// ```BASH
 /// curl -X POST 'http://127.0.0.1:8787/api/user/' -H 'Content-Type: application/json' \
-/// -d '{"mail": "<MAIL>", "username": "<USER>", "password": "<PASS>", "role_id": 1, "channel_id": 1}' \
+/// -d '{"mail": "<MAIL>", "username": "<USER>", "password": "<PASS>", "role_id": 1, "channel_id": 1, "channel_role": "editor"}' \
 /// -H 'Authorization: Bearer <TOKEN>'
 /// ```
+///
+/// `channel_id` and `channel_role` are optional, without them the user gets no channel permission.
 #[post("/user/")]
 #[has_any_role("Role::Admin", type = "Role")]
-async fn add_user(data: web::Json<User>) -> Result<impl Responder, ServiceError> {
+async fn add_user(
+    user: web::ReqData<LoginUser>,
+    data: web::Json<User>,
+) -> Result<impl Responder, ServiceError> {
+    if data.channel_id.is_some()
+        && data
+            .channel_role
+            .as_deref()
+            .and_then(ChannelRole::set_role)
+            .is_none()
+    {
+        return Err(ServiceError::BadRequest(
+            "Channel needs a valid channel_role!".into(),
+        ));
+    }
+
+    let summary = summarize(&data.username);
+
     match handles::insert_user(data.into_inner()).await {
-        Ok(_) => Ok("Add User Success"),
+        Ok(_) => {
+            audit(&user, None, "user.create", summary).await;
+
+            Ok("Add User Success")
+        }
         Err(e) => {
             error!("{e}");
             Err(ServiceError::InternalServerError)
@@ -224,582 +414,2116 @@ async fn add_user(data: web::Json<User>) -> Result<impl Responder, ServiceError>
     }
 }
 
-/// #### ffpapi Settings
-///
-/// **Get Settings from Channel**
+/// **Get all Users**
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/channel/1 -H "Authorization: Bearer <TOKEN>"
+/// curl -X GET 'http://127.0.0.1:8787/api/users' -H 'Authorization: Bearer <TOKEN>'
 /// ```
 ///
 /// **Response:**
 ///
 /// ```JSON
-/// {
-///     "id": 1,
-///     "name": "Channel 1",
-///     "preview_url": "http://localhost/live/preview.m3u8",
-///     "config_path": "/etc/ffplayout/ffplayout.yml",
-///     "extra_extensions": "jpg,jpeg,png",
-///     "service": "ffplayout.service",
-///     "utc_offset": "+120"
-/// }
+/// [{ "id": 1, "mail": "admin@example.org", "username": "admin", "role": "admin", "disabled": false }]
 /// ```
-#[get("/channel/{id}")]
-#[has_any_role("Role::Admin", "Role::User", type = "Role")]
-async fn get_channel(id: web::Path<i32>) -> Result<impl Responder, ServiceError> {
-    if let Ok(channel) = handles::select_channel(&id).await {
-        return Ok(web::Json(channel));
+#[get("/users")]
+#[has_any_role("Role::Admin", type = "Role")]
+async fn get_users() -> Result<impl Responder, ServiceError> {
+    match list_users().await {
+        Ok(users) => Ok(web::Json(users)),
+        Err(e) => Err(e),
     }
-
-    Err(ServiceError::InternalServerError)
 }
 
-/// **Get settings from all Channels**
+/// **Change User**
+///
+/// Change role, reset password or disable/enable a user account, all fields are optional.
+/// A new role or password revokes all tokens from the user.
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/channels -H "Authorization: Bearer <TOKEN>"
+/// curl -X PATCH 'http://127.0.0.1:8787/api/user/2' -H 'Content-Type: application/json' \
+/// -d '{"role": "user", "password": "<PASS>", "disabled": true}' -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[get("/channels")]
+#[patch("/user/{id}")]
 #[has_any_role("Role::Admin", type = "Role")]
-async fn get_all_channels() -> Result<impl Responder, ServiceError> {
-    if let Ok(channel) = handles::select_all_channels().await {
-        return Ok(web::Json(channel));
-    }
+async fn patch_user(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<UserUpdate>,
+) -> Result<impl Responder, ServiceError> {
+    change_user(*id, Some(user.id), &data).await?;
 
-    Err(ServiceError::InternalServerError)
+    let summary = format!(
+        "user_id: {id}, role: {:?}, password reset: {}, disabled: {:?}",
+        data.role,
+        data.password.is_some(),
+        data.disabled
+    );
+    audit(&user, None, "user.change", summary).await;
+
+    Ok("Update Success")
 }
 
-/// **Update Channel**
+/// **Delete User**
 ///
 /// ```BASH
-/// curl -X PATCH http://127.0.0.1:8787/api/channel/1 -H "Content-Type: application/json" \
-/// -d '{ "id": 1, "name": "Channel 1", "preview_url": "http://localhost/live/stream.m3u8", \
-/// "config_path": "/etc/ffplayout/ffplayout.yml", "extra_extensions": "jpg,jpeg,png"}' \
-/// -H "Authorization: Bearer <TOKEN>"
+/// curl -X DELETE 'http://127.0.0.1:8787/api/user/2' -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[patch("/channel/{id}")]
+#[delete("/user/{id}")]
 #[has_any_role("Role::Admin", type = "Role")]
-async fn patch_channel(
+async fn remove_user_account(
     id: web::Path<i32>,
-    data: web::Json<Channel>,
+    user: web::ReqData<LoginUser>,
 ) -> Result<impl Responder, ServiceError> {
-    if handles::update_channel(*id, data.into_inner())
-        .await
-        .is_ok()
-    {
-        return Ok("Update Success");
-    };
+    remove_user(*id, Some(user.id)).await?;
+    audit(&user, None, "user.delete", format!("user_id: {id}")).await;
 
-    Err(ServiceError::InternalServerError)
+    Ok("Delete User Success")
 }
 
-/// **Create new Channel**
+/// **Logout**
+///
+/// Revoke the current access token, and the refresh token when it is given.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/channel/ -H "Content-Type: application/json" \
-/// -d '{ "name": "Channel 2", "preview_url": "http://localhost/live/channel2.m3u8", \
-/// "config_path": "/etc/ffplayout/channel2.yml", "extra_extensions": "jpg,jpeg,png",
-/// "service": "ffplayout@channel2.service" }' \
-/// -H "Authorization: Bearer <TOKEN>"
+/// curl -X POST 'http://127.0.0.1:8787/api/auth/logout/' -H 'Content-Type: application/json' \
+/// -d '{ "refresh_token": "<REFRESH TOKEN>" }' -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/channel/")]
-#[has_any_role("Role::Admin", type = "Role")]
-async fn add_channel(data: web::Json<Channel>) -> Result<impl Responder, ServiceError> {
-    match create_channel(data.into_inner()).await {
-        Ok(c) => Ok(web::Json(c)),
-        Err(e) => Err(e),
+#[post("/auth/logout/")]
+#[has_any_role("Role::Admin", "Role::User", "Role::Guest", type = "Role")]
+async fn logout(
+    claims: web::ReqData<Claims>,
+    data: Option<web::Json<RefreshObj>>,
+) -> Result<impl Responder, ServiceError> {
+    if claims.kind == TokenKind::Access {
+        handles::insert_revoked(&claims.jti, claims.exp).await?;
+    }
+
+    if let Some(obj) = data {
+        if let Ok(refresh_claims) = decode_jwt(&obj.refresh_token).await {
+            if refresh_claims.id == claims.id {
+                handles::delete_token_by_jti(&refresh_claims.jti).await?;
+            }
+        }
     }
+
+    Ok("Logout Success")
 }
 
-/// **Delete Channel**
+/// **Get API Tokens**
 ///
 /// ```BASH
-/// curl -X DELETE http://127.0.0.1:8787/api/channel/2 -H "Authorization: Bearer <TOKEN>"
+/// curl -X GET 'http://127.0.0.1:8787/api/tokens' -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[delete("/channel/{id}")]
+///
+/// **Response:**
+///
+/// ```JSON
+/// [{ "id": 1, "user_id": 1, "kind": "api", "name": "backup script", "scope": "read", "created": 1660000000, "expires": null }]
+/// ```
+#[get("/tokens")]
 #[has_any_role("Role::Admin", type = "Role")]
-async fn remove_channel(id: web::Path<i32>) -> Result<impl Responder, ServiceError> {
-    if delete_channel(*id).await.is_ok() {
-        return Ok("Delete Channel Success");
+async fn get_tokens() -> Result<impl Responder, ServiceError> {
+    match handles::select_tokens(TokenKind::Api.as_str()).await {
+        Ok(tokens) => Ok(web::Json(tokens)),
+        Err(e) => {
+            error!("{e}");
+            Err(ServiceError::InternalServerError)
+        }
     }
-
-    Err(ServiceError::InternalServerError)
 }
 
-/// #### ffplayout Config
+/// **Create API Token**
 ///
-/// **Get Config**
+/// API tokens are made for automation, they have a name and a scope: `read` (only GET requests without side effects, so no thumbnail creation) or `write`.
+/// The token belongs to the user from `user_id`, default is the current user.
+/// It has the rights from the current role of this user, a role change counts for the token too.
+/// Without `days` the token not expires. The token is only visible in this response.
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/playout/config/1 -H 'Authorization: <TOKEN>'
+/// curl -X POST 'http://127.0.0.1:8787/api/tokens/' -H 'Content-Type: application/json' \
+/// -d '{"name": "backup script", "scope": "read", "days": 365}' -H 'Authorization: Bearer <TOKEN>'
 /// ```
-///
-/// Response is a JSON object from the ffplayout.yml
-#[get("/playout/config/{id}")]
-#[has_any_role("Role::Admin", "Role::User", type = "Role")]
-async fn get_playout_config(
-    id: web::Path<i32>,
-    _details: AuthDetails<Role>,
+#[post("/tokens/")]
+#[has_any_role("Role::Admin", type = "Role")]
+async fn add_token(
+    user: web::ReqData<LoginUser>,
+    data: web::Json<TokenObj>,
 ) -> Result<impl Responder, ServiceError> {
-    if let Ok(channel) = handles::select_channel(&id).await {
-        if let Ok(config) = read_playout_config(&channel.config_path) {
-            return Ok(web::Json(config));
-        }
-    };
+    if !["read", "write"].contains(&data.scope.as_str()) {
+        return Err(ServiceError::BadRequest(format!(
+            "Unknown scope: {}",
+            data.scope
+        )));
+    }
 
-    Err(ServiceError::InternalServerError)
+    let owner = handles::select_user_by_id(&data.user_id.unwrap_or(user.id)).await?;
+    let role = handles::select_role(&owner.role_id.unwrap_or_default())
+        .await
+        .unwrap_or_else(|_| "guest".to_string());
+    let summary = format!(
+        "name: {}, scope: {}, owner: {}",
+        data.name, data.scope, owner.username
+    );
+    let claims = Claims::api(owner.id, owner.username, role, &data.scope, data.days);
+    let expires = data.days.map(|_| claims.exp);
+
+    handles::insert_token(&claims.to_token(&data.name, expires)).await?;
+    audit(&user, None, "token.create", summary).await;
+
+    Ok(web::Json(ApiToken {
+        name: data.name.clone(),
+        scope: data.scope.clone(),
+        token: create_jwt(claims)?,
+    }))
 }
 
-/// **Update Config**
+/// **Revoke API Token**
 ///
 /// ```BASH
-/// curl -X PUT http://127.0.0.1:8787/api/playout/config/1 -H "Content-Type: application/json" \
-/// -d { <CONFIG DATA> } -H 'Authorization: <TOKEN>'
+/// curl -X DELETE 'http://127.0.0.1:8787/api/tokens/1' -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[put("/playout/config/{id}")]
+#[delete("/tokens/{id}")]
 #[has_any_role("Role::Admin", type = "Role")]
-async fn update_playout_config(
+async fn remove_token(
     id: web::Path<i32>,
-    data: web::Json<PlayoutConfig>,
+    user: web::ReqData<LoginUser>,
 ) -> Result<impl Responder, ServiceError> {
-    if let Ok(channel) = handles::select_channel(&id).await {
-        if let Ok(f) = std::fs::OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&channel.config_path)
-        {
-            serde_yaml::to_writer(f, &data).unwrap();
-
-            return Ok("Update playout config success.");
-        } else {
-            return Err(ServiceError::InternalServerError);
-        };
-    };
+    match handles::delete_token(&id).await {
+        Ok(_) => {
+            audit(&user, None, "token.revoke", format!("token_id: {id}")).await;
 
-    Err(ServiceError::InternalServerError)
+            Ok("Revoke Token Success")
+        }
+        Err(e) => {
+            error!("{e}");
+            Err(ServiceError::InternalServerError)
+        }
+    }
 }
 
-/// #### Text Presets
+/// **Get Channel Permissions from User**
 ///
-/// Text presets are made for sending text messages to the ffplayout engine, to overlay them as a lower third.
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/user/2/channels' -H 'Authorization: Bearer <TOKEN>'
+/// ```
 ///
-/// **Get all Presets**
+/// **Response:**
 ///
-/// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/presets/ -H 'Content-Type: application/json' \
-/// -H 'Authorization: <TOKEN>'
+/// ```JSON
+/// [{ "id": 1, "user_id": 2, "channel_id": 1, "role": "editor" }]
 /// ```
-#[get("/presets/{id}")]
-#[has_any_role("Role::Admin", "Role::User", type = "Role")]
-async fn get_presets(id: web::Path<i32>) -> Result<impl Responder, ServiceError> {
-    if let Ok(presets) = handles::select_presets(*id).await {
-        return Ok(web::Json(presets));
+#[get("/user/{id}/channels")]
+#[has_any_role("Role::Admin", type = "Role")]
+async fn get_user_channels(id: web::Path<i32>) -> Result<impl Responder, ServiceError> {
+    match handles::select_user_channels(&id).await {
+        Ok(permissions) => Ok(web::Json(permissions)),
+        Err(e) => {
+            error!("{e}");
+            Err(ServiceError::InternalServerError)
+        }
     }
-
-    Err(ServiceError::InternalServerError)
 }
 
-/// **Update Preset**
+/// **Set Channel Permission for User**
+///
+/// Roles are: viewer, editor, operator, admin. Every role includes the rights from the roles before.
 ///
 /// ```BASH
-/// curl -X PUT http://127.0.0.1:8787/api/presets/1 -H 'Content-Type: application/json' \
-/// -d '{ "name": "<PRESET NAME>", "text": "<TEXT>", "x": "<X>", "y": "<Y>", "fontsize": 24, \
-/// "line_spacing": 4, "fontcolor": "#ffffff", "box": 1, "boxcolor": "#000000", "boxborderw": 4, "alpha": 1.0, "channel_id": 1 }' \
-/// -H 'Authorization: <TOKEN>'
+/// curl -X PUT 'http://127.0.0.1:8787/api/user/2/channels' -H 'Content-Type: application/json' \
+/// -d '{"channel_id": 1, "role": "editor"}' -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[put("/presets/{id}")]
-#[has_any_role("Role::Admin", "Role::User", type = "Role")]
-async fn update_preset(
+#[put("/user/{id}/channels")]
+#[has_any_role("Role::Admin", type = "Role")]
+async fn set_user_channel(
     id: web::Path<i32>,
-    data: web::Json<TextPreset>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<UserChannel>,
 ) -> Result<impl Responder, ServiceError> {
-    if handles::update_preset(&id, data.into_inner()).await.is_ok() {
-        return Ok("Update Success");
+    if ChannelRole::set_role(&data.role).is_none() {
+        return Err(ServiceError::BadRequest(format!(
+            "Unknown channel role: {}",
+            data.role
+        )));
     }
 
-    Err(ServiceError::InternalServerError)
+    handles::select_channel(&data.channel_id).await?;
+
+    match handles::upsert_user_channel(&id, &data.channel_id, &data.role).await {
+        Ok(_) => {
+            let summary = format!("user_id: {id}, role: {}", data.role);
+            audit(&user, Some(data.channel_id), "permission.set", summary).await;
+
+            Ok("Update Success")
+        }
+        Err(e) => {
+            error!("{e}");
+            Err(ServiceError::InternalServerError)
+        }
+    }
 }
 
-/// **Add new Preset**
+/// **Remove Channel Permission from User**
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/presets/ -H 'Content-Type: application/json' \
-/// -d '{ "name": "<PRESET NAME>", "text": "TEXT>", "x": "<X>", "y": "<Y>", "fontsize": 24, \
-/// "line_spacing": 4, "fontcolor": "#ffffff", "box": 1, "boxcolor": "#000000", "boxborderw": 4, "alpha": 1.0, "channel_id": 1 }' \
-/// -H 'Authorization: <TOKEN>'
+/// curl -X DELETE 'http://127.0.0.1:8787/api/user/2/channels/1' -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/presets/")]
-#[has_any_role("Role::Admin", "Role::User", type = "Role")]
-async fn add_preset(data: web::Json<TextPreset>) -> Result<impl Responder, ServiceError> {
-    if handles::insert_preset(data.into_inner()).await.is_ok() {
+#[delete("/user/{id}/channels/{channel}")]
+#[has_any_role("Role::Admin", type = "Role")]
+async fn remove_user_channel(
+    params: web::Path<(i32, i32)>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    match handles::delete_user_channel(&params.0, &params.1).await {
+        Ok(_) => {
+            let summary = format!("user_id: {}", params.0);
+            audit(&user, Some(params.1), "permission.delete", summary).await;
+
+            Ok("Delete Success")
+        }
+        Err(e) => {
+            error!("{e}");
+            Err(ServiceError::InternalServerError)
+        }
+    }
+}
+
+/// **Get Audit Trail**
+///
+/// List user actions, newest first. Optional filters are: `user_id`, `username`, `channel_id`,
+/// `action` (prefix, like `playlist` or `control.next`), `from` and `to` (unix timestamps),
+/// `limit` (default 100) and `offset`.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/audit?channel_id=1&action=playlist&limit=50' \
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// [{ "id": 1, "timestamp": 1660000000, "user_id": 1, "username": "admin", "channel_id": 1,
+///    "action": "playlist.save", "summary": "date: 2022-06-20, clips: 24" }]
+/// ```
+#[get("/audit")]
+#[has_any_role("Role::Admin", type = "Role")]
+async fn get_audit(filter: web::Query<AuditFilter>) -> Result<impl Responder, ServiceError> {
+    match handles::select_audit(&filter).await {
+        Ok(entries) => Ok(web::Json(entries)),
+        Err(e) => {
+            error!("{e}");
+            Err(ServiceError::InternalServerError)
+        }
+    }
+}
+
+/// #### ffpapi Settings
+///
+/// **Get Settings from Channel**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/channel/1 -H "Authorization: Bearer <TOKEN>"
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
+///     "id": 1,
+///     "name": "Channel 1",
+///     "preview_url": "http://localhost/live/preview.m3u8",
+///     "config_path": "/etc/ffplayout/ffplayout.yml",
+///     "extra_extensions": "jpg,jpeg,png",
+///     "service": "ffplayout.service",
+///     "utc_offset": "+120"
+/// }
+/// ```
+#[get("/channel/{id}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+async fn get_channel(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    if let Ok(channel) = handles::select_channel(&id).await {
+        return Ok(web::Json(channel));
+    }
+
+    Err(ServiceError::InternalServerError)
+}
+
+/// **Get settings from all Channels**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/channels -H "Authorization: Bearer <TOKEN>"
+/// ```
+#[get("/channels")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+async fn get_all_channels(user: web::ReqData<LoginUser>) -> Result<impl Responder, ServiceError> {
+    if let Ok(mut channels) = handles::select_all_channels().await {
+        if Role::set_role(&user.role) != Role::Admin {
+            let permissions = handles::select_user_channels(&user.id).await?;
+            channels.retain(|c| permissions.iter().any(|p| p.channel_id == c.id));
+        }
+
+        return Ok(web::Json(channels));
+    }
+
+    Err(ServiceError::InternalServerError)
+}
+
+/// **Overview from all Channels**
+///
+/// Service state, current and next clip, remaining time, ingest, sync delta and the latest errors
+/// from every channel of the user. Channels which don't answer in time get empty values,
+/// `sync_delta` is `null` until the first clip from a playlist was checked.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/overview -H "Authorization: Bearer <TOKEN>"
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// [
+///     {
+///         "id": 1,
+///         "name": "Channel 1",
+///         "state": "active",
+///         "current": { "seek": 0.0, "out": 120.0, "duration": 120.0, "category": "", "source": "/path/clip.mp4" },
+///         "next": { "seek": 0.0, "out": 60.0, "duration": 60.0, "category": "", "source": "/path/next.mp4" },
+///         "remaining_sec": 42.3,
+///         "ingest_runs": false,
+///         "sync_delta": 0.12,
+///         "errors": [{ "timestamp": "2022-06-20 12:00:00", "unit": "Decoder", "message": "..." }]
+///     }
+/// ]
+/// ```
+#[get("/overview")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+async fn get_overview(user: web::ReqData<LoginUser>) -> Result<impl Responder, ServiceError> {
+    Ok(web::Json(channel_overview(&user).await?))
+}
+
+/// **Update Channel**
+///
+/// ```BASH
+/// curl -X PATCH http://127.0.0.1:8787/api/channel/1 -H "Content-Type: application/json" \
+/// -d '{ "id": 1, "name": "Channel 1", "preview_url": "http://localhost/live/stream.m3u8", \
+/// "config_path": "/etc/ffplayout/ffplayout.yml", "extra_extensions": "jpg,jpeg,png"}' \
+/// -H "Authorization: Bearer <TOKEN>"
+/// ```
+#[patch("/channel/{id}")]
+#[has_any_role("Role::Admin", type = "Role")]
+async fn patch_channel(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<Channel>,
+) -> Result<impl Responder, ServiceError> {
+    let summary = summarize(&*data);
+
+    if handles::update_channel(*id, data.into_inner())
+        .await
+        .is_ok()
+    {
+        audit(&user, Some(*id), "channel.update", summary).await;
+
+        return Ok("Update Success");
+    };
+
+    Err(ServiceError::InternalServerError)
+}
+
+/// **Create new Channel**
+///
+/// The config comes from the template, with free ports for RPC server, ingest and text messages.
+/// Folders for playlists, media, HLS and logs get created and the service is enabled.
+/// Without `config_path` and `service`, they are named after the channel, like
+/// `/etc/ffplayout/channel_2.yml` and `ffplayout@channel_2.service`.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/channel/ -H "Content-Type: application/json" \
+/// -d '{ "name": "Channel 2", "preview_url": "http://localhost/live/channel_2/stream.m3u8", \
+/// "extra_extensions": "jpg,jpeg,png" }' \
+/// -H "Authorization: Bearer <TOKEN>"
+/// ```
+#[post("/channel/")]
+#[has_any_role("Role::Admin", type = "Role")]
+async fn add_channel(
+    user: web::ReqData<LoginUser>,
+    data: web::Json<Channel>,
+) -> Result<impl Responder, ServiceError> {
+    let summary = summarize(&*data);
+
+    match create_channel(data.into_inner()).await {
+        Ok(c) => {
+            audit(&user, Some(c.id), "channel.create", summary).await;
+
+            Ok(web::Json(c))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// **Delete Channel**
+///
+/// Stops and disables the service, removes the config, the HLS and log folders. Playlist and media
+/// folders are only removed when they are empty. Only what the API has created for the channel gets
+/// removed, channels from before, like the default channel, keep all their files.
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/channel/2 -H "Authorization: Bearer <TOKEN>"
+/// ```
+#[delete("/channel/{id}")]
+#[has_any_role("Role::Admin", type = "Role")]
+async fn remove_channel(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    delete_channel(*id).await?;
+    audit(&user, Some(*id), "channel.delete", String::new()).await;
+
+    Ok("Delete Channel Success")
+}
+
+/// #### ffplayout Config
+///
+/// **Get Config**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/playout/config/1 -H 'Authorization: <TOKEN>'
+/// ```
+///
+/// Response is a JSON object from the ffplayout.yml. Users below the channel role admin get it
+/// without passwords, RPC authorization and webhook or command targets.
+#[get("/playout/config/{id}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+async fn get_playout_config(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    if let Ok(channel) = handles::select_channel(&id).await {
+        if let Ok(mut config) = read_playout_config(&channel.config_path) {
+            if check_channel(&user, &id, ChannelRole::Admin).await.is_err() {
+                redact_secrets(&mut config);
+            }
+
+            return Ok(web::Json(config));
+        }
+    };
+
+    Err(ServiceError::InternalServerError)
+}
+
+/// **Update Config**
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/playout/config/1 -H "Content-Type: application/json" \
+/// -d { <CONFIG DATA> } -H 'Authorization: <TOKEN>'
+/// ```
+///
+/// Only for admins, the config contains paths and alert commands, which run on the server.
+#[put("/playout/config/{id}")]
+#[has_any_role("Role::Admin", type = "Role")]
+async fn update_playout_config(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<PlayoutConfig>,
+) -> Result<impl Responder, ServiceError> {
+    if let Ok(channel) = handles::select_channel(&id).await {
+        if let Ok(f) = std::fs::OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(&channel.config_path)
+        {
+            serde_yaml::to_writer(f, &data).unwrap();
+
+            // config contains passwords, so only the path goes to the audit trail
+            audit(&user, Some(*id), "config.update", channel.config_path).await;
+
+            return Ok("Update playout config success.");
+        } else {
+            return Err(ServiceError::InternalServerError);
+        };
+    };
+
+    Err(ServiceError::InternalServerError)
+}
+
+/// #### Text Presets
+///
+/// Text presets are made for sending text messages to the ffplayout engine, to overlay them as a lower third.
+///
+/// **Get all Presets**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/presets/ -H 'Content-Type: application/json' \
+/// -H 'Authorization: <TOKEN>'
+/// ```
+#[get("/presets/{id}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+async fn get_presets(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    if let Ok(presets) = handles::select_presets(*id).await {
+        return Ok(web::Json(presets));
+    }
+
+    Err(ServiceError::InternalServerError)
+}
+
+/// **Update Preset**
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/presets/1 -H 'Content-Type: application/json' \
+/// -d '{ "name": "<PRESET NAME>", "text": "<TEXT>", "x": "<X>", "y": "<Y>", "fontsize": 24, \
+/// "line_spacing": 4, "fontcolor": "#ffffff", "box": 1, "boxcolor": "#000000", "boxborderw": 4, "alpha": 1.0, "channel_id": 1 }' \
+/// -H 'Authorization: <TOKEN>'
+/// ```
+#[put("/presets/{id}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+async fn update_preset(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<TextPreset>,
+) -> Result<impl Responder, ServiceError> {
+    let preset = handles::select_preset(&id).await?;
+    check_channel(&user, &preset.channel_id, ChannelRole::Editor).await?;
+    check_channel(&user, &data.channel_id, ChannelRole::Editor).await?;
+
+    let channel_id = data.channel_id;
+    let summary = summarize(&*data);
+
+    if handles::update_preset(&id, data.into_inner()).await.is_ok() {
+        audit(&user, Some(channel_id), "preset.update", summary).await;
+
+        return Ok("Update Success");
+    }
+
+    Err(ServiceError::InternalServerError)
+}
+
+/// **Add new Preset**
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/presets/ -H 'Content-Type: application/json' \
+/// -d '{ "name": "<PRESET NAME>", "text": "TEXT>", "x": "<X>", "y": "<Y>", "fontsize": 24, \
+/// "line_spacing": 4, "fontcolor": "#ffffff", "box": 1, "boxcolor": "#000000", "boxborderw": 4, "alpha": 1.0, "channel_id": 1 }' \
+/// -H 'Authorization: <TOKEN>'
+/// ```
+#[post("/presets/")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+async fn add_preset(
+    user: web::ReqData<LoginUser>,
+    data: web::Json<TextPreset>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &data.channel_id, ChannelRole::Editor).await?;
+
+    let channel_id = data.channel_id;
+    let summary = summarize(&*data);
+
+    if handles::insert_preset(data.into_inner()).await.is_ok() {
+        audit(&user, Some(channel_id), "preset.create", summary).await;
+
         return Ok("Add preset Success");
     }
 
-    Err(ServiceError::InternalServerError)
+    Err(ServiceError::InternalServerError)
+}
+
+/// **Delete Preset**
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/presets/1 -H 'Content-Type: application/json' \
+/// -H 'Authorization: <TOKEN>'
+/// ```
+#[delete("/presets/{id}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+async fn delete_preset(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    let preset = handles::select_preset(&id).await?;
+    check_channel(&user, &preset.channel_id, ChannelRole::Editor).await?;
+
+    if handles::delete_preset(&id).await.is_ok() {
+        let summary = summarize(&preset.name);
+        audit(&user, Some(preset.channel_id), "preset.delete", summary).await;
+
+        return Ok("Delete preset Success");
+    }
+
+    Err(ServiceError::InternalServerError)
+}
+
+/// ### ffplayout controlling
+///
+/// here we communicate with the engine for:
+/// - jump to last or next clip
+/// - reset playlist state
+/// - get infos about current, next, last clip
+/// - send text to the engine, for overlaying it (as lower third etc.)
+///
+/// **Send Text to ffplayout**
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/text/ \
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>' \
+/// -d '{"text": "Hello from ffplayout", "x": "(w-text_w)/2", "y": "(h-text_h)/2", \
+///     "fontsize": "24", "line_spacing": "4", "fontcolor": "#ffffff", "box": "1", \
+///     "boxcolor": "#000000", "boxborderw": "4", "alpha": "1.0"}'
+/// ```
+#[post("/control/{id}/text/")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn send_text_message(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<HashMap<String, String>>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Operator).await?;
+
+    let summary = summarize(&data.get("text"));
+
+    match send_message(*id, data.into_inner()).await {
+        Ok(res) => {
+            audit(&user, Some(*id), "control.text", summary).await;
+
+            Ok(res.text().await.unwrap_or_else(|_| "Success".into()))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// **Control Playout**
+///
+/// - next
+/// - back
+/// - reset
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/playout/ -H 'Content-Type: application/json'
+/// -d '{ "command": "reset" }' -H 'Authorization: <TOKEN>'
+/// ```
+#[post("/control/{id}/playout/")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn control_playout(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    control: web::Json<Process>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Operator).await?;
+
+    match control_state(*id, control.command.clone()).await {
+        Ok(res) => {
+            let action = format!("control.{}", control.command);
+            audit(&user, Some(*id), &action, String::new()).await;
+
+            Ok(res.text().await.unwrap_or_else(|_| "Success".into()))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// **Get current Clip**
+///
+/// `sync_delta` is `null`, when no clip from a playlist was checked yet.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/control/1/media/current
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
+///     "jsonrpc": "2.0",
+///     "result": {
+///       "current_media": {
+///         "category": "",
+///         "duration": 154.2,
+///         "out": 154.2,
+///         "seek": 0.0,
+///         "source": "/opt/tv-media/clip.mp4"
+///       },
+///       "index": 39,
+///       "ingest_runs": false,
+///       "play_mode": "playlist",
+///       "played_sec": 67.80771999300123,
+///       "remaining_sec": 86.39228000699876,
+///       "start_sec": 24713.631999999998,
+///       "start_time": "06:51:53.631",
+///       "sync_delta": 0.12
+///     },
+///     "id": 1
+/// }
+/// ```
+#[get("/control/{id}/media/current")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn media_current(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    match media_info(*id, "current".into()).await {
+        Ok(res) => Ok(res.text().await.unwrap_or_else(|_| "Success".into())),
+        Err(e) => Err(e),
+    }
+}
+
+/// **Get next Clip**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/control/1/media/next/ -H 'Authorization: <TOKEN>'
+/// ```
+#[get("/control/{id}/media/next")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn media_next(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    match media_info(*id, "next".into()).await {
+        Ok(res) => Ok(res.text().await.unwrap_or_else(|_| "Success".into())),
+        Err(e) => Err(e),
+    }
+}
+
+/// **Get last Clip**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/control/1/media/last/
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// ```
+#[get("/control/{id}/media/last")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn media_last(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    match media_info(*id, "last".into()).await {
+        Ok(res) => Ok(res.text().await.unwrap_or_else(|_| "Success".into())),
+        Err(e) => Err(e),
+    }
+}
+
+/// #### ffplayout Process Control
+///
+/// Control ffplayout process, like:
+/// - start
+/// - stop
+/// - restart
+/// - status
+/// - info
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/process/
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// -d '{"command": "start"}'
+/// ```
+///
+/// **info** returns the state with pid, uptime in seconds, restarts and the last exit code:
+///
+/// ```JSON
+/// { "state": "active", "enabled": true, "pid": 1234, "uptime": 3600, "restarts": 0, "exit_code": null, "signal": null }
+/// ```
+#[post("/control/{id}/process/")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn process_control(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    proc: web::Json<Process>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Operator).await?;
+
+    if proc.command == "info" {
+        return Ok(HttpResponse::Ok().json(service_info(*id).await?));
+    }
+
+    let res = control_service(*id, &proc.command).await?;
+
+    if proc.command != "status" {
+        let action = format!("process.{}", proc.command);
+        audit(&user, Some(*id), &action, String::new()).await;
+    }
+
+    Ok(HttpResponse::Ok().body(res))
+}
+
+/// #### ffplayout Playlist Operations
+///
+/// **Get playlist**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/playlist/1?date=2022-06-20
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// ```
+#[get("/playlist/{id}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn get_playlist(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    obj: web::Query<DateObj>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    let playlist = read_playlist(*id, obj.date.clone()).await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header((ETAG, playlist_etag(&playlist)))
+        .json(playlist))
+}
+
+/// **Save playlist**
+///
+/// When the playlist exists already, the response header `ETag` from **Get playlist** must be send
+/// as `If-Match`, then the playlist is only saved when nobody else changed it in the meantime.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>' -H 'If-Match: "<ETAG>"'
+/// -- data "{<JSON playlist data>}"
+/// ```
+#[post("/playlist/{id}/")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn save_playlist(
+    req: HttpRequest,
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<JsonPlaylist>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let summary = format!("date: {}, clips: {}", data.date, data.program.len());
+    let (res, etag) = save_checked(*id, data.into_inner(), if_match(&req), &user).await?;
+    audit(&user, Some(*id), "playlist.save", summary).await;
+
+    Ok(HttpResponse::Ok().insert_header((ETAG, etag)).body(res))
+}
+
+/// **Generate Playlist**
+///
+/// A new playlist will be generated as background job, the result has the generated dates.
+/// For a date range use the generate job from [Jobs](#jobs).
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/generate/2022-06-20
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// ```
+///
+/// **Response:** the queued job, see [Jobs](#jobs).
+#[post("/playlist/{id}/generate/{date}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn gen_playlist(
+    params: web::Path<(i32, String)>,
+    user: web::ReqData<LoginUser>,
+) -> Result<HttpResponse, ServiceError> {
+    check_channel(&user, &params.0, ChannelRole::Editor).await?;
+
+    let task = JobTask::Generate {
+        start: params.1.clone(),
+        end: None,
+    };
+    let job = submit_job(params.0, Some(&user), &task).await?;
+    let summary = format!("date: {}", params.1);
+    audit(&user, Some(params.0), "playlist.generate", summary).await;
+
+    Ok(HttpResponse::Accepted().json(job))
+}
+
+/// **Delete Playlist**
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/playlist/1/2022-06-20
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// ```
+#[delete("/playlist/{id}/{date}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn del_playlist(
+    params: web::Path<(i32, String)>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &params.0, ChannelRole::Editor).await?;
+
+    delete_playlist(params.0, &params.1, &user).await?;
+    let summary = format!("date: {}", params.1);
+    audit(&user, Some(params.0), "playlist.delete", summary).await;
+
+    Ok(format!("Delete playlist from {} success!", params.1))
+}
+
+/// **Copy Playlist**
+///
+/// Copy the playlist from `date` to a single date or to a date range.
+/// Existing playlists are skipped, unless `overwrite` is true. With `move` the source gets deleted.
+/// `shift` moves the items by seconds, `probe` reads the durations from the media files again.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/copy
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// -d '{ "date": "2022-06-20", "target": ["2022-06-21", "2022-06-26"], "overwrite": false,
+///       "move": false, "shift": 0.0, "probe": false }'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// { "written": ["2022-06-21", "2022-06-22"], "skipped": ["2022-06-23"] }
+/// ```
+#[post("/playlist/{id}/copy")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn copy_playlists(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<CopyRequest>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let summary = summarize(&*data);
+    let report = copy_playlist(*id, data.into_inner(), &user).await?;
+    audit(&user, Some(*id), "playlist.copy", summary).await;
+
+    Ok(web::Json(report))
+}
+
+/// **Export Playlist**
+///
+/// Export playlist from date as extended M3U, CSV or printable HTML run-down,
+/// `format` can be: `m3u`, `csv` or `html`.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/playlist/1/export/2022-06-20?format=csv'
+/// -H 'Authorization: <TOKEN>'
+/// ```
+#[get("/playlist/{id}/export/{date}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn export_playlist_file(
+    params: web::Path<(i32, String)>,
+    user: web::ReqData<LoginUser>,
+    obj: web::Query<ExportObj>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &params.0, ChannelRole::Viewer).await?;
+
+    let (config, _) = playout_config(&params.0).await?;
+    let (date, format) = (params.1.clone(), obj.format);
+    let content = web::block(move || export_playlist(&config, &date, format))
+        .await?
+        .map_err(|e| ServiceError::NoContent(e.to_string()))?;
+    let mut response = HttpResponse::Ok();
+    response.content_type(format.mime_type());
+
+    if format != ExportFormat::Html {
+        response.insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"{}.{}\"",
+                params.1,
+                format.extension()
+            ),
+        ));
+    }
+
+    Ok(response.body(content))
+}
+
+/// **XMLTV EPG**
+///
+/// Program guide from the playlists, `from` and `to` are dates (YYYY-MM-DD).
+/// Without `from` the EPG begins today, without `to` it has only one day.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/epg/1?from=2022-06-20&to=2022-06-26'
+/// -H 'Authorization: <TOKEN>'
+/// ```
+#[get("/epg/{id}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn get_epg(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    obj: web::Query<EpgObj>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    let (config, channel) = playout_config(&id).await?;
+    let from = obj
+        .from
+        .clone()
+        .unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string());
+    let to = obj.to.clone().unwrap_or_else(|| from.clone());
+    let dates = date_range(&[from, to]).map_err(|e| ServiceError::BadRequest(e.to_string()))?;
+    let xml = web::block(move || xmltv(&config, &channel.name, &dates)).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/xml; charset=utf-8")
+        .body(xml))
+}
+
+/// #### Playlist Items
+///
+/// Edit single items from a playlist. All edit requests need the `ETag` from the last read
+/// in the `If-Match` header, otherwise they are rejected with status 428.
+/// When the playlist was changed in the meantime, the response has status 412.
+///
+/// Each response contains the playlist, the begin times from its items
+/// and the `gap` or `overrun` against the playlist length, all in seconds.
+/// The new `ETag` comes in the response header.
+///
+/// **Get Playlist Timing**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/playlist/1/2022-06-20/timing
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// { "playlist": { "channel": "Channel 1", "date": "2022-06-20", "program": [...] },
+///   "timing": { "day_start": 21600.0, "length": 86400.0, "total": 86000.0, "gap": 400.0,
+///     "overrun": 0.0, "items": [{ "index": 0, "begin": 21600.0, "begin_time": "06:00:00.000",
+///     "length": 30.0, "source": "/path/clip.mp4", "overrun": false }] } }
+/// ```
+#[get("/playlist/{id}/{date}/timing")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn get_playlist_timing(
+    params: web::Path<(i32, String)>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &params.0, ChannelRole::Viewer).await?;
+
+    let (result, etag) = read_timing(params.0, params.1.clone()).await?;
+
+    Ok(HttpResponse::Ok().insert_header((ETAG, etag)).json(result))
+}
+
+fn if_match(req: &HttpRequest) -> Option<&str> {
+    req.headers().get(IF_MATCH).and_then(|h| h.to_str().ok())
+}
+
+async fn edit_item(
+    req: &HttpRequest,
+    id: i32,
+    date: &str,
+    user: &LoginUser,
+    edit: PlaylistEdit,
+) -> Result<HttpResponse, ServiceError> {
+    check_channel(user, &id, ChannelRole::Editor).await?;
+
+    let summary = format!("date: {date}, {}", edit.summary());
+    let (result, etag) = edit_playlist(id, date.to_string(), edit, if_match(req), user).await?;
+    audit(user, Some(id), "playlist.edit", summary).await;
+
+    Ok(HttpResponse::Ok().insert_header((ETAG, etag)).json(result))
+}
+
+/// **Insert Item**
+///
+/// Without `index` the clip gets appended.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/2022-06-20/items
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>' -H 'If-Match: "<ETAG>"'
+/// -d '{ "index": 3, "clip": { "in": 0.0, "out": 30.0, "duration": 30.0, "source": "/path/clip.mp4" } }'
+/// ```
+#[post("/playlist/{id}/{date}/items")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn insert_item(
+    req: HttpRequest,
+    params: web::Path<(i32, String)>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<InsertObj>,
+) -> Result<impl Responder, ServiceError> {
+    let data = data.into_inner();
+    let edit = PlaylistEdit::Insert {
+        index: data.index,
+        clip: data.clip,
+    };
+
+    edit_item(&req, params.0, &params.1, &user, edit).await
+}
+
+/// **Replace Item**
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/playlist/1/2022-06-20/items/3
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>' -H 'If-Match: "<ETAG>"'
+/// -d '{ "in": 0.0, "out": 30.0, "duration": 30.0, "source": "/path/clip.mp4" }'
+/// ```
+#[put("/playlist/{id}/{date}/items/{index}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn replace_item(
+    req: HttpRequest,
+    params: web::Path<(i32, String, usize)>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<Media>,
+) -> Result<impl Responder, ServiceError> {
+    let edit = PlaylistEdit::Replace {
+        index: params.2,
+        clip: data.into_inner(),
+    };
+
+    edit_item(&req, params.0, &params.1, &user, edit).await
+}
+
+/// **Trim Item**
+///
+/// Change in and/or out point from an item.
+///
+/// ```BASH
+/// curl -X PATCH http://127.0.0.1:8787/api/playlist/1/2022-06-20/items/3
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>' -H 'If-Match: "<ETAG>"'
+/// -d '{ "in": 5.0, "out": 25.0 }'
+/// ```
+#[patch("/playlist/{id}/{date}/items/{index}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn trim_item(
+    req: HttpRequest,
+    params: web::Path<(i32, String, usize)>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<TrimObj>,
+) -> Result<impl Responder, ServiceError> {
+    let edit = PlaylistEdit::Trim {
+        index: params.2,
+        seek: data.seek,
+        out: data.out,
+    };
+
+    edit_item(&req, params.0, &params.1, &user, edit).await
+}
+
+/// **Move Item**
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/2022-06-20/items/3/move
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>' -H 'If-Match: "<ETAG>"'
+/// -d '{ "to": 0 }'
+/// ```
+#[post("/playlist/{id}/{date}/items/{index}/move")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn move_item(
+    req: HttpRequest,
+    params: web::Path<(i32, String, usize)>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<ItemMoveObj>,
+) -> Result<impl Responder, ServiceError> {
+    let edit = PlaylistEdit::Move {
+        index: params.2,
+        to: data.to,
+    };
+
+    edit_item(&req, params.0, &params.1, &user, edit).await
+}
+
+/// **Delete Item**
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/playlist/1/2022-06-20/items/3
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>' -H 'If-Match: "<ETAG>"'
+/// ```
+#[delete("/playlist/{id}/{date}/items/{index}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn delete_item(
+    req: HttpRequest,
+    params: web::Path<(i32, String, usize)>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    let edit = PlaylistEdit::Delete { index: params.2 };
+
+    edit_item(&req, params.0, &params.1, &user, edit).await
+}
+
+/// **Playlist Revisions**
+///
+/// Every save, generate, import, delete and restore keeps the playlist as revision.
+/// List the revisions from a date, newest first:
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/playlist/1/revisions?date=2022-06-20
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// [{ "id": 12, "channel_id": 1, "date": "2022-06-20", "timestamp": 1655726400,
+///    "user_id": 1, "username": "admin", "action": "save" }]
+/// ```
+#[get("/playlist/{id}/revisions")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn get_revisions(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    obj: web::Query<DateObj>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    let revisions = list_revisions(*id, &obj.date).await?;
+
+    Ok(web::Json(revisions))
+}
+
+/// **Diff Revisions**
+///
+/// Compare the clips from two revisions, the response holds the added and removed clips.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/playlist/1/revisions/diff?from=11&to=12'
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// { "from": 11, "to": 12, "changes": [{ "op": "removed", "index": 3,
+///    "clip": { "in": 0.0, "out": 30.0, "duration": 30.0, "source": "/path/clip.mp4" } }] }
+/// ```
+#[get("/playlist/{id}/revisions/diff")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn diff_revision(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    obj: web::Query<DiffObj>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    let diff = diff_revisions(*id, obj.from, obj.to).await?;
+
+    Ok(web::Json(diff))
 }
 
-/// **Delete Preset**
+/// **Restore Revision**
+///
+/// Write the playlist from the revision back to its date.
 ///
 /// ```BASH
-/// curl -X DELETE http://127.0.0.1:8787/api/presets/1 -H 'Content-Type: application/json' \
-/// -H 'Authorization: <TOKEN>'
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/revisions/11/restore
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
 /// ```
-#[delete("/presets/{id}")]
+#[post("/playlist/{id}/revisions/{revision}/restore")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-async fn delete_preset(id: web::Path<i32>) -> Result<impl Responder, ServiceError> {
-    if handles::delete_preset(&id).await.is_ok() {
-        return Ok("Delete preset Success");
-    }
+pub async fn restore_playlist(
+    params: web::Path<(i32, i32)>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &params.0, ChannelRole::Editor).await?;
 
-    Err(ServiceError::InternalServerError)
+    let res = restore_revision(params.0, params.1, &user).await?;
+    let summary = format!("revision: {}", params.1);
+    audit(&user, Some(params.0), "playlist.restore", summary).await;
+
+    Ok(res)
 }
 
-/// ### ffplayout controlling
+/// ### Log file
 ///
-/// here we communicate with the engine for:
-/// - jump to last or next clip
-/// - reset playlist state
-/// - get infos about current, next, last clip
-/// - send text to the engine, for overlaying it (as lower third etc.)
+/// **Read Log File**
 ///
-/// **Send Text to ffplayout**
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/log/1?date=2022-06-20
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// ```
+///
+/// Optional filters are: `level` (minimum level: debug, info, warning, error), `from` and `to`
+/// (time like `12:00:00` or date time like `2022-06-20 12:00:00`), `unit` (process unit,
+/// like decoder, encoder, ingest) and `search` (text). With `page` and `per_page` the result
+/// can be paginated, the header `X-Total-Count` contains the number of all matching entries.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/control/1/text/ \
-/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>' \
-/// -d '{"text": "Hello from ffplayout", "x": "(w-text_w)/2", "y": "(h-text_h)/2", \
-///     "fontsize": "24", "line_spacing": "4", "fontcolor": "#ffffff", "box": "1", \
-///     "boxcolor": "#000000", "boxborderw": "4", "alpha": "1.0"}'
+/// curl -X GET 'http://127.0.0.1:8787/api/log/1?level=warning&unit=decoder&page=1&per_page=100'
+/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
 /// ```
-#[post("/control/{id}/text/")]
+#[get("/log/{id}")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn send_text_message(
+pub async fn get_log(
     id: web::Path<i32>,
-    data: web::Json<HashMap<String, String>>,
+    user: web::ReqData<LoginUser>,
+    log: web::Query<LogFilter>,
+) -> Result<HttpResponse, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    let (text, total) = read_log_file(&id, &log).await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("X-Total-Count", total.to_string()))
+        .content_type("text/plain; charset=utf-8")
+        .body(text))
+}
+
+/// **Live Tail Log File**
+///
+/// Follow the current log file as server-sent events, every log entry is one event.
+/// The filters `level`, `unit` and `search` work like in the log file endpoint.
+///
+/// ```BASH
+/// curl -N -X GET 'http://127.0.0.1:8787/api/log/1/tail?level=info'
+/// -H 'Authorization: <TOKEN>'
+/// ```
+#[get("/log/{id}/tail")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn tail_log(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    log: web::Query<LogFilter>,
+) -> Result<HttpResponse, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    let stream = tail_log_file(&id, log.into_inner()).await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-cache"))
+        .content_type("text/event-stream")
+        .streaming(stream))
+}
+
+/// ### File Operations
+///
+/// **Get File/Folder List**
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/browse/ -H 'Content-Type: application/json'
+/// -d '{ "source": "/" }' -H 'Authorization: <TOKEN>'
+/// ```
+#[post("/file/{id}/browse/")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn file_browser(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<PathObject>,
 ) -> Result<impl Responder, ServiceError> {
-    match send_message(*id, data.into_inner()).await {
-        Ok(res) => Ok(res.text().await.unwrap_or_else(|_| "Success".into())),
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    match browser(*id, &data.into_inner()).await {
+        Ok(obj) => Ok(web::Json(obj)),
         Err(e) => Err(e),
     }
 }
 
-/// **Control Playout**
+/// **Get Thumbnail**
 ///
-/// - next
-/// - back
-/// - reset
+/// Poster frame (`kind=poster`, default) or preview strip with 10 small thumbnails side by side
+/// (`kind=sprite`) from a media file. The images are created on first request, or after upload.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/control/1/playout/ -H 'Content-Type: application/json'
-/// -d '{ "command": "reset" }' -H 'Authorization: <TOKEN>'
+/// curl -X GET 'http://127.0.0.1:8787/api/file/1/thumb?path=folder/clip.mp4&kind=sprite' \
+/// -H 'Authorization: <TOKEN>' --output sprite.jpg
 /// ```
-#[post("/control/{id}/playout/")]
+#[get("/file/{id}/thumb")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn control_playout(
+pub async fn get_thumbnail(
     id: web::Path<i32>,
-    control: web::Json<Process>,
+    user: web::ReqData<LoginUser>,
+    query: web::Query<ThumbQuery>,
+) -> Result<HttpResponse, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    let image = thumbnail(*id, &query).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("image/jpeg")
+        .insert_header(("Cache-Control", "private, max-age=3600"))
+        .body(image))
+}
+
+/// **Create Folder**
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/create-folder/ -H 'Content-Type: application/json'
+/// -d '{"source": "<FOLDER PATH>"}' -H 'Authorization: <TOKEN>'
+/// ```
+#[post("/file/{id}/create-folder/")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn add_dir(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<PathObject>,
+) -> Result<HttpResponse, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let res = create_directory(*id, &data).await?;
+    audit(&user, Some(*id), "file.create-folder", data.source.clone()).await;
+
+    Ok(res)
+}
+
+/// **Rename File**
+///
+/// Rename or move a file or folder. When it is used in playlists from today on, the request
+/// gives a `409 Conflict` with the affected dates. With `rewrite` the playlists get the new path,
+/// with `force` they stay as they are.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/rename/ -H 'Content-Type: application/json'
+/// -d '{"source": "<SOURCE>", "target": "<TARGET>", "rewrite": true}' -H 'Authorization: <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// { "source": "clip.mp4", "target": "intro.mp4", "rewritten": ["2022-06-20", "2022-06-21"] }
+/// ```
+#[post("/file/{id}/rename/")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn move_rename(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<RenameObject>,
 ) -> Result<impl Responder, ServiceError> {
-    match control_state(*id, control.command.clone()).await {
-        Ok(res) => Ok(res.text().await.unwrap_or_else(|_| "Success".into())),
-        Err(e) => Err(e),
-    }
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let report = rename_file(*id, &data, &user).await?;
+    let summary = format!(
+        "{} -> {}, rewritten: {}",
+        data.source,
+        data.target,
+        report.rewritten.join(", ")
+    );
+    audit(&user, Some(*id), "file.rename", summary).await;
+
+    Ok(web::Json(report))
 }
 
-/// **Get current Clip**
+/// **Get Playlist References**
+///
+/// Clips from playlists from today on, which use the file or a file inside the folder.
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/control/1/media/current
-/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// curl -X GET 'http://127.0.0.1:8787/api/file/1/references?path=<PATH>'
+/// -H 'Authorization: <TOKEN>'
 /// ```
 ///
 /// **Response:**
 ///
 /// ```JSON
-/// {
-///     "jsonrpc": "2.0",
-///     "result": {
-///       "current_media": {
-///         "category": "",
-///         "duration": 154.2,
-///         "out": 154.2,
-///         "seek": 0.0,
-///         "source": "/opt/tv-media/clip.mp4"
-///       },
-///       "index": 39,
-///       "play_mode": "playlist",
-///       "played_sec": 67.80771999300123,
-///       "remaining_sec": 86.39228000699876,
-///       "start_sec": 24713.631999999998,
-///       "start_time": "06:51:53.631"
-///     },
-///     "id": 1
-/// }
+/// [{ "date": "2022-06-20", "index": 4, "source": "/tv-media/folder/clip.mp4" }]
 /// ```
-#[get("/control/{id}/media/current")]
+#[get("/file/{id}/references")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn media_current(id: web::Path<i32>) -> Result<impl Responder, ServiceError> {
-    match media_info(*id, "current".into()).await {
-        Ok(res) => Ok(res.text().await.unwrap_or_else(|_| "Success".into())),
-        Err(e) => Err(e),
-    }
+pub async fn get_references(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    obj: web::Query<FileObj>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    let (config, _) = playout_config(&id).await?;
+    let (path, _, _) = norm_abs_path(&config.storage.path, &obj.path);
+    let references = web::block(move || playlist_references(&config.playlist.path, &path)).await?;
+
+    Ok(web::Json(references))
 }
 
-/// **Get next Clip**
+/// **Copy File/Folder**
+///
+/// Folders are copied with their content, `target` is an existing folder or the new name.
+/// The copy runs as background job, see [Jobs](#jobs).
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/control/1/media/next/ -H 'Authorization: <TOKEN>'
+/// curl -X POST http://127.0.0.1:8787/api/file/1/copy/ -H 'Content-Type: application/json'
+/// -d '{"source": "<SOURCE>", "target": "<TARGET>"}' -H 'Authorization: <TOKEN>'
 /// ```
-#[get("/control/{id}/media/next")]
+#[post("/file/{id}/copy/")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn media_next(id: web::Path<i32>) -> Result<impl Responder, ServiceError> {
-    match media_info(*id, "next".into()).await {
-        Ok(res) => Ok(res.text().await.unwrap_or_else(|_| "Success".into())),
-        Err(e) => Err(e),
-    }
+pub async fn copy_file(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<MoveObject>,
+) -> Result<HttpResponse, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let task = JobTask::Copy {
+        source: data.source.clone(),
+        target: data.target.clone(),
+    };
+    let job = submit_job(*id, Some(&user), &task).await?;
+    let summary = format!("{} -> {}", data.source, data.target);
+    audit(&user, Some(*id), "file.copy", summary).await;
+
+    Ok(HttpResponse::Accepted().json(job))
+}
+
+/// **Remove File/Folder**
+///
+/// The file or folder goes to the trash of the channel. Folders with content need `recursive`.
+/// Files which are used in playlists from today on give a `409 Conflict`, unless `force` is set.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/remove/ -H 'Content-Type: application/json'
+/// -d '{"source": "<SOURCE>", "recursive": false, "force": false}' -H 'Authorization: <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// { "id": "<TRASH ID>", "source": "folder/clip.mp4", "is_dir": false, "deleted": 1668410542,
+///   "username": "admin" }
+/// ```
+#[post("/file/{id}/remove/")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn remove(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<RemoveObject>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let item = remove_file_or_folder(*id, &data, &user).await?;
+    audit(
+        &user,
+        Some(*id),
+        "file.remove",
+        summarize(&data.into_inner()),
+    )
+    .await;
+
+    Ok(web::Json(item))
+}
+
+/// **Get Trash**
+///
+/// Deleted files and folders, newest first.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/file/1/trash -H 'Authorization: <TOKEN>'
+/// ```
+#[get("/file/{id}/trash")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn get_trash(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    Ok(web::Json(list_trash(*id).await?))
+}
+
+/// **Restore from Trash**
+///
+/// The item goes back to its original path, which must be free.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/trash/<TRASH ID>/restore
+/// -H 'Authorization: <TOKEN>'
+/// ```
+#[post("/file/{id}/trash/{item}/restore")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn restore_trash_item(
+    params: web::Path<(i32, String)>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, item) = params.into_inner();
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let item = restore_trash(id, &item).await?;
+    audit(&user, Some(id), "file.restore", item.source.clone()).await;
+
+    Ok(web::Json(item))
+}
+
+/// **Purge Trash Item**
+///
+/// Delete the item for ever.
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/file/1/trash/<TRASH ID> -H 'Authorization: <TOKEN>'
+/// ```
+#[delete("/file/{id}/trash/{item}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn purge_trash_item(
+    params: web::Path<(i32, String)>,
+    user: web::ReqData<LoginUser>,
+) -> Result<HttpResponse, ServiceError> {
+    let (id, item) = params.into_inner();
+    check_channel(&user, &id, ChannelRole::Operator).await?;
+
+    purge_trash(id, Some(item.clone())).await?;
+    audit(&user, Some(id), "file.purge", item).await;
+
+    Ok(HttpResponse::Ok().into())
+}
+
+/// **Empty Trash**
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/file/1/trash -H 'Authorization: <TOKEN>'
+/// ```
+#[delete("/file/{id}/trash")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+pub async fn empty_trash(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Operator).await?;
+
+    let count = purge_trash(*id, None).await?;
+    audit(&user, Some(*id), "file.purge", format!("items: {count}")).await;
+
+    Ok(web::Json(count))
 }
 
-/// **Get last Clip**
+/// **Upload File**
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/control/1/media/last/
-/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// curl -X POST http://127.0.0.1:8787/api/file/1/upload/ -H 'Authorization: <TOKEN>'
+/// -F "file=@file.mp4"
 /// ```
-#[get("/control/{id}/media/last")]
+#[put("/file/{id}/upload/")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn media_last(id: web::Path<i32>) -> Result<impl Responder, ServiceError> {
-    match media_info(*id, "last".into()).await {
-        Ok(res) => Ok(res.text().await.unwrap_or_else(|_| "Success".into())),
-        Err(e) => Err(e),
-    }
+async fn save_file(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    payload: Multipart,
+    obj: web::Query<FileObj>,
+) -> Result<HttpResponse, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let res = upload(*id, payload, &obj.path, false).await?;
+    audit(&user, Some(*id), "file.upload", obj.path.clone()).await;
+
+    Ok(res)
 }
 
-/// #### ffplayout Process Control
+/// **Start Resumable Upload**
 ///
-/// Control ffplayout process, like:
-/// - start
-/// - stop
-/// - restart
-/// - status
+/// For big files and unstable connections. Creates an upload session, `path` is the target folder,
+/// `checksum` (sha256 hex digest) is optional and gets verified after the last chunk.
+/// The file size is checked against `upload_limit` from the channel config.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/control/1/process/
-/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
-/// -d '{"command": "start"}'
+/// curl -X POST http://127.0.0.1:8787/api/file/1/uploads -H 'Content-Type: application/json'
+/// -d '{ "path": "masters", "name": "show.mxf", "size": 4294967296, "checksum": "<SHA256>" }'
+/// -H 'Authorization: <TOKEN>'
 /// ```
-#[post("/control/{id}/process/")]
+///
+/// **Response:**
+///
+/// ```JSON
+/// { "id": "<UPLOAD ID>", "offset": 0, "size": 4294967296, "complete": false }
+/// ```
+#[post("/file/{id}/uploads")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn process_control(
+async fn start_upload(
     id: web::Path<i32>,
-    proc: web::Json<Process>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<UploadRequest>,
 ) -> Result<impl Responder, ServiceError> {
-    control_service(*id, &proc.command).await
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let status = create_upload(*id, &data).await?;
+    audit(
+        &user,
+        Some(*id),
+        "file.upload.start",
+        summarize(&data.into_inner()),
+    )
+    .await;
+
+    Ok(web::Json(status))
 }
 
-/// #### ffplayout Playlist Operations
+/// **Get Upload Offset**
 ///
-/// **Get playlist**
+/// Bytes which are received so far, after a broken connection the upload continues from here.
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/playlist/1?date=2022-06-20
-/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// curl -X GET http://127.0.0.1:8787/api/file/1/uploads/<UPLOAD ID> -H 'Authorization: <TOKEN>'
 /// ```
-#[get("/playlist/{id}")]
+#[get("/file/{id}/uploads/{upload}")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn get_playlist(
-    id: web::Path<i32>,
-    obj: web::Query<DateObj>,
+async fn get_upload(
+    params: web::Path<(i32, String)>,
+    user: web::ReqData<LoginUser>,
 ) -> Result<impl Responder, ServiceError> {
-    match read_playlist(*id, obj.date.clone()).await {
-        Ok(playlist) => Ok(web::Json(playlist)),
-        Err(e) => Err(e),
-    }
+    check_channel(&user, &params.0, ChannelRole::Editor).await?;
+
+    Ok(web::Json(upload_status(params.0, &params.1).await?))
 }
 
-/// **Save playlist**
+/// **Upload Chunk**
+///
+/// Send the next chunk as raw body, the `Upload-Offset` header must match the received bytes.
+/// A wrong offset gives a `409 Conflict`. After the last chunk the file gets moved to its target.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/playlist/1/
-/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
-/// -- data "{<JSON playlist data>}"
+/// curl -X PATCH http://127.0.0.1:8787/api/file/1/uploads/<UPLOAD ID> -H 'Upload-Offset: 0'
+/// -H 'Content-Type: application/offset+octet-stream' --data-binary @chunk.bin
+/// -H 'Authorization: <TOKEN>'
 /// ```
-#[post("/playlist/{id}/")]
+#[patch("/file/{id}/uploads/{upload}")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn save_playlist(
-    id: web::Path<i32>,
-    data: web::Json<JsonPlaylist>,
-) -> Result<impl Responder, ServiceError> {
-    match write_playlist(*id, data.into_inner()).await {
-        Ok(res) => Ok(res),
-        Err(e) => Err(e),
+async fn upload_file_chunk(
+    req: HttpRequest,
+    params: web::Path<(i32, String)>,
+    user: web::ReqData<LoginUser>,
+    payload: web::Payload,
+) -> Result<HttpResponse, ServiceError> {
+    let (id, upload) = params.into_inner();
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let offset = match req
+        .headers()
+        .get("Upload-Offset")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.parse::<u64>().ok())
+    {
+        Some(o) => o,
+        None => {
+            return Err(ServiceError::BadRequest(
+                "Upload-Offset header is missing!".into(),
+            ))
+        }
+    };
+
+    let status = upload_chunk(id, &upload, offset, payload).await?;
+
+    if status.complete {
+        let summary = format!("upload: {upload}, size: {}", status.size);
+        audit(&user, Some(id), "file.upload", summary).await;
     }
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Upload-Offset", status.offset.to_string()))
+        .json(status))
 }
 
-/// **Generate Playlist**
-///
-/// A new playlist will be generated and response.
+/// **Cancel Upload**
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/playlist/1/generate/2022-06-20
-/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// curl -X DELETE http://127.0.0.1:8787/api/file/1/uploads/<UPLOAD ID> -H 'Authorization: <TOKEN>'
 /// ```
-#[get("/playlist/{id}/generate/{date}")]
+#[delete("/file/{id}/uploads/{upload}")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn gen_playlist(
+async fn remove_upload(
     params: web::Path<(i32, String)>,
-) -> Result<impl Responder, ServiceError> {
-    match generate_playlist(params.0, params.1.clone()).await {
-        Ok(playlist) => Ok(web::Json(playlist)),
-        Err(e) => Err(e),
-    }
+    user: web::ReqData<LoginUser>,
+) -> Result<HttpResponse, ServiceError> {
+    let (id, upload) = params.into_inner();
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    cancel_upload(id, &upload).await?;
+    audit(&user, Some(id), "upload.cancel", upload).await;
+
+    Ok(HttpResponse::Ok().into())
 }
 
-/// **Delete Playlist**
+/// **Conform File**
+///
+/// Transcode a media file from storage to the house format from the `conform` config:
+/// resolution, fps, codecs and a two-pass loudness normalization. It runs as background job,
+/// with `keep_original: false` the source file gets replaced. Other existing files are never
+/// overwritten, the conformed file gets a `_conform` suffix then. An original, which is used
+/// in playlists, is kept in any case; the job result tells it with `original_kept`.
 ///
 /// ```BASH
-/// curl -X DELETE http://127.0.0.1:8787/api/playlist/1/2022-06-20
-/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// curl -X POST http://127.0.0.1:8787/api/file/1/conform -H 'Content-Type: application/json'
+/// -d '{ "source": "masters/show.mxf" }' -H 'Authorization: <TOKEN>'
 /// ```
-#[delete("/playlist/{id}/{date}")]
+///
+/// **Response:** the queued job, see [Jobs](#jobs).
+#[post("/file/{id}/conform")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn del_playlist(
-    params: web::Path<(i32, String)>,
-) -> Result<impl Responder, ServiceError> {
-    match delete_playlist(params.0, &params.1).await {
-        Ok(_) => Ok(format!("Delete playlist from {} success!", params.1)),
-        Err(e) => Err(e),
-    }
+async fn conform_media(
+    id: web::Path<i32>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<PathObject>,
+) -> Result<HttpResponse, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let task = JobTask::Conform {
+        source: data.source.clone(),
+    };
+    let job = submit_job(*id, Some(&user), &task).await?;
+    audit(&user, Some(*id), "file.conform", data.source.clone()).await;
+
+    Ok(HttpResponse::Accepted().json(job))
 }
 
-/// ### Log file
+/// **Import playlist**
+///
+/// Import text/m3u, csv or xspf file and convert it to a playlist.
+/// In text/m3u files lines with leading "#" will be ignore, durations and titles come from `#EXTINF`.
+/// CSV files need a header line with a `source` column.
 ///
-/// **Read Log Life**
+/// The import runs as background job, the response is the queued job.
+/// Lines with errors are skipped, the `result` from the finished job lists them:
+///
+/// ```JSON
+/// {"message": "Write playlist from 2022-06-20 success! 1 line(s) skipped.", "items": 24,
+///  "errors": [{"line": 7, "message": "File not exists: /path/to/clip.mp4"}]}
+/// ```
 ///
 /// ```BASH
-/// curl -X Get http://127.0.0.1:8787/api/log/1
-/// -H 'Content-Type: application/json' -H 'Authorization: <TOKEN>'
+/// curl -X POST http://127.0.0.1:8787/api/file/1/import/ -H 'Authorization: <TOKEN>'
+/// -F "file=@list.m3u"
 /// ```
-#[get("/log/{id}")]
+#[put("/file/{id}/import/")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn get_log(
+async fn import_playlist(
     id: web::Path<i32>,
-    log: web::Query<DateObj>,
-) -> Result<impl Responder, ServiceError> {
-    read_log_file(&id, &log.date).await
+    user: web::ReqData<LoginUser>,
+    payload: Multipart,
+    obj: web::Query<ImportObj>,
+) -> Result<HttpResponse, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let file = Path::new(&obj.file).file_name().unwrap_or_default();
+    // the file waits in the job queue, so it needs a unique name
+    let path = env::temp_dir()
+        .join(format!(
+            "ffplayout_{}_{}",
+            Local::now().timestamp_millis(),
+            file.to_string_lossy()
+        ))
+        .to_string_lossy()
+        .to_string();
+
+    upload(*id, payload, &path, true).await?;
+
+    let task = JobTask::Import {
+        file: path.clone(),
+        date: obj.date.clone(),
+    };
+    let job = match submit_job(*id, Some(&user), &task).await {
+        Ok(job) => job,
+        Err(e) => {
+            fs::remove_file(path)?;
+            return Err(e);
+        }
+    };
+
+    let summary = format!("file: {}, date: {}, job: {}", obj.file, obj.date, job.id);
+    audit(&user, Some(*id), "playlist.import", summary).await;
+
+    Ok(HttpResponse::Accepted().json(job))
 }
 
-/// ### File Operations
+/// ### Media Library
 ///
-/// **Get File/Folder List**
+/// **Search Library**
+///
+/// Find indexed media files. Optional filters are: `search` (part of file name), `tag`, `category`,
+/// `folder` (relative to storage), `min_duration` and `max_duration` (seconds),
+/// `sort` (`name`, `duration` or `last_played`, with leading `-` for descending order),
+/// `limit` (default 100) and `offset`.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/file/1/browse/ -H 'Content-Type: application/json'
-/// -d '{ "source": "/" }' -H 'Authorization: <TOKEN>'
+/// curl -X GET 'http://127.0.0.1:8787/api/library/1?tag=news&max_duration=600&sort=-duration' \
+/// -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/file/{id}/browse/")]
+///
+/// **Response:**
+///
+/// ```JSON
+/// [{ "id": 12, "channel_id": 1, "path": "/tv-media/news/clip.mp4", "name": "clip.mp4",
+///    "size": 31457280, "modified": 1660000000, "duration": 154.2, "video_codec": "h264",
+///    "audio_codec": "aac", "width": 1920, "height": 1080, "category": "", "tags": "news,sport",
+///    "last_played": 1660003600 }]
+/// ```
+#[get("/library/{id}")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn file_browser(
+async fn get_library(
     id: web::Path<i32>,
-    data: web::Json<PathObject>,
+    user: web::ReqData<LoginUser>,
+    filter: web::Query<MediaFilter>,
 ) -> Result<impl Responder, ServiceError> {
-    match browser(*id, &data.into_inner()).await {
-        Ok(obj) => Ok(web::Json(obj)),
-        Err(e) => Err(e),
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    let (config, _) = playout_config(&id).await?;
+    let mut filter = filter.into_inner();
+
+    if let Some(folder) = &filter.folder {
+        let (path, _, _) = norm_abs_path(&config.storage.path, folder);
+        filter.folder = Some(format!("{}/", path.to_string_lossy().trim_end_matches('/')));
     }
+
+    let media = handles::select_media(&id, &filter).await?;
+
+    Ok(web::Json(media))
 }
 
-/// **Create Folder**
+/// **Get Library Tags**
+///
+/// All tags with the number of files which use them.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/file/1/create-folder/ -H 'Content-Type: application/json'
-/// -d '{"source": "<FOLDER PATH>"}' -H 'Authorization: <TOKEN>'
+/// curl -X GET http://127.0.0.1:8787/api/library/1/tags -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/file/{id}/create-folder/")]
+#[get("/library/{id}/tags")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn add_dir(
+async fn get_library_tags(
     id: web::Path<i32>,
-    data: web::Json<PathObject>,
-) -> Result<HttpResponse, ServiceError> {
-    create_directory(*id, &data.into_inner()).await
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
+
+    Ok(web::Json(library_tags(*id).await?))
 }
 
-/// **Rename File**
+/// **Scan Library**
+///
+/// Index new and changed files from storage and remove missing files from library.
+/// Normally the file watcher does this, a scan is only needed after changes outside of its view.
+///
+/// The scan runs as background job, the `result` from the finished job has the counts:
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/file/1/rename/ -H 'Content-Type: application/json'
-/// -d '{"source": "<SOURCE>", "target": "<TARGET>"}' -H 'Authorization: <TOKEN>'
+/// curl -X POST http://127.0.0.1:8787/api/library/1/scan -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/file/{id}/rename/")]
+///
+/// **Result:**
+///
+/// ```JSON
+/// { "added": 3, "updated": 1, "removed": 0 }
+/// ```
+#[post("/library/{id}/scan")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn move_rename(
+async fn scan_media(
     id: web::Path<i32>,
-    data: web::Json<MoveObject>,
-) -> Result<impl Responder, ServiceError> {
-    match rename_file(*id, &data.into_inner()).await {
-        Ok(obj) => Ok(web::Json(obj)),
-        Err(e) => Err(e),
-    }
+    user: web::ReqData<LoginUser>,
+) -> Result<HttpResponse, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let job = submit_job(*id, Some(&user), &JobTask::Scan).await?;
+    audit(&user, Some(*id), "library.scan", format!("job: {}", job.id)).await;
+
+    Ok(HttpResponse::Accepted().json(job))
 }
 
-/// **Remove File/Folder**
+/// **Update Media Category and Tags**
+///
+/// Fields which are not given stay untouched, tags get stored in lower case.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/file/1/remove/ -H 'Content-Type: application/json'
-/// -d '{"source": "<SOURCE>"}' -H 'Authorization: <TOKEN>'
+/// curl -X PUT http://127.0.0.1:8787/api/library/1/media/12 -H 'Content-Type: application/json' \
+/// -d '{ "category": "news", "tags": ["News", "Sport"] }' -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/file/{id}/remove/")]
+#[put("/library/{id}/media/{media_id}")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-pub async fn remove(
-    id: web::Path<i32>,
-    data: web::Json<PathObject>,
+async fn update_media_item(
+    params: web::Path<(i32, i32)>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<MediaUpdate>,
 ) -> Result<impl Responder, ServiceError> {
-    match remove_file_or_folder(*id, &data.into_inner().source).await {
-        Ok(obj) => Ok(web::Json(obj)),
-        Err(e) => Err(e),
-    }
+    let (id, media_id) = params.into_inner();
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let media = update_media(id, media_id, &data).await?;
+    let summary = format!("media: {}, {}", media.path, summarize(&data.into_inner()));
+    audit(&user, Some(id), "library.update", summary).await;
+
+    Ok(web::Json(media))
 }
 
-/// **Upload File**
+/// ### Jobs
+///
+/// Long running work runs as background job: playlist generation (`generate`), playlist
+/// import (`import`), transcoding to the house format (`conform`) and library scans (`scan`).
+/// The jobs from a channel run one after another, conform jobs in their own queue.
+/// `status` is `queued`, `running`, `done`, `failed` or `cancelled`,
+/// `progress` is in percent and `result` holds the outcome as json string.
+///
+/// **Add Job**
+///
+/// Imports need a file upload, they are only started from the import endpoint.
+/// A generate job covers at most 366 days.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/file/1/upload/ -H 'Authorization: <TOKEN>'
-/// -F "file=@file.mp4"
+/// curl -X POST http://127.0.0.1:8787/api/jobs/1 -H 'Content-Type: application/json'
+/// -d '{ "kind": "generate", "start": "2022-06-20", "end": "2022-06-26" }'
+/// -H 'Authorization: <TOKEN>'
 /// ```
-#[put("/file/{id}/upload/")]
+///
+/// **Response:**
+///
+/// ```JSON
+/// { "id": 7, "channel_id": 1, "kind": "generate",
+///   "task": "{\"kind\":\"generate\",\"start\":\"2022-06-20\",\"end\":\"2022-06-26\"}",
+///   "status": "queued", "progress": 0.0, "result": "", "message": "", "user_id": 1,
+///   "username": "admin", "created": 1668410542, "started": null, "finished": null }
+/// ```
+#[post("/jobs/{id}")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-async fn save_file(
+async fn add_job(
     id: web::Path<i32>,
-    payload: Multipart,
-    obj: web::Query<FileObj>,
+    user: web::ReqData<LoginUser>,
+    data: web::Json<JobTask>,
 ) -> Result<HttpResponse, ServiceError> {
-    upload(*id, payload, &obj.path, false).await
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    if let JobTask::Import { .. } = *data {
+        return Err(ServiceError::BadRequest(
+            "Imports are started from the import endpoint!".into(),
+        ));
+    }
+
+    let job = submit_job(*id, Some(&user), &data).await?;
+    audit(&user, Some(*id), "job.add", summarize(&data.into_inner())).await;
+
+    Ok(HttpResponse::Accepted().json(job))
 }
 
-/// **Import playlist**
+/// **Get Jobs**
 ///
-/// Import text/m3u file and convert it to a playlist
-/// lines with leading "#" will be ignore
+/// Newest jobs first, optional filters are `kind`, `status` and `limit` (default 50).
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/file/1/import/ -H 'Authorization: <TOKEN>'
-/// -F "file=@list.m3u"
+/// curl -X GET 'http://127.0.0.1:8787/api/jobs/1?status=running' -H 'Authorization: <TOKEN>'
 /// ```
-#[put("/file/{id}/import/")]
+#[get("/jobs/{id}")]
 #[has_any_role("Role::Admin", "Role::User", type = "Role")]
-async fn import_playlist(
+async fn get_jobs(
     id: web::Path<i32>,
-    payload: Multipart,
-    obj: web::Query<ImportObj>,
-) -> Result<HttpResponse, ServiceError> {
-    let file = Path::new(&obj.file).file_name().unwrap_or_default();
-    let path = env::temp_dir().join(&file).to_string_lossy().to_string();
-    let (config, _) = playout_config(&id).await?;
-    let channel = handles::select_channel(&id).await?;
+    user: web::ReqData<LoginUser>,
+    filter: web::Query<JobFilter>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &id, ChannelRole::Viewer).await?;
 
-    upload(*id, payload, &path, true).await?;
-    import_file(&config, &obj.date, Some(channel.name), &path)?;
+    Ok(web::Json(handles::select_jobs(&id, &filter).await?))
+}
+
+/// **Get Job**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/jobs/1/7 -H 'Authorization: <TOKEN>'
+/// ```
+#[get("/jobs/{id}/{job}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+async fn get_job_status(
+    params: web::Path<(i32, i64)>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    check_channel(&user, &params.0, ChannelRole::Viewer).await?;
 
-    fs::remove_file(path)?;
+    Ok(web::Json(get_job(params.0, params.1).await?))
+}
 
-    Ok(HttpResponse::Ok().into())
+/// **Cancel Job**
+///
+/// Queued jobs are cancelled at once, running jobs stop at their next progress step.
+/// Running imports and scans can not be cancelled.
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/jobs/1/7 -H 'Authorization: <TOKEN>'
+/// ```
+#[delete("/jobs/{id}/{job}")]
+#[has_any_role("Role::Admin", "Role::User", type = "Role")]
+async fn remove_job(
+    params: web::Path<(i32, i64)>,
+    user: web::ReqData<LoginUser>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, job) = params.into_inner();
+    check_channel(&user, &id, ChannelRole::Editor).await?;
+
+    let job = cancel_job(id, job).await?;
+    audit(&user, Some(id), "job.cancel", format!("job: {}", job.id)).await;
+
+    Ok(web::Json(job))
 }
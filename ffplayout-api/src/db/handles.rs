@@ -7,7 +7,13 @@ use rand::{distributions::Alphanumeric, Rng};
 use simplelog::*;
 use sqlx::{migrate::MigrateDatabase, sqlite::SqliteQueryResult, Pool, Sqlite, SqlitePool};
 
-use crate::db::models::{Channel, TextPreset, User};
+use crate::db::{
+    migrations::run_migrations,
+    models::{
+        AuditEntry, AuditFilter, Channel, Job, JobFilter, MediaFilter, MediaItem, PlaylistRevision,
+        TextPreset, Token, User, UserChannel, UserFields, UserInfo,
+    },
+};
 use crate::utils::{db_path, local_utc_offset, GlobalSettings};
 
 #[derive(Debug, sqlx::FromRow)]
@@ -15,78 +21,16 @@ struct Role {
     name: String,
 }
 
-async fn create_schema() -> Result<SqliteQueryResult, sqlx::Error> {
-    let conn = connection().await?;
-    let query = "PRAGMA foreign_keys = ON;
-    CREATE TABLE IF NOT EXISTS global
-        (
-            id                       INTEGER PRIMARY KEY AUTOINCREMENT,
-            secret                   TEXT NOT NULL,
-            UNIQUE(secret)
-        );
-    CREATE TABLE IF NOT EXISTS roles
-        (
-            id                       INTEGER PRIMARY KEY AUTOINCREMENT,
-            name                     TEXT NOT NULL,
-            UNIQUE(name)
-        );
-    CREATE TABLE IF NOT EXISTS channels
-        (
-            id                       INTEGER PRIMARY KEY AUTOINCREMENT,
-            name                     TEXT NOT NULL,
-            preview_url              TEXT NOT NULL,
-            config_path              TEXT NOT NULL,
-            extra_extensions         TEXT NOT NULL,
-            service                  TEXT NOT NULL,
-            UNIQUE(name, service)
-        );
-    CREATE TABLE IF NOT EXISTS presets
-        (
-            id                       INTEGER PRIMARY KEY AUTOINCREMENT,
-            name                     TEXT NOT NULL,
-            text                     TEXT NOT NULL,
-            x                        TEXT NOT NULL,
-            y                        TEXT NOT NULL,
-            fontsize                 TEXT NOT NULL,
-            line_spacing             TEXT NOT NULL,
-            fontcolor                TEXT NOT NULL,
-            box                      TEXT NOT NULL,
-            boxcolor                 TEXT NOT NULL,
-            boxborderw               TEXT NOT NULL,
-            alpha                    TEXT NOT NULL,
-            channel_id               INTEGER NOT NULL DEFAULT 1,
-            FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE SET NULL ON DELETE SET NULL,
-            UNIQUE(name)
-        );
-    CREATE TABLE IF NOT EXISTS user
-        (
-            id                       INTEGER PRIMARY KEY AUTOINCREMENT,
-            mail                     TEXT NOT NULL,
-            username                 TEXT NOT NULL,
-            password                 TEXT NOT NULL,
-            salt                     TEXT NOT NULL,
-            role_id                  INTEGER NOT NULL DEFAULT 2,
-            channel_id               INTEGER NOT NULL DEFAULT 1,
-            FOREIGN KEY (role_id)    REFERENCES roles (id) ON UPDATE SET NULL ON DELETE SET NULL,
-            FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE SET NULL ON DELETE SET NULL,
-            UNIQUE(mail, username)
-        );";
-    let result = sqlx::query(query).execute(&conn).await;
-    conn.close().await;
-
-    result
-}
-
 pub async fn db_init(domain: Option<String>) -> Result<&'static str, Box<dyn std::error::Error>> {
     let db_path = db_path()?;
 
     if !Sqlite::database_exists(&db_path).await.unwrap_or(false) {
         Sqlite::create_database(&db_path).await.unwrap();
-        match create_schema().await {
-            Ok(_) => info!("Database created Successfully"),
-            Err(e) => panic!("{e}"),
-        }
+        info!("Database created Successfully");
     }
+
+    run_migrations().await?;
+
     let secret: String = rand::thread_rng()
         .sample_iter(&Alphanumeric)
         .take(80)
@@ -187,13 +131,14 @@ pub async fn update_channel(id: i32, channel: Channel) -> Result<SqliteQueryResu
 pub async fn insert_channel(channel: Channel) -> Result<Channel, sqlx::Error> {
     let conn = connection().await?;
 
-    let query = "INSERT INTO channels (name, preview_url, config_path, extra_extensions, service) VALUES($1, $2, $3, $4, $5)";
+    let query = "INSERT INTO channels (name, preview_url, config_path, extra_extensions, service, provisioned) VALUES($1, $2, $3, $4, $5, $6)";
     let result = sqlx::query(query)
         .bind(channel.name)
         .bind(channel.preview_url)
         .bind(channel.config_path)
         .bind(channel.extra_extensions)
         .bind(channel.service)
+        .bind(channel.provisioned)
         .execute(&conn)
         .await?;
     let new_channel: Channel = sqlx::query_as("SELECT * FROM channels WHERE id = $1")
@@ -208,6 +153,13 @@ pub async fn insert_channel(channel: Channel) -> Result<Channel, sqlx::Error> {
 pub async fn delete_channel(id: &i32) -> Result<SqliteQueryResult, sqlx::Error> {
     let conn = connection().await?;
 
+    for table in ["user_channels", "playlist_revisions", "media", "jobs"] {
+        sqlx::query(&format!("DELETE FROM {table} WHERE channel_id = $1"))
+            .bind(id)
+            .execute(&conn)
+            .await?;
+    }
+
     let query = "DELETE FROM channels WHERE id = $1";
     let result: SqliteQueryResult = sqlx::query(query).bind(id).execute(&conn).await?;
     conn.close().await;
@@ -215,6 +167,38 @@ pub async fn delete_channel(id: &i32) -> Result<SqliteQueryResult, sqlx::Error>
     Ok(result)
 }
 
+/// Channels which the supervisor starts together with the API.
+pub async fn select_autostart_channels() -> Result<Vec<i32>, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT id FROM channels WHERE autostart = 1";
+    let result: Vec<(i32,)> = sqlx::query_as(query).fetch_all(&conn).await?;
+    conn.close().await;
+
+    Ok(result.into_iter().map(|(id,)| id).collect())
+}
+
+pub async fn update_autostart(id: &i32, autostart: bool) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "UPDATE channels SET autostart = $2 WHERE id = $1";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(id)
+        .bind(autostart)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn select_role_id(name: &str) -> Result<i32, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT id FROM roles WHERE name = $1";
+    let (id,): (i32,) = sqlx::query_as(query).bind(name).fetch_one(&conn).await?;
+    conn.close().await;
+
+    Ok(id)
+}
+
 pub async fn select_role(id: &i32) -> Result<String, sqlx::Error> {
     let conn = connection().await?;
     let query = "SELECT name FROM roles WHERE id = $1";
@@ -226,7 +210,8 @@ pub async fn select_role(id: &i32) -> Result<String, sqlx::Error> {
 
 pub async fn select_login(user: &str) -> Result<User, sqlx::Error> {
     let conn = connection().await?;
-    let query = "SELECT id, mail, username, password, salt, role_id FROM user WHERE username = $1";
+    let query =
+        "SELECT id, mail, username, password, salt, role_id, disabled FROM user WHERE username = $1";
     let result: User = sqlx::query_as(query).bind(user).fetch_one(&conn).await?;
     conn.close().await;
 
@@ -242,6 +227,26 @@ pub async fn select_user(user: &str) -> Result<User, sqlx::Error> {
     Ok(result)
 }
 
+pub async fn select_user_by_id(id: &i32) -> Result<User, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT id, mail, username, role_id, channel_id, disabled, tokens_valid_after
+        FROM user WHERE id = $1";
+    let result: User = sqlx::query_as(query).bind(id).fetch_one(&conn).await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn select_users() -> Result<Vec<UserInfo>, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT user.id, user.mail, user.username, roles.name AS role, user.disabled
+        FROM user LEFT JOIN roles ON roles.id = user.role_id ORDER BY user.id";
+    let result: Vec<UserInfo> = sqlx::query_as(query).fetch_all(&conn).await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
 pub async fn insert_user(user: User) -> Result<SqliteQueryResult, sqlx::Error> {
     let conn = connection().await?;
     let salt = SaltString::generate(&mut OsRng);
@@ -249,25 +254,158 @@ pub async fn insert_user(user: User) -> Result<SqliteQueryResult, sqlx::Error> {
         .hash_password(user.password.clone().as_bytes(), &salt)
         .unwrap();
 
-    let query =
-        "INSERT INTO user (mail, username, password, salt, role_id) VALUES($1, $2, $3, $4, $5)";
+    let channel_id = user.channel_id.unwrap_or(1);
+
+    let query = "INSERT INTO user (mail, username, password, salt, role_id, channel_id)
+        VALUES($1, $2, $3, $4, $5, $6)";
     let result = sqlx::query(query)
         .bind(user.mail)
         .bind(user.username)
         .bind(password_hash.to_string())
         .bind(salt.to_string())
         .bind(user.role_id)
+        .bind(channel_id)
+        .execute(&conn)
+        .await?;
+
+    // channel permission only when the request asks for it
+    if let (Some(channel_id), Some(role)) = (user.channel_id, user.channel_role) {
+        sqlx::query(
+            "INSERT OR IGNORE INTO user_channels (user_id, channel_id, role) VALUES($1, $2, $3)",
+        )
+        .bind(result.last_insert_rowid())
+        .bind(channel_id)
+        .bind(role)
+        .execute(&conn)
+        .await?;
+    }
+
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn update_user(id: i32, fields: &UserFields) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "UPDATE user SET mail = COALESCE($2, mail), password = COALESCE($3, password),
+        salt = COALESCE($4, salt), role_id = COALESCE($5, role_id), disabled = COALESCE($6, disabled)
+        WHERE id = $1";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(id)
+        .bind(&fields.mail)
+        .bind(&fields.password)
+        .bind(&fields.salt)
+        .bind(fields.role_id)
+        .bind(fields.disabled)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Delete refresh and API tokens from user, access tokens which are issued before `since` are
+/// rejected from now on.
+pub async fn revoke_user_tokens(id: &i32, since: i64) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+
+    sqlx::query("UPDATE user SET tokens_valid_after = $2 WHERE id = $1")
+        .bind(id)
+        .bind(since)
         .execute(&conn)
         .await?;
+
+    let query = "DELETE FROM tokens WHERE user_id = $1";
+    let result: SqliteQueryResult = sqlx::query(query).bind(id).execute(&conn).await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn delete_user(id: &i32) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+
+    sqlx::query("DELETE FROM user_channels WHERE user_id = $1")
+        .bind(id)
+        .execute(&conn)
+        .await?;
+
+    sqlx::query("DELETE FROM tokens WHERE user_id = $1")
+        .bind(id)
+        .execute(&conn)
+        .await?;
+
+    let query = "DELETE FROM user WHERE id = $1";
+    let result: SqliteQueryResult = sqlx::query(query).bind(id).execute(&conn).await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn select_user_channel(
+    user_id: &i32,
+    channel_id: &i32,
+) -> Result<UserChannel, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT * FROM user_channels WHERE user_id = $1 AND channel_id = $2";
+    let result: UserChannel = sqlx::query_as(query)
+        .bind(user_id)
+        .bind(channel_id)
+        .fetch_one(&conn)
+        .await?;
     conn.close().await;
 
     Ok(result)
 }
 
-pub async fn update_user(id: i32, fields: String) -> Result<SqliteQueryResult, sqlx::Error> {
+pub async fn select_user_channels(user_id: &i32) -> Result<Vec<UserChannel>, sqlx::Error> {
     let conn = connection().await?;
-    let query = format!("UPDATE user SET {fields} WHERE id = $1");
-    let result: SqliteQueryResult = sqlx::query(&query).bind(id).execute(&conn).await?;
+    let query = "SELECT * FROM user_channels WHERE user_id = $1";
+    let result: Vec<UserChannel> = sqlx::query_as(query).bind(user_id).fetch_all(&conn).await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn upsert_user_channel(
+    user_id: &i32,
+    channel_id: &i32,
+    role: &str,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "INSERT INTO user_channels (user_id, channel_id, role) VALUES($1, $2, $3)
+        ON CONFLICT(user_id, channel_id) DO UPDATE SET role = $3";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(user_id)
+        .bind(channel_id)
+        .bind(role)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn delete_user_channel(
+    user_id: &i32,
+    channel_id: &i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "DELETE FROM user_channels WHERE user_id = $1 AND channel_id = $2";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(user_id)
+        .bind(channel_id)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn select_preset(id: &i32) -> Result<TextPreset, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT * FROM presets WHERE id = $1";
+    let result: TextPreset = sqlx::query_as(query).bind(id).fetch_one(&conn).await?;
     conn.close().await;
 
     Ok(result)
@@ -340,3 +478,534 @@ pub async fn delete_preset(id: &i32) -> Result<SqliteQueryResult, sqlx::Error> {
 
     Ok(result)
 }
+
+pub async fn insert_token(token: &Token) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "INSERT INTO tokens (jti, user_id, kind, name, scope, created, expires)
+        VALUES($1, $2, $3, $4, $5, $6, $7)";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(&token.jti)
+        .bind(token.user_id)
+        .bind(&token.kind)
+        .bind(&token.name)
+        .bind(&token.scope)
+        .bind(token.created)
+        .bind(token.expires)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn select_token(jti: &str) -> Result<Token, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT * FROM tokens WHERE jti = $1";
+    let result: Token = sqlx::query_as(query).bind(jti).fetch_one(&conn).await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn select_tokens(kind: &str) -> Result<Vec<Token>, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT * FROM tokens WHERE kind = $1 ORDER BY id";
+    let result: Vec<Token> = sqlx::query_as(query).bind(kind).fetch_all(&conn).await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn delete_token(id: &i32) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "DELETE FROM tokens WHERE id = $1";
+    let result: SqliteQueryResult = sqlx::query(query).bind(id).execute(&conn).await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn delete_token_by_jti(jti: &str) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "DELETE FROM tokens WHERE jti = $1";
+    let result: SqliteQueryResult = sqlx::query(query).bind(jti).execute(&conn).await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Put token to the revocation list and remove expired entries from it.
+pub async fn insert_revoked(jti: &str, expires: i64) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+
+    sqlx::query("DELETE FROM revoked_tokens WHERE expires < strftime('%s', 'now')")
+        .execute(&conn)
+        .await?;
+
+    let query = "INSERT OR IGNORE INTO revoked_tokens (jti, expires) VALUES($1, $2)";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(jti)
+        .bind(expires)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn is_revoked(jti: &str) -> Result<bool, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT jti FROM revoked_tokens WHERE jti = $1";
+    let result: Option<(String,)> = sqlx::query_as(query)
+        .bind(jti)
+        .fetch_optional(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result.is_some())
+}
+
+pub async fn insert_audit(entry: &AuditEntry) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "INSERT INTO audit (timestamp, user_id, username, channel_id, action, summary)
+        VALUES($1, $2, $3, $4, $5, $6)";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(entry.timestamp)
+        .bind(entry.user_id)
+        .bind(&entry.username)
+        .bind(entry.channel_id)
+        .bind(&entry.action)
+        .bind(&entry.summary)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Select audit entries, newest first. Filters with `None` are ignored.
+pub async fn select_audit(filter: &AuditFilter) -> Result<Vec<AuditEntry>, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT * FROM audit
+        WHERE ($1 IS NULL OR user_id = $1)
+            AND ($2 IS NULL OR username = $2)
+            AND ($3 IS NULL OR channel_id = $3)
+            AND ($4 IS NULL OR action LIKE $4 || '%')
+            AND ($5 IS NULL OR timestamp >= $5)
+            AND ($6 IS NULL OR timestamp <= $6)
+        ORDER BY timestamp DESC, id DESC LIMIT $7 OFFSET $8";
+    let result: Vec<AuditEntry> = sqlx::query_as(query)
+        .bind(filter.user_id)
+        .bind(&filter.username)
+        .bind(filter.channel_id)
+        .bind(&filter.action)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(filter.limit.unwrap_or(100))
+        .bind(filter.offset.unwrap_or(0))
+        .fetch_all(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn insert_revision(
+    revision: &PlaylistRevision,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "INSERT INTO playlist_revisions (channel_id, date, timestamp, user_id, username, action, data)
+        VALUES($1, $2, $3, $4, $5, $6, $7)";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(revision.channel_id)
+        .bind(&revision.date)
+        .bind(revision.timestamp)
+        .bind(revision.user_id)
+        .bind(&revision.username)
+        .bind(&revision.action)
+        .bind(&revision.data)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Select revisions from playlist date, newest first and without playlist data.
+pub async fn select_revisions(
+    channel_id: &i32,
+    date: &str,
+) -> Result<Vec<PlaylistRevision>, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT id, channel_id, date, timestamp, user_id, username, action, '' AS data
+        FROM playlist_revisions WHERE channel_id = $1 AND date = $2 ORDER BY id DESC";
+    let result: Vec<PlaylistRevision> = sqlx::query_as(query)
+        .bind(channel_id)
+        .bind(date)
+        .fetch_all(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn select_revision(channel_id: &i32, id: &i32) -> Result<PlaylistRevision, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT * FROM playlist_revisions WHERE channel_id = $1 AND id = $2";
+    let result: PlaylistRevision = sqlx::query_as(query)
+        .bind(channel_id)
+        .bind(id)
+        .fetch_one(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Insert media file to library, or update its technical data when it exists.
+/// Category, tags and last played time are kept.
+pub async fn upsert_media(item: &MediaItem) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "INSERT INTO media (channel_id, path, name, size, modified, duration, video_codec, audio_codec, width, height)
+        VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT(channel_id, path) DO UPDATE SET name = excluded.name, size = excluded.size,
+            modified = excluded.modified, duration = excluded.duration, video_codec = excluded.video_codec,
+            audio_codec = excluded.audio_codec, width = excluded.width, height = excluded.height";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(item.channel_id)
+        .bind(&item.path)
+        .bind(&item.name)
+        .bind(item.size)
+        .bind(item.modified)
+        .bind(item.duration)
+        .bind(&item.video_codec)
+        .bind(&item.audio_codec)
+        .bind(item.width)
+        .bind(item.height)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Select all indexed files from channel, or only the files from one folder.
+pub async fn select_media_files(
+    channel_id: &i32,
+    folder: Option<&str>,
+) -> Result<Vec<MediaItem>, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT * FROM media WHERE channel_id = $1
+        AND ($2 IS NULL OR (substr(path, 1, length($2) + 1) = $2 || '/'
+            AND instr(substr(path, length($2) + 2), '/') = 0))";
+    let result: Vec<MediaItem> = sqlx::query_as(query)
+        .bind(channel_id)
+        .bind(folder)
+        .fetch_all(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Search media library. `sort` can be: name, duration or last_played, with leading "-" for descending order.
+pub async fn select_media(
+    channel_id: &i32,
+    filter: &MediaFilter,
+) -> Result<Vec<MediaItem>, sqlx::Error> {
+    let conn = connection().await?;
+    let sort = filter.sort.as_deref().unwrap_or("name");
+    let (column, direction) = match sort.strip_prefix('-') {
+        Some(s) => (s, "DESC"),
+        None => (sort, "ASC"),
+    };
+    let column = match column {
+        "duration" => "duration",
+        "last_played" => "last_played",
+        _ => "name COLLATE NOCASE",
+    };
+    let query = format!(
+        "SELECT * FROM media
+        WHERE channel_id = $1
+            AND ($2 IS NULL OR name LIKE '%' || $2 || '%')
+            AND ($3 IS NULL OR (',' || tags || ',') LIKE '%,' || $3 || ',%')
+            AND ($4 IS NULL OR category = $4)
+            AND ($5 IS NULL OR substr(path, 1, length($5)) = $5)
+            AND ($6 IS NULL OR duration >= $6)
+            AND ($7 IS NULL OR duration <= $7)
+        ORDER BY {column} {direction}, id LIMIT $8 OFFSET $9"
+    );
+    let result: Vec<MediaItem> = sqlx::query_as(&query)
+        .bind(channel_id)
+        .bind(&filter.search)
+        .bind(filter.tag.as_ref().map(|t| t.trim().to_lowercase()))
+        .bind(&filter.category)
+        .bind(&filter.folder)
+        .bind(filter.min_duration)
+        .bind(filter.max_duration)
+        .bind(filter.limit.unwrap_or(100))
+        .bind(filter.offset.unwrap_or(0))
+        .fetch_all(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn select_media_item(channel_id: &i32, id: &i32) -> Result<MediaItem, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT * FROM media WHERE channel_id = $1 AND id = $2";
+    let result: MediaItem = sqlx::query_as(query)
+        .bind(channel_id)
+        .bind(id)
+        .fetch_one(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn select_media_tags(channel_id: &i32) -> Result<Vec<String>, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT tags FROM media WHERE channel_id = $1 AND tags != ''";
+    let result: Vec<(String,)> = sqlx::query_as(query)
+        .bind(channel_id)
+        .fetch_all(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result.into_iter().map(|(t,)| t).collect())
+}
+
+pub async fn update_media_info(
+    channel_id: &i32,
+    id: &i32,
+    category: &str,
+    tags: &str,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "UPDATE media SET category = $3, tags = $4 WHERE channel_id = $1 AND id = $2";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(channel_id)
+        .bind(id)
+        .bind(category)
+        .bind(tags)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn update_last_played(
+    channel_id: &i32,
+    path: &str,
+    timestamp: i64,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "UPDATE media SET last_played = $3 WHERE channel_id = $1 AND path = $2";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(channel_id)
+        .bind(path)
+        .bind(timestamp)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Change path from a file, or from all files in a folder.
+pub async fn rename_media(
+    channel_id: &i32,
+    source: &str,
+    target: &str,
+    name: &str,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "UPDATE media SET path = $3 || substr(path, length($2) + 1),
+            name = CASE WHEN path = $2 THEN $4 ELSE name END
+        WHERE channel_id = $1 AND (path = $2 OR substr(path, 1, length($2) + 1) = $2 || '/')";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(channel_id)
+        .bind(source)
+        .bind(target)
+        .bind(name)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Delete a file, or all files in a folder, from library.
+pub async fn delete_media(channel_id: &i32, path: &str) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "DELETE FROM media
+        WHERE channel_id = $1 AND (path = $2 OR substr(path, 1, length($2) + 1) = $2 || '/')";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(channel_id)
+        .bind(path)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn insert_job(job: &Job) -> Result<Job, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "INSERT INTO jobs (channel_id, kind, task, status, user_id, username, created)
+        VALUES($1, $2, $3, 'queued', $4, $5, $6)";
+    let result = sqlx::query(query)
+        .bind(job.channel_id)
+        .bind(&job.kind)
+        .bind(&job.task)
+        .bind(job.user_id)
+        .bind(&job.username)
+        .bind(job.created)
+        .execute(&conn)
+        .await?;
+    let new_job: Job = sqlx::query_as("SELECT * FROM jobs WHERE id = $1")
+        .bind(result.last_insert_rowid())
+        .fetch_one(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(new_job)
+}
+
+pub async fn select_job(channel_id: &i32, id: &i64) -> Result<Job, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT * FROM jobs WHERE channel_id = $1 AND id = $2";
+    let result: Job = sqlx::query_as(query)
+        .bind(channel_id)
+        .bind(id)
+        .fetch_one(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Select jobs from channel, newest first.
+pub async fn select_jobs(channel_id: &i32, filter: &JobFilter) -> Result<Vec<Job>, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT * FROM jobs
+        WHERE channel_id = $1 AND ($2 IS NULL OR kind = $2) AND ($3 IS NULL OR status = $3)
+        ORDER BY id DESC LIMIT $4";
+    let result: Vec<Job> = sqlx::query_as(query)
+        .bind(channel_id)
+        .bind(&filter.kind)
+        .bind(&filter.status)
+        .bind(filter.limit.unwrap_or(50))
+        .fetch_all(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Jobs which wait to run, oldest first.
+pub async fn select_queued_jobs() -> Result<Vec<Job>, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "SELECT * FROM jobs WHERE status = 'queued' ORDER BY id";
+    let result: Vec<Job> = sqlx::query_as(query).fetch_all(&conn).await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn start_job(id: &i64, started: i64) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "UPDATE jobs SET status = 'running', started = $2 WHERE id = $1";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(id)
+        .bind(started)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn update_job_progress(
+    id: &i64,
+    progress: f64,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "UPDATE jobs SET progress = $2 WHERE id = $1";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(id)
+        .bind(progress)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+pub async fn finish_job(
+    id: &i64,
+    status: &str,
+    result: &str,
+    message: &str,
+    finished: i64,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "UPDATE jobs SET status = $2, result = $3, message = $4, finished = $5,
+        progress = CASE WHEN $2 = 'done' THEN 100 ELSE progress END
+        WHERE id = $1";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(id)
+        .bind(status)
+        .bind(result)
+        .bind(message)
+        .bind(finished)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Cancel job, when it is still waiting. Running jobs are not touched.
+pub async fn cancel_queued_job(
+    channel_id: &i32,
+    id: &i64,
+    finished: i64,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "UPDATE jobs SET status = 'cancelled', finished = $3
+        WHERE channel_id = $1 AND id = $2 AND status = 'queued'";
+    let result: SqliteQueryResult = sqlx::query(query)
+        .bind(channel_id)
+        .bind(id)
+        .bind(finished)
+        .execute(&conn)
+        .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Mark jobs as failed, which were running when the API stopped.
+pub async fn interrupt_jobs(finished: i64) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "UPDATE jobs SET status = 'failed', message = 'Interrupted by API restart',
+        finished = $1 WHERE status = 'running'";
+    let result: SqliteQueryResult = sqlx::query(query).bind(finished).execute(&conn).await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Remove finished jobs, which are older than `before`.
+pub async fn delete_old_jobs(before: i64) -> Result<SqliteQueryResult, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "DELETE FROM jobs WHERE finished IS NOT NULL AND finished < $1";
+    let result: SqliteQueryResult = sqlx::query(query).bind(before).execute(&conn).await?;
+    conn.close().await;
+
+    Ok(result)
+}
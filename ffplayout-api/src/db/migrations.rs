@@ -0,0 +1,387 @@
+/// Database Migrations
+///
+/// Every schema change gets a new migration with the next version number.
+/// Migrations run in order, each one in its own transaction, and the applied versions
+/// are saved in the `schema_version` table. Never change a migration which is already released,
+/// add a new one instead.
+use chrono::{Local, TimeZone, Utc};
+use simplelog::*;
+use sqlx::{Sqlite, Transaction};
+
+use crate::db::handles::connection;
+
+/// One step of a migration.
+pub enum Step {
+    /// Plain SQL, can contain multiple statements.
+    Sql(&'static str),
+    /// Add column to table, when it not exists already.
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        definition: &'static str,
+    },
+}
+
+pub struct Migration {
+    pub version: i32,
+    pub description: &'static str,
+    pub steps: &'static [Step],
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "base schema",
+        steps: &[Step::Sql(
+            "CREATE TABLE IF NOT EXISTS global
+                (
+                    id                       INTEGER PRIMARY KEY AUTOINCREMENT,
+                    secret                   TEXT NOT NULL,
+                    UNIQUE(secret)
+                );
+            CREATE TABLE IF NOT EXISTS roles
+                (
+                    id                       INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name                     TEXT NOT NULL,
+                    UNIQUE(name)
+                );
+            CREATE TABLE IF NOT EXISTS channels
+                (
+                    id                       INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name                     TEXT NOT NULL,
+                    preview_url              TEXT NOT NULL,
+                    config_path              TEXT NOT NULL,
+                    extra_extensions         TEXT NOT NULL,
+                    service                  TEXT NOT NULL,
+                    UNIQUE(name, service)
+                );
+            CREATE TABLE IF NOT EXISTS presets
+                (
+                    id                       INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name                     TEXT NOT NULL,
+                    text                     TEXT NOT NULL,
+                    x                        TEXT NOT NULL,
+                    y                        TEXT NOT NULL,
+                    fontsize                 TEXT NOT NULL,
+                    line_spacing             TEXT NOT NULL,
+                    fontcolor                TEXT NOT NULL,
+                    box                      TEXT NOT NULL,
+                    boxcolor                 TEXT NOT NULL,
+                    boxborderw               TEXT NOT NULL,
+                    alpha                    TEXT NOT NULL,
+                    channel_id               INTEGER NOT NULL DEFAULT 1,
+                    FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE SET NULL ON DELETE SET NULL,
+                    UNIQUE(name)
+                );
+            CREATE TABLE IF NOT EXISTS user
+                (
+                    id                       INTEGER PRIMARY KEY AUTOINCREMENT,
+                    mail                     TEXT NOT NULL,
+                    username                 TEXT NOT NULL,
+                    password                 TEXT NOT NULL,
+                    salt                     TEXT NOT NULL,
+                    role_id                  INTEGER NOT NULL DEFAULT 2,
+                    channel_id               INTEGER NOT NULL DEFAULT 1,
+                    FOREIGN KEY (role_id)    REFERENCES roles (id) ON UPDATE SET NULL ON DELETE SET NULL,
+                    FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE SET NULL ON DELETE SET NULL,
+                    UNIQUE(mail, username)
+                );",
+        )],
+    },
+    Migration {
+        version: 2,
+        description: "channel permissions for users",
+        steps: &[Step::Sql(
+            "CREATE TABLE IF NOT EXISTS user_channels
+                (
+                    id                       INTEGER PRIMARY KEY AUTOINCREMENT,
+                    user_id                  INTEGER NOT NULL,
+                    channel_id               INTEGER NOT NULL,
+                    role                     TEXT NOT NULL DEFAULT 'viewer',
+                    FOREIGN KEY (user_id)    REFERENCES user (id) ON UPDATE CASCADE ON DELETE CASCADE,
+                    FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE,
+                    UNIQUE(user_id, channel_id)
+                );
+            INSERT OR IGNORE INTO user_channels (user_id, channel_id, role)
+                SELECT user.id, user.channel_id, 'operator' FROM user
+                INNER JOIN channels ON channels.id = user.channel_id;",
+        )],
+    },
+    Migration {
+        version: 3,
+        description: "disable user accounts",
+        steps: &[Step::AddColumn {
+            table: "user",
+            column: "disabled",
+            definition: "INTEGER NOT NULL DEFAULT 0",
+        }],
+    },
+    Migration {
+        version: 4,
+        description: "api and refresh tokens, revocation list",
+        steps: &[Step::Sql(
+            "CREATE TABLE IF NOT EXISTS tokens
+                (
+                    id                       INTEGER PRIMARY KEY AUTOINCREMENT,
+                    jti                      TEXT NOT NULL,
+                    user_id                  INTEGER NOT NULL,
+                    kind                     TEXT NOT NULL,
+                    name                     TEXT NOT NULL DEFAULT '',
+                    scope                    TEXT NOT NULL DEFAULT 'write',
+                    created                  INTEGER NOT NULL,
+                    expires                  INTEGER,
+                    FOREIGN KEY (user_id)    REFERENCES user (id) ON UPDATE CASCADE ON DELETE CASCADE,
+                    UNIQUE(jti)
+                );
+            CREATE TABLE IF NOT EXISTS revoked_tokens
+                (
+                    jti                      TEXT PRIMARY KEY,
+                    expires                  INTEGER NOT NULL
+                );",
+        )],
+    },
+    Migration {
+        version: 5,
+        description: "audit trail",
+        steps: &[Step::Sql(
+            "CREATE TABLE IF NOT EXISTS audit
+                (
+                    id                       INTEGER PRIMARY KEY AUTOINCREMENT,
+                    timestamp                INTEGER NOT NULL,
+                    user_id                  INTEGER NOT NULL,
+                    username                 TEXT NOT NULL,
+                    channel_id               INTEGER,
+                    action                   TEXT NOT NULL,
+                    summary                  TEXT NOT NULL DEFAULT ''
+                );
+            CREATE INDEX IF NOT EXISTS audit_timestamp ON audit (timestamp);",
+        )],
+    },
+    Migration {
+        version: 6,
+        description: "playlist revisions",
+        steps: &[Step::Sql(
+            "CREATE TABLE IF NOT EXISTS playlist_revisions
+                (
+                    id                       INTEGER PRIMARY KEY AUTOINCREMENT,
+                    channel_id               INTEGER NOT NULL,
+                    date                     TEXT NOT NULL,
+                    timestamp                INTEGER NOT NULL,
+                    user_id                  INTEGER NOT NULL,
+                    username                 TEXT NOT NULL,
+                    action                   TEXT NOT NULL,
+                    data                     TEXT NOT NULL,
+                    FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE
+                );
+            CREATE INDEX IF NOT EXISTS playlist_revisions_date ON playlist_revisions (channel_id, date);",
+        )],
+    },
+    Migration {
+        version: 7,
+        description: "media library",
+        steps: &[Step::Sql(
+            "CREATE TABLE IF NOT EXISTS media
+                (
+                    id                       INTEGER PRIMARY KEY AUTOINCREMENT,
+                    channel_id               INTEGER NOT NULL,
+                    path                     TEXT NOT NULL,
+                    name                     TEXT NOT NULL,
+                    size                     INTEGER NOT NULL DEFAULT 0,
+                    modified                 INTEGER NOT NULL DEFAULT 0,
+                    duration                 REAL NOT NULL DEFAULT 0,
+                    video_codec              TEXT NOT NULL DEFAULT '',
+                    audio_codec              TEXT NOT NULL DEFAULT '',
+                    width                    INTEGER NOT NULL DEFAULT 0,
+                    height                   INTEGER NOT NULL DEFAULT 0,
+                    category                 TEXT NOT NULL DEFAULT '',
+                    tags                     TEXT NOT NULL DEFAULT '',
+                    last_played              INTEGER,
+                    FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE,
+                    UNIQUE(channel_id, path)
+                );
+            CREATE INDEX IF NOT EXISTS media_name ON media (channel_id, name);",
+        )],
+    },
+    Migration {
+        version: 8,
+        description: "background jobs",
+        steps: &[Step::Sql(
+            "CREATE TABLE IF NOT EXISTS jobs
+                (
+                    id                       INTEGER PRIMARY KEY AUTOINCREMENT,
+                    channel_id               INTEGER NOT NULL,
+                    kind                     TEXT NOT NULL,
+                    task                     TEXT NOT NULL,
+                    status                   TEXT NOT NULL DEFAULT 'queued',
+                    progress                 REAL NOT NULL DEFAULT 0,
+                    result                   TEXT NOT NULL DEFAULT '',
+                    message                  TEXT NOT NULL DEFAULT '',
+                    user_id                  INTEGER NOT NULL DEFAULT 0,
+                    username                 TEXT NOT NULL DEFAULT '',
+                    created                  INTEGER NOT NULL,
+                    started                  INTEGER,
+                    finished                 INTEGER,
+                    FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE
+                );
+            CREATE INDEX IF NOT EXISTS jobs_status ON jobs (status, id);",
+        )],
+    },
+    Migration {
+        version: 9,
+        description: "channel autostart for supervisor",
+        steps: &[Step::AddColumn {
+            table: "channels",
+            column: "autostart",
+            definition: "INTEGER NOT NULL DEFAULT 0",
+        }],
+    },
+    Migration {
+        version: 10,
+        description: "revoke user tokens on password and role change",
+        steps: &[Step::AddColumn {
+            table: "user",
+            column: "tokens_valid_after",
+            definition: "INTEGER NOT NULL DEFAULT 0",
+        }],
+    },
+    Migration {
+        version: 11,
+        description: "paths which the channel provisioning created",
+        steps: &[Step::AddColumn {
+            table: "channels",
+            column: "provisioned",
+            definition: "TEXT NOT NULL DEFAULT '[]'",
+        }],
+    },
+];
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct SchemaVersion {
+    pub version: i32,
+    pub description: String,
+    pub applied: i64,
+}
+
+async fn apply_step(tx: &mut Transaction<'_, Sqlite>, step: &Step) -> Result<(), sqlx::Error> {
+    match step {
+        Step::Sql(query) => {
+            sqlx::query(query).execute(&mut *tx).await?;
+        }
+        Step::AddColumn {
+            table,
+            column,
+            definition,
+        } => {
+            let (exists,): (i32,) =
+                sqlx::query_as("SELECT COUNT(*) FROM pragma_table_info($1) WHERE name = $2")
+                    .bind(*table)
+                    .bind(*column)
+                    .fetch_one(&mut *tx)
+                    .await?;
+
+            if exists == 0 {
+                let query = format!("ALTER TABLE {table} ADD COLUMN {column} {definition}");
+                sqlx::query(&query).execute(&mut *tx).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the applied migrations.
+pub async fn applied_versions() -> Result<Vec<SchemaVersion>, sqlx::Error> {
+    let conn = connection().await?;
+    let query = "CREATE TABLE IF NOT EXISTS schema_version
+        (
+            version                  INTEGER PRIMARY KEY,
+            description              TEXT NOT NULL,
+            applied                  INTEGER NOT NULL
+        )";
+    sqlx::query(query).execute(&conn).await?;
+
+    let result: Vec<SchemaVersion> =
+        sqlx::query_as("SELECT * FROM schema_version ORDER BY version")
+            .fetch_all(&conn)
+            .await?;
+    conn.close().await;
+
+    Ok(result)
+}
+
+/// Get the migrations, which are not applied.
+pub async fn pending_migrations() -> Result<Vec<&'static Migration>, sqlx::Error> {
+    let applied = applied_versions().await?;
+
+    Ok(MIGRATIONS
+        .iter()
+        .filter(|m| !applied.iter().any(|a| a.version == m.version))
+        .collect())
+}
+
+/// Run all pending migrations, returns the number of applied migrations.
+pub async fn run_migrations() -> Result<usize, sqlx::Error> {
+    let pending = pending_migrations().await?;
+
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let conn = connection().await?;
+
+    for migration in &pending {
+        let mut tx = conn.begin().await?;
+
+        for step in migration.steps {
+            apply_step(&mut tx, step).await?;
+        }
+
+        sqlx::query(
+            "INSERT INTO schema_version (version, description, applied) VALUES($1, $2, $3)",
+        )
+        .bind(migration.version)
+        .bind(migration.description)
+        .bind(Utc::now().timestamp())
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        info!(
+            "Database migration <b><magenta>{}</></b> applied: {}",
+            migration.version, migration.description
+        );
+    }
+
+    conn.close().await;
+
+    Ok(pending.len())
+}
+
+/// Print applied and pending migrations.
+pub async fn db_status() -> Result<(), sqlx::Error> {
+    let applied = applied_versions().await?;
+
+    for migration in MIGRATIONS {
+        match applied.iter().find(|a| a.version == migration.version) {
+            Some(a) => {
+                let time = Local
+                    .timestamp_opt(a.applied, 0)
+                    .single()
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default();
+
+                println!(
+                    "{:>4}  {:<45} applied {time}",
+                    migration.version, migration.description
+                );
+            }
+            None => println!(
+                "{:>4}  {:<45} pending",
+                migration.version, migration.description
+            ),
+        }
+    }
+
+    Ok(())
+}
@@ -1,2 +1,3 @@
 pub mod handles;
+pub mod migrations;
 pub mod models;
@@ -20,8 +20,42 @@ pub struct User {
     #[sqlx(default)]
     #[serde(skip_serializing)]
     pub channel_id: Option<i32>,
+    /// Role in `channel_id`, only for new users.
+    #[sqlx(default)]
+    #[serde(skip_serializing)]
+    pub channel_role: Option<String>,
     #[sqlx(default)]
     pub token: Option<String>,
+    #[sqlx(default)]
+    pub refresh_token: Option<String>,
+    #[sqlx(default)]
+    #[serde(default)]
+    pub disabled: bool,
+    /// Tokens which are issued before this time are not valid anymore.
+    #[sqlx(default)]
+    #[serde(skip)]
+    pub tokens_valid_after: i64,
+}
+
+/// Changed user fields, `None` keeps the value from the database.
+#[derive(Debug, Default)]
+pub struct UserFields {
+    pub mail: Option<String>,
+    pub password: Option<String>,
+    pub salt: Option<String>,
+    pub role_id: Option<i32>,
+    pub disabled: Option<bool>,
+}
+
+/// User data for the user management, without password.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct UserInfo {
+    pub id: i32,
+    pub mail: String,
+    pub username: String,
+    #[sqlx(default)]
+    pub role: Option<String>,
+    pub disabled: bool,
 }
 
 fn empty_string() -> String {
@@ -32,13 +66,40 @@ fn empty_string() -> String {
 pub struct LoginUser {
     pub id: i32,
     pub username: String,
+    pub role: String,
 }
 
 impl LoginUser {
-    pub fn new(id: i32, username: String) -> Self {
-        Self { id, username }
+    pub fn new(id: i32, username: String, role: String) -> Self {
+        Self { id, username, role }
     }
 }
+
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+pub struct UserChannel {
+    #[sqlx(default)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[serde(skip_deserializing)]
+    pub user_id: i32,
+    pub channel_id: i32,
+    pub role: String,
+}
+
+/// Refresh or API token, the token itself is never stored, only its id (jti).
+#[derive(Debug, Serialize, Clone, sqlx::FromRow)]
+pub struct Token {
+    pub id: i32,
+    #[serde(skip)]
+    pub jti: String,
+    pub user_id: i32,
+    pub kind: String,
+    pub name: String,
+    pub scope: String,
+    pub created: i64,
+    pub expires: Option<i64>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
 pub struct TextPreset {
     #[sqlx(default)]
@@ -64,11 +125,142 @@ pub struct Channel {
     pub id: i32,
     pub name: String,
     pub preview_url: String,
+    /// Empty on create, for a config from the template.
+    #[serde(default)]
     pub config_path: String,
     pub extra_extensions: String,
+    #[serde(default)]
     pub service: String,
 
     #[sqlx(default)]
     #[serde(default)]
     pub utc_offset: i32,
+
+    /// Config and folders as json list, which `create_channel` has made.
+    /// Only these are removed with the channel.
+    #[sqlx(default)]
+    #[serde(skip)]
+    pub provisioned: String,
+}
+
+#[derive(Debug, Serialize, Clone, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub timestamp: i64,
+    pub user_id: i32,
+    pub username: String,
+    pub channel_id: Option<i32>,
+    pub action: String,
+    pub summary: String,
+}
+
+/// Saved version of a playlist, `data` holds the playlist JSON.
+///
+/// In revision lists the data is empty and not serialized.
+#[derive(Debug, Serialize, Clone, sqlx::FromRow)]
+pub struct PlaylistRevision {
+    pub id: i32,
+    pub channel_id: i32,
+    pub date: String,
+    pub timestamp: i64,
+    pub user_id: i32,
+    pub username: String,
+    pub action: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub data: String,
+}
+
+/// Query filter for audit entries, `from` and `to` are unix timestamps.
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditFilter {
+    #[serde(default)]
+    pub user_id: Option<i32>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub channel_id: Option<i32>,
+    #[serde(default)]
+    pub action: Option<String>,
+    #[serde(default)]
+    pub from: Option<i64>,
+    #[serde(default)]
+    pub to: Option<i64>,
+    #[serde(default)]
+    pub limit: Option<i64>,
+    #[serde(default)]
+    pub offset: Option<i64>,
+}
+
+/// Media file from the library index, `path` is the absolute path like it is used in playlists.
+///
+/// Tags are stored comma separated.
+#[derive(Debug, Default, Serialize, Clone, sqlx::FromRow)]
+pub struct MediaItem {
+    pub id: i32,
+    pub channel_id: i32,
+    pub path: String,
+    pub name: String,
+    pub size: i64,
+    pub modified: i64,
+    pub duration: f64,
+    pub video_codec: String,
+    pub audio_codec: String,
+    pub width: i64,
+    pub height: i64,
+    pub category: String,
+    pub tags: String,
+    pub last_played: Option<i64>,
+}
+
+/// Search and filter for the media library, `search` matches the file name.
+#[derive(Debug, Default, Deserialize)]
+pub struct MediaFilter {
+    #[serde(default)]
+    pub search: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub min_duration: Option<f64>,
+    #[serde(default)]
+    pub max_duration: Option<f64>,
+    #[serde(default)]
+    pub sort: Option<String>,
+    #[serde(default)]
+    pub limit: Option<i64>,
+    #[serde(default)]
+    pub offset: Option<i64>,
+}
+
+/// Background job, `task` holds the job parameters and `result` the outcome, both as json.
+///
+/// Jobs without user are started by the API itself, like the conform after an upload.
+#[derive(Debug, Default, Serialize, Clone, sqlx::FromRow)]
+pub struct Job {
+    pub id: i64,
+    pub channel_id: i32,
+    pub kind: String,
+    pub task: String,
+    pub status: String,
+    pub progress: f64,
+    pub result: String,
+    pub message: String,
+    pub user_id: i32,
+    pub username: String,
+    pub created: i64,
+    pub started: Option<i64>,
+    pub finished: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct JobFilter {
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub limit: Option<i64>,
 }
@@ -1,7 +1,11 @@
 use std::{path::Path, process::exit};
 
 use actix_files::Files;
-use actix_web::{dev::ServiceRequest, middleware, web, App, Error, HttpMessage, HttpServer};
+use actix_web::{
+    dev::ServiceRequest,
+    error::{ErrorForbidden, ErrorUnauthorized},
+    middleware, web, App, Error, HttpMessage, HttpServer,
+};
 use actix_web_grants::permissions::AttachPermissions;
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 use actix_web_httpauth::middleware::HttpAuthentication;
@@ -14,28 +18,64 @@ pub mod db;
 pub mod utils;
 
 use api::{
-    auth,
+    auth::{self, TokenKind},
     routes::{
-        add_channel, add_dir, add_preset, add_user, control_playout, del_playlist, delete_preset,
-        file_browser, gen_playlist, get_all_channels, get_channel, get_log, get_playlist,
-        get_playout_config, get_presets, get_user, import_playlist, login, media_current,
-        media_last, media_next, move_rename, patch_channel, process_control, remove,
-        remove_channel, save_file, save_playlist, send_text_message, update_playout_config,
-        update_preset, update_user,
+        add_channel, add_dir, add_job, add_preset, add_token, add_user, conform_media,
+        control_playout, copy_file, copy_playlists, del_playlist, delete_item, delete_preset,
+        diff_revision, empty_trash, export_playlist_file, file_browser, gen_playlist,
+        get_all_channels, get_audit, get_channel, get_epg, get_job_status, get_jobs, get_library,
+        get_library_tags, get_log, get_overview, get_playlist, get_playlist_timing,
+        get_playout_config, get_presets, get_references, get_revisions, get_thumbnail, get_tokens,
+        get_trash, get_upload, get_user, get_user_channels, get_users, import_playlist,
+        insert_item, login, logout, media_current, media_last, media_next, move_item, move_rename,
+        patch_channel, patch_user, process_control, purge_trash_item, refresh, remove,
+        remove_channel, remove_job, remove_token, remove_upload, remove_user_account,
+        remove_user_channel, replace_item, restore_playlist, restore_trash_item, save_file,
+        save_playlist, scan_media, send_text_message, set_user_channel, start_upload, tail_log,
+        trim_item, update_media_item, update_playout_config, update_preset, update_user,
+        upload_file_chunk,
     },
 };
-use db::models::LoginUser;
-use utils::{args_parse::Args, db_path, init_config, run_args, Role};
+use db::{handles, migrations::run_migrations, models::LoginUser};
+use utils::{
+    args_parse::Args, db_path, init_config, jobs::job_runner, library::library_sync, run_args, Role,
+};
 
-use ffplayout_lib::utils::{init_logging, PlayoutConfig};
+#[cfg(target_os = "linux")]
+use utils::supervisor;
+
+use ffplayout_lib::utils::{access::scope_allows, init_logging, PlayoutConfig};
 
 async fn validator(req: ServiceRequest, credentials: BearerAuth) -> Result<ServiceRequest, Error> {
-    // We just get permissions from JWT
     let claims = auth::decode_jwt(credentials.token()).await?;
-    req.attach(vec![Role::set_role(&claims.role)]);
+
+    if claims.kind == TokenKind::Refresh {
+        return Err(ErrorUnauthorized("Refresh token is not allowed here!"));
+    }
+
+    // API tokens with read scope are only allowed to get data
+    if !scope_allows(claims.scope.as_deref(), req.method().as_str(), req.path()) {
+        return Err(ErrorForbidden("Token has only read access!"));
+    }
+
+    // deleted and disabled users lose their access immediately
+    let user = match handles::select_user_by_id(&claims.id).await {
+        Ok(user) if !user.disabled => user,
+        _ => return Err(ErrorUnauthorized("User not exists or is disabled!")),
+    };
+
+    auth::check_revocation(&claims, user.tokens_valid_after).await?;
+
+    // the role from the token can be outdated, a changed role counts at once
+    let role = handles::select_role(&user.role_id.unwrap_or_default())
+        .await
+        .map_err(|_| ErrorUnauthorized("User has no role!"))?;
+
+    req.attach(vec![Role::set_role(&role)]);
 
     req.extensions_mut()
-        .insert(LoginUser::new(claims.id, claims.username));
+        .insert(LoginUser::new(user.id, user.username, role));
+    req.extensions_mut().insert(claims);
 
     Ok(req)
 }
@@ -61,13 +101,19 @@ async fn main() -> std::io::Result<()> {
     config.logging.log_to_file = false;
     config.logging.timestamp = false;
 
-    let logging = init_logging(&config, None, None);
+    let logging = init_logging(&config, None, None, None);
     CombinedLogger::init(logging).unwrap();
 
     if let Err(c) = run_args(args.clone()).await {
         exit(c);
     }
 
+    #[cfg(not(target_os = "linux"))]
+    if args.supervise {
+        error!("The built-in supervisor is only available on Linux!");
+        exit(1);
+    }
+
     if let Some(conn) = args.listen {
         if let Ok(p) = db_path() {
             if !Path::new(&p).is_file() {
@@ -75,7 +121,27 @@ async fn main() -> std::io::Result<()> {
                 exit(1);
             }
         }
+        if let Err(e) = run_migrations().await {
+            error!("Migrate database failed: {e}");
+            exit(1);
+        }
+
         init_config().await;
+
+        // index media files and keep them in sync with the storage
+        for channel in handles::select_all_channels().await.unwrap_or_default() {
+            actix_web::rt::spawn(library_sync(channel.id));
+        }
+
+        actix_web::rt::spawn(job_runner());
+
+        #[cfg(target_os = "linux")]
+        if args.supervise {
+            info!("Run the playout processes with the built-in supervisor");
+            supervisor::activate();
+            actix_web::rt::spawn(supervisor::supervisor());
+        }
+
         let ip_port = conn.split(':').collect::<Vec<&str>>();
         let addr = ip_port[0];
         let port = ip_port[1].parse::<u16>().unwrap();
@@ -83,16 +149,28 @@ async fn main() -> std::io::Result<()> {
         info!("running ffplayout API, listen on {conn}");
 
         // no allow origin here, give it to the reverse proxy
-        HttpServer::new(move || {
+        let result = HttpServer::new(move || {
             let auth = HttpAuthentication::bearer(validator);
             App::new()
                 .wrap(middleware::Logger::default())
                 .service(login)
+                .service(refresh)
                 .service(
                     web::scope("/api")
                         .wrap(auth)
+                        .service(logout)
+                        .service(get_tokens)
+                        .service(add_token)
+                        .service(remove_token)
                         .service(add_user)
+                        .service(get_audit)
                         .service(get_user)
+                        .service(get_users)
+                        .service(patch_user)
+                        .service(remove_user_account)
+                        .service(get_user_channels)
+                        .service(set_user_channel)
+                        .service(remove_user_channel)
                         .service(get_playout_config)
                         .service(update_playout_config)
                         .service(add_preset)
@@ -101,6 +179,7 @@ async fn main() -> std::io::Result<()> {
                         .service(delete_preset)
                         .service(get_channel)
                         .service(get_all_channels)
+                        .service(get_overview)
                         .service(patch_channel)
                         .service(add_channel)
                         .service(remove_channel)
@@ -115,19 +194,59 @@ async fn main() -> std::io::Result<()> {
                         .service(save_playlist)
                         .service(gen_playlist)
                         .service(del_playlist)
+                        .service(copy_playlists)
+                        .service(get_epg)
+                        .service(export_playlist_file)
+                        .service(get_playlist_timing)
+                        .service(insert_item)
+                        .service(replace_item)
+                        .service(trim_item)
+                        .service(move_item)
+                        .service(delete_item)
+                        .service(get_revisions)
+                        .service(diff_revision)
+                        .service(restore_playlist)
                         .service(get_log)
+                        .service(tail_log)
                         .service(file_browser)
+                        .service(get_thumbnail)
                         .service(add_dir)
                         .service(move_rename)
+                        .service(get_references)
+                        .service(copy_file)
                         .service(remove)
+                        .service(get_trash)
+                        .service(restore_trash_item)
+                        .service(purge_trash_item)
+                        .service(empty_trash)
                         .service(save_file)
-                        .service(import_playlist),
+                        .service(start_upload)
+                        .service(get_upload)
+                        .service(upload_file_chunk)
+                        .service(remove_upload)
+                        .service(conform_media)
+                        .service(import_playlist)
+                        .service(get_library)
+                        .service(get_library_tags)
+                        .service(scan_media)
+                        .service(add_job)
+                        .service(get_jobs)
+                        .service(get_job_status)
+                        .service(remove_job)
+                        .service(update_media_item),
                 )
                 .service(Files::new("/", public_path()).index_file("index.html"))
         })
         .bind((addr, port))?
         .run()
-        .await
+        .await;
+
+        #[cfg(target_os = "linux")]
+        if args.supervise {
+            supervisor::shutdown();
+        }
+
+        result
     } else {
         error!("Run ffpapi with listen parameter!");
 
@@ -25,4 +25,45 @@ pub struct Args {
 
     #[clap(short, long, help = "Admin password")]
     pub password: Option<String>,
+
+    #[clap(long, help = "List all users")]
+    pub list_users: bool,
+
+    #[clap(long, help = "Delete user", value_name = "USERNAME")]
+    pub delete_user: Option<String>,
+
+    #[clap(
+        long,
+        help = "Change role from user, together with --role",
+        value_name = "USERNAME"
+    )]
+    pub change_role: Option<String>,
+
+    #[clap(long, help = "New user role: admin, user or guest")]
+    pub role: Option<String>,
+
+    #[clap(
+        long,
+        help = "Reset password from user, the password comes from --password or from prompt",
+        value_name = "USERNAME"
+    )]
+    pub reset_password: Option<String>,
+
+    #[clap(long, help = "Disable user account", value_name = "USERNAME")]
+    pub disable_user: Option<String>,
+
+    #[clap(long, help = "Enable user account", value_name = "USERNAME")]
+    pub enable_user: Option<String>,
+
+    #[clap(long, help = "Apply pending database migrations")]
+    pub migrate: bool,
+
+    #[clap(long, help = "Show applied and pending database migrations")]
+    pub db_status: bool,
+
+    #[clap(
+        long,
+        help = "Run the ffplayout processes from the channels by ffpapi itself, instead of systemd (Linux only)"
+    )]
+    pub supervise: bool,
 }
@@ -0,0 +1,40 @@
+use chrono::Utc;
+use serde::Serialize;
+use simplelog::*;
+
+use crate::db::{
+    handles::insert_audit,
+    models::{AuditEntry, LoginUser},
+};
+
+// Longer payload summaries get cut, to keep the audit table small.
+const SUMMARY_LENGTH: usize = 500;
+
+/// Short text from request payload, for the audit entry.
+pub fn summarize<T: Serialize>(payload: &T) -> String {
+    let text = serde_json::to_string(payload).unwrap_or_default();
+
+    match text.char_indices().nth(SUMMARY_LENGTH) {
+        Some((i, _)) => format!("{}...", &text[..i]),
+        None => text,
+    }
+}
+
+/// Write an audit entry for a user action.
+///
+/// A failing audit entry should not break the action itself, so errors are only logged.
+pub async fn audit(user: &LoginUser, channel_id: Option<i32>, action: &str, summary: String) {
+    let entry = AuditEntry {
+        id: 0,
+        timestamp: Utc::now().timestamp(),
+        user_id: user.id,
+        username: user.username.clone(),
+        channel_id,
+        action: action.to_string(),
+        summary,
+    };
+
+    if let Err(e) = insert_audit(&entry).await {
+        error!("Write audit entry <b><magenta>{action}</></b> failed: {e}");
+    }
+}
@@ -1,40 +1,220 @@
-use std::fs;
+/// Channel Provisioning
+///
+/// A new channel gets its own config, copied from the template, with free ports for the RPC
+/// server, the ingest server and the text messages. Playlists and media go to
+/// `<template parent>/<channel>/`, HLS segments and logs to a `<channel>` subfolder of the
+/// template folders. Deleting a channel takes back the same steps, in reverse order,
+/// for the paths which are stored with the channel.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use simplelog::*;
 
-use crate::utils::{control::control_service, errors::ServiceError};
+use crate::utils::{
+    control::control_service, errors::ServiceError, library::library_sync, read_playout_config,
+};
 
 use crate::db::{handles, models::Channel};
+use ffplayout_lib::utils::{
+    free_tcp_socket,
+    provision::{
+        channel_config, channel_folders, channel_slug, cleanup, deprovision_paths, hls_folder,
+        in_config_dir, ingest_socket, socket_port,
+    },
+    PlayoutConfig,
+};
 
-pub async fn create_channel(target_channel: Channel) -> Result<Channel, ServiceError> {
-    if !target_channel.service.starts_with("ffplayout@") {
-        return Err(ServiceError::BadRequest("Bad service name!".to_string()));
+const CONFIG_DIR: &str = "/etc/ffplayout";
+
+fn template_path() -> &'static str {
+    if Path::new("/usr/share/ffplayout/ffplayout.yml.orig").is_file() {
+        return "/usr/share/ffplayout/ffplayout.yml.orig";
+    }
+
+    "./assets/ffplayout.yml"
+}
+
+/// Sockets from all channel configs, also from channels which are not running.
+async fn used_sockets() -> Result<Vec<String>, ServiceError> {
+    let mut sockets = vec![];
+
+    for channel in handles::select_all_channels().await? {
+        if let Ok(config) = read_playout_config(&channel.config_path) {
+            sockets.push(config.rpc_server.address);
+            sockets.extend(ingest_socket(&config.ingest.input_param));
+            sockets.extend(config.text.zmq_stream_socket);
+            sockets.extend(config.text.zmq_server_socket);
+        }
+    }
+
+    Ok(sockets)
+}
+
+/// Free socket on localhost, with a port which no other channel has in its config.
+fn free_socket(used: &mut Vec<String>) -> Result<String, ServiceError> {
+    for _ in 0..20 {
+        if let Some(socket) = free_tcp_socket(String::new()) {
+            if !used.iter().any(|u| socket_port(u) == socket_port(&socket)) {
+                used.push(socket.clone());
+
+                return Ok(socket);
+            }
+        }
+    }
+
+    Err(ServiceError::Conflict("No free port found!".to_string()))
+}
+
+/// Write config and create the folders, the created paths are given back for a cleanup.
+fn provision(config_path: &str, config: &PlayoutConfig) -> Result<Vec<PathBuf>, ServiceError> {
+    let mut created = vec![];
+    let yaml = serde_yaml::to_string(config).map_err(|e| e.to_string())?;
+
+    fs::write(config_path, yaml)
+        .map_err(|e| ServiceError::BadRequest(format!("Write config failed: {e}")))?;
+    created.push(PathBuf::from(config_path));
+
+    for folder in channel_folders(config) {
+        let mut missing: Vec<PathBuf> = folder
+            .ancestors()
+            .take_while(|p| !p.exists())
+            .map(Path::to_path_buf)
+            .collect();
+        missing.reverse();
+
+        if let Err(e) = fs::create_dir_all(&folder) {
+            cleanup(&created);
+
+            return Err(ServiceError::BadRequest(format!(
+                "Create folder {} failed: {e}",
+                folder.display()
+            )));
+        }
+
+        created.append(&mut missing);
+    }
+
+    Ok(created)
+}
+
+pub async fn create_channel(mut target_channel: Channel) -> Result<Channel, ServiceError> {
+    let slug = channel_slug(&target_channel.name);
+
+    if slug.is_empty() {
+        return Err(ServiceError::BadRequest("Bad channel name!".to_string()));
     }
 
-    if !target_channel.config_path.starts_with("/etc/ffplayout") {
+    if target_channel.config_path.is_empty() {
+        target_channel.config_path = format!("{CONFIG_DIR}/{slug}.yml");
+    }
+
+    if !in_config_dir(CONFIG_DIR, &target_channel.config_path) {
         return Err(ServiceError::BadRequest("Bad config path!".to_string()));
     }
 
-    fs::copy(
-        "/usr/share/ffplayout/ffplayout.yml.orig",
-        &target_channel.config_path,
-    )?;
+    // the service instance reads the config with its own name
+    let slug = Path::new(&target_channel.config_path)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
 
-    let new_channel = handles::insert_channel(target_channel).await?;
-    control_service(new_channel.id, "enable").await?;
+    if target_channel.service.is_empty() {
+        target_channel.service = format!("ffplayout@{slug}.service");
+    }
+
+    if target_channel.service != format!("ffplayout@{slug}.service") {
+        return Err(ServiceError::BadRequest("Bad service name!".to_string()));
+    }
+
+    if Path::new(&target_channel.config_path).exists() {
+        return Err(ServiceError::Conflict(format!(
+            "Config {} already exists!",
+            target_channel.config_path
+        )));
+    }
+
+    let template = read_playout_config(template_path())
+        .map_err(|e| ServiceError::BadRequest(format!("Read config template failed: {e}")))?;
+    let mut used = used_sockets().await?;
+    let config = channel_config(template, &slug, &mut || free_socket(&mut used))?;
+    let created = provision(&target_channel.config_path, &config)?;
+    target_channel.provisioned = serde_json::to_string(&created).map_err(|e| e.to_string())?;
+
+    let new_channel = match handles::insert_channel(target_channel).await {
+        Ok(c) => c,
+        Err(e) => {
+            cleanup(&created);
+            return Err(e.into());
+        }
+    };
+
+    if let Err(e) = control_service(new_channel.id, "enable").await {
+        cleanup(&created);
+        handles::delete_channel(&new_channel.id).await?;
+
+        return Err(e);
+    }
+
+    info!(
+        "Create channel <yellow>{}</> with config <b><magenta>{}</></b>",
+        new_channel.id, new_channel.config_path
+    );
+
+    actix_web::rt::spawn(library_sync(new_channel.id));
 
     Ok(new_channel)
 }
 
+/// Folders which other channels use, they stay in any case.
+async fn shared_folders(channel: &Channel) -> Vec<PathBuf> {
+    let mut shared = vec![];
+
+    for other in handles::select_all_channels().await.unwrap_or_default() {
+        if other.id != channel.id {
+            if let Ok(c) = read_playout_config(&other.config_path) {
+                shared.append(&mut channel_folders(&c));
+            }
+        }
+    }
+
+    shared
+}
+
+/// Delete channel, with the config and folders which `create_channel` has made.
+/// Channels which are not created over the API keep their files.
 pub async fn delete_channel(id: i32) -> Result<(), ServiceError> {
     let channel = handles::select_channel(&id).await?;
-    control_service(channel.id, "stop").await?;
-    control_service(channel.id, "disable").await?;
 
-    if let Err(e) = fs::remove_file(channel.config_path) {
-        error!("{e}");
+    // a broken service must not keep the channel
+    for command in ["stop", "disable"] {
+        if let Err(e) = control_service(channel.id, command).await {
+            error!("Service {command} from channel <yellow>{id}</> failed: {e}");
+        }
+    }
+
+    let created: Vec<PathBuf> = serde_json::from_str(&channel.provisioned).unwrap_or_default();
+    let output = match read_playout_config(&channel.config_path) {
+        Ok(config) => {
+            let mut output = vec![PathBuf::from(&config.logging.log_path)];
+            output.extend(hls_folder(&config));
+            output
+        }
+        Err(_) => vec![],
     };
+    let shared = shared_folders(&channel).await;
+    let (outputs, paths) = deprovision_paths(CONFIG_DIR, &created, &shared, &output);
+
+    // HLS segments and logs are only output from the channel
+    for folder in outputs {
+        if let Err(e) = fs::remove_dir_all(&folder) {
+            error!("Remove <b><magenta>{}</></b> failed: {e}", folder.display());
+        }
+    }
 
+    cleanup(&paths);
     handles::delete_channel(&id).await?;
 
     Ok(())
@@ -1,5 +1,6 @@
-use std::{collections::HashMap, process::Command};
+use std::{collections::HashMap, fs, process::Command};
 
+use actix_web::web;
 use reqwest::{
     header::{HeaderMap, AUTHORIZATION, CONTENT_TYPE},
     Client, Response,
@@ -7,9 +8,25 @@ use reqwest::{
 use serde::{Deserialize, Serialize};
 
 use crate::db::handles::select_channel;
+#[cfg(target_os = "linux")]
+use crate::utils::supervisor;
 use crate::utils::{errors::ServiceError, playout_config};
 use ffplayout_lib::vec_strings;
 
+/// Playout process state, from systemd or from the built-in supervisor.
+#[derive(Debug, Default, Serialize)]
+pub struct ProcessStatus {
+    /// Like systemd: active, activating, inactive or failed.
+    pub state: String,
+    pub enabled: bool,
+    pub pid: Option<u32>,
+    /// Seconds since the last start.
+    pub uptime: i64,
+    pub restarts: u32,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 struct RpcObj<T> {
     jsonrpc: String,
@@ -65,46 +82,19 @@ impl SystemD {
         })
     }
 
-    fn enable(mut self) -> Result<String, ServiceError> {
-        self.cmd
-            .append(&mut vec!["enable".to_string(), self.service]);
-
-        Command::new("sudo").args(self.cmd).spawn()?;
-
-        Ok("Success".to_string())
-    }
-
-    fn disable(mut self) -> Result<String, ServiceError> {
+    /// Run systemctl command and wait for it, a failed command gives its error message back.
+    fn run(mut self, command: &str) -> Result<String, ServiceError> {
         self.cmd
-            .append(&mut vec!["disable".to_string(), self.service]);
-
-        Command::new("sudo").args(self.cmd).spawn()?;
-
-        Ok("Success".to_string())
-    }
-
-    fn start(mut self) -> Result<String, ServiceError> {
-        self.cmd
-            .append(&mut vec!["start".to_string(), self.service]);
-
-        Command::new("sudo").args(self.cmd).spawn()?;
-
-        Ok("Success".to_string())
-    }
-
-    fn stop(mut self) -> Result<String, ServiceError> {
-        self.cmd.append(&mut vec!["stop".to_string(), self.service]);
+            .append(&mut vec![command.to_string(), self.service]);
 
-        Command::new("sudo").args(self.cmd).spawn()?;
-
-        Ok("Success".to_string())
-    }
-
-    fn restart(mut self) -> Result<String, ServiceError> {
-        self.cmd
-            .append(&mut vec!["restart".to_string(), self.service]);
+        let output = Command::new("sudo").args(self.cmd).output()?;
 
-        Command::new("sudo").args(self.cmd).spawn()?;
+        if !output.status.success() {
+            return Err(ServiceError::ServiceUnavailable(format!(
+                "systemctl {command} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
 
         Ok("Success".to_string())
     }
@@ -117,6 +107,50 @@ impl SystemD {
 
         Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
     }
+
+    /// Service state with pid, uptime, restarts and the last exit code.
+    fn info(mut self) -> Result<ProcessStatus, ServiceError> {
+        self.cmd.append(&mut vec_strings![
+            "show",
+            self.service,
+            "--property=ActiveState,UnitFileState,MainPID,NRestarts",
+            "--property=ExecMainCode,ExecMainStatus,ActiveEnterTimestampMonotonic"
+        ]);
+
+        // show needs no root rights
+        let output = Command::new(&self.cmd[0]).args(&self.cmd[1..]).output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let props: HashMap<&str, &str> = stdout.lines().filter_map(|l| l.split_once('=')).collect();
+        let number = |key: &str| props.get(key).and_then(|v| v.parse::<i64>().ok());
+        let state = props.get("ActiveState").unwrap_or(&"unknown").to_string();
+        // exit code from the last run: 1 is a normal exit, 2 and 3 a signal
+        let (exit_code, signal) = match number("ExecMainCode") {
+            Some(1) => (number("ExecMainStatus").map(|c| c as i32), None),
+            Some(2) | Some(3) => (None, number("ExecMainStatus").map(|c| c as i32)),
+            _ => (None, None),
+        };
+        let mut uptime = 0;
+
+        if state == "active" {
+            let since_boot = fs::read_to_string("/proc/uptime")
+                .ok()
+                .and_then(|u| u.split(' ').next().and_then(|s| s.parse::<f64>().ok()))
+                .unwrap_or_default();
+            let started = number("ActiveEnterTimestampMonotonic").unwrap_or_default() / 1_000_000;
+
+            uptime = (since_boot as i64 - started).max(0);
+        }
+
+        Ok(ProcessStatus {
+            state,
+            enabled: props.get("UnitFileState") == Some(&"enabled"),
+            pid: number("MainPID").filter(|p| *p > 0).map(|p| p as u32),
+            uptime,
+            restarts: number("NRestarts").unwrap_or_default() as u32,
+            exit_code,
+            signal,
+        })
+    }
 }
 
 fn create_header(auth: &str) -> HeaderMap {
@@ -178,16 +212,40 @@ pub async fn media_info(id: i32, command: String) -> Result<Response, ServiceErr
     post_request(id, json_obj).await
 }
 
+/// Control the playout process, over systemd or over the built-in supervisor.
 pub async fn control_service(id: i32, command: &str) -> Result<String, ServiceError> {
+    #[cfg(target_os = "linux")]
+    if supervisor::is_active() {
+        return match command {
+            "enable" => supervisor::set_autostart(id, true).await,
+            "disable" => supervisor::set_autostart(id, false).await,
+            "start" => supervisor::start(id).await,
+            "stop" => supervisor::stop(id).await,
+            "restart" => supervisor::restart(id).await,
+            "status" => Ok(supervisor::status(id).await?.state),
+            _ => Err(ServiceError::BadRequest("Command not found!".to_string())),
+        };
+    }
+
     let system_d = SystemD::new(id).await?;
+    let command = command.to_string();
 
-    match command {
-        "enable" => system_d.enable(),
-        "disable" => system_d.disable(),
-        "start" => system_d.start(),
-        "stop" => system_d.stop(),
-        "restart" => system_d.restart(),
+    web::block(move || match command.as_str() {
+        "enable" | "disable" | "start" | "stop" | "restart" => system_d.run(&command),
         "status" => system_d.status(),
         _ => Err(ServiceError::BadRequest("Command not found!".to_string())),
+    })
+    .await?
+}
+
+/// Process state with pid, uptime, restarts and the last exit code.
+pub async fn service_info(id: i32) -> Result<ProcessStatus, ServiceError> {
+    #[cfg(target_os = "linux")]
+    if supervisor::is_active() {
+        return supervisor::status(id).await;
     }
+
+    let system_d = SystemD::new(id).await?;
+
+    web::block(move || system_d.info()).await?
 }
@@ -1,4 +1,4 @@
-use actix_web::{error::ResponseError, Error, HttpResponse};
+use actix_web::{error::ResponseError, http::StatusCode, Error, HttpResponse};
 use derive_more::Display;
 
 #[derive(Debug, Display)]
@@ -15,11 +15,20 @@ pub enum ServiceError {
     #[display(fmt = "Unauthorized")]
     Unauthorized,
 
+    #[display(fmt = "Forbidden: {}", _0)]
+    Forbidden(String),
+
     #[display(fmt = "NoContent: {}", _0)]
     NoContent(String),
 
     #[display(fmt = "ServiceUnavailable: {}", _0)]
     ServiceUnavailable(String),
+
+    #[display(fmt = "PreconditionFailed: {}", _0)]
+    PreconditionFailed(String),
+
+    #[display(fmt = "PreconditionRequired: {}", _0)]
+    PreconditionRequired(String),
 }
 
 // impl ResponseError trait allows to convert our errors into http responses with appropriate data
@@ -32,10 +41,17 @@ impl ResponseError for ServiceError {
             ServiceError::BadRequest(ref message) => HttpResponse::BadRequest().json(message),
             ServiceError::Conflict(ref message) => HttpResponse::Conflict().json(message),
             ServiceError::Unauthorized => HttpResponse::Unauthorized().json("No Permission!"),
+            ServiceError::Forbidden(ref message) => HttpResponse::Forbidden().json(message),
             ServiceError::NoContent(ref message) => HttpResponse::NoContent().json(message),
             ServiceError::ServiceUnavailable(ref message) => {
                 HttpResponse::ServiceUnavailable().json(message)
             }
+            ServiceError::PreconditionFailed(ref message) => {
+                HttpResponse::PreconditionFailed().json(message)
+            }
+            ServiceError::PreconditionRequired(ref message) => {
+                HttpResponse::build(StatusCode::PRECONDITION_REQUIRED).json(message)
+            }
         }
     }
 }
@@ -1,6 +1,10 @@
-use std::{fs, io::Write, path::PathBuf};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
-use actix_multipart::Multipart;
+use actix_multipart::{Field, Multipart};
 use actix_web::{web, HttpResponse};
 use futures_util::TryStreamExt as _;
 use rand::{distributions::Alphanumeric, Rng};
@@ -8,9 +12,20 @@ use relative_path::RelativePath;
 use serde::{Deserialize, Serialize};
 
 use simplelog::*;
-
-use crate::utils::{errors::ServiceError, playout_config};
-use ffplayout_lib::utils::{file_extension, MediaProbe};
+use walkdir::WalkDir;
+
+use crate::db::{handles::select_media_files, models::LoginUser};
+use crate::utils::{
+    errors::ServiceError,
+    jobs::{submit_job, JobTask},
+    library::indexed_duration,
+    playlist::{playlist_references, reference_dates, rewrite_references},
+    playout_config,
+    thumbnails::{create_thumbs, move_thumbs},
+    trash::{move_to_trash, TrashItem},
+    upload::check_size,
+};
+use ffplayout_lib::utils::{file_extension, is_hidden, MediaProbe, PlayoutConfig};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PathObject {
@@ -33,8 +48,8 @@ impl PathObject {
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct MoveObject {
-    source: String,
-    target: String,
+    pub source: String,
+    pub target: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -47,7 +62,7 @@ pub struct VideoFile {
 ///
 /// This function takes care, that it is not possible to break out from root_path.
 /// It also gives alway a relative path back.
-fn norm_abs_path(root_path: &str, input_path: &str) -> (PathBuf, String, String) {
+pub fn norm_abs_path(root_path: &str, input_path: &str) -> (PathBuf, String, String) {
     let mut path = PathBuf::from(root_path);
     let path_relative = RelativePath::new(root_path)
         .normalize()
@@ -92,6 +107,10 @@ pub async fn browser(id: i32, path_obj: &PathObject) -> Result<PathObject, Servi
     let extensions = config.storage.extensions;
     let (path, parent, path_component) = norm_abs_path(&config.storage.path, &path_obj.source);
     let mut obj = PathObject::new(path_component, Some(parent));
+    let folder = path.to_string_lossy().trim_end_matches('/').to_string();
+    let indexed = select_media_files(&id, Some(&folder))
+        .await
+        .unwrap_or_default();
 
     let mut paths: Vec<_> = match fs::read_dir(path) {
         Ok(p) => p.filter_map(|r| r.ok()).collect(),
@@ -119,11 +138,19 @@ pub async fn browser(id: i32, path_obj: &PathObject) -> Result<PathObject, Servi
         } else if file_path.is_file() {
             if let Some(ext) = file_extension(&file_path) {
                 if extensions.contains(&ext.to_string().to_lowercase()) {
-                    let media = MediaProbe::new(&path.display().to_string());
-                    let mut duration = 0.0;
-
-                    if let Some(dur) = media.format.and_then(|f| f.duration) {
-                        duration = dur.parse().unwrap_or(0.0)
+                    // take duration from library, when the file has not changed
+                    let mut duration = indexed
+                        .iter()
+                        .find(|m| Path::new(&m.path) == path)
+                        .and_then(|m| indexed_duration(m, &path))
+                        .unwrap_or(0.0);
+
+                    if duration == 0.0 {
+                        let media = MediaProbe::new(&path.display().to_string());
+
+                        if let Some(dur) = media.format.and_then(|f| f.duration) {
+                            duration = dur.parse().unwrap_or(0.0)
+                        }
                     }
 
                     let video = VideoFile {
@@ -158,108 +185,256 @@ pub async fn create_directory(
     Ok(HttpResponse::Ok().into())
 }
 
-// fn copy_and_delete(source: &PathBuf, target: &PathBuf) -> Result<PathObject, ServiceError> {
-//     match fs::copy(&source, &target) {
-//         Ok(_) => {
-//             if let Err(e) = fs::remove_file(source) {
-//                 error!("{e}");
-//                 return Err(ServiceError::BadRequest(
-//                     "Removing File not possible!".into(),
-//                 ));
-//             };
-
-//             return Ok(PathObject::new(target.display().to_string()));
-//         }
-//         Err(e) => {
-//             error!("{e}");
-//             Err(ServiceError::BadRequest("Error in file copy!".into()))
-//         }
-//     }
-// }
-
-fn rename(source: &PathBuf, target: &PathBuf) -> Result<MoveObject, ServiceError> {
-    match fs::rename(&source, &target) {
-        Ok(_) => Ok(MoveObject {
-            source: source
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string(),
-            target: target
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string(),
-        }),
-        Err(e) => {
-            error!("{e}");
-            Err(ServiceError::BadRequest("Rename failed!".into()))
-        }
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RenameObject {
+    pub source: String,
+    pub target: String,
+    /// Update the clips in playlists from today on, which use the source.
+    #[serde(default)]
+    pub rewrite: bool,
+    /// Rename also, when the source is used in playlists and they should stay as they are.
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenameReport {
+    pub source: String,
+    pub target: String,
+    /// Dates from the playlists, which are rewritten.
+    pub rewritten: Vec<String>,
+}
+
+/// Hidden files and folders, like trash, thumbnails and upload sessions, belong to the API.
+fn check_hidden(root: &str, paths: &[&Path]) -> Result<(), ServiceError> {
+    if paths.iter().any(|p| is_hidden(root, p)) {
+        return Err(ServiceError::BadRequest(
+            "Hidden files and folders are not allowed!".into(),
+        ));
     }
+
+    Ok(())
 }
 
-pub async fn rename_file(id: i32, move_object: &MoveObject) -> Result<MoveObject, ServiceError> {
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Rename or move file or folder. When the source is used in playlists from today on,
+/// the references are rewritten with the `rewrite` flag, or left as they are with `force`.
+/// Without one of them the rename is refused, the error lists the affected dates.
+pub async fn rename_file(
+    id: i32,
+    obj: &RenameObject,
+    user: &LoginUser,
+) -> Result<RenameReport, ServiceError> {
     let (config, _) = playout_config(&id).await?;
-    let (source_path, _, _) = norm_abs_path(&config.storage.path, &move_object.source);
-    let (mut target_path, _, _) = norm_abs_path(&config.storage.path, &move_object.target);
+    let (source_path, _, relative) = norm_abs_path(&config.storage.path, &obj.source);
+    let (mut target_path, _, _) = norm_abs_path(&config.storage.path, &obj.target);
 
-    if !source_path.exists() {
+    if !source_path.exists() || relative.is_empty() {
         return Err(ServiceError::BadRequest("Source file not exist!".into()));
     }
 
-    if (source_path.is_dir() || source_path.is_file()) && source_path.parent() == Some(&target_path)
-    {
-        return rename(&source_path, &target_path);
-    }
-
     if target_path.is_dir() {
-        target_path = target_path.join(source_path.file_name().unwrap());
+        target_path = target_path.join(source_path.file_name().unwrap_or_default());
     }
 
-    if target_path.is_file() {
+    check_hidden(&config.storage.path, &[&source_path, &target_path])?;
+
+    if target_path.exists() {
         return Err(ServiceError::BadRequest(
             "Target file already exists!".into(),
         ));
     }
 
-    if source_path.is_file() && target_path.parent().is_some() {
-        return rename(&source_path, &target_path);
+    if target_path.starts_with(&source_path) {
+        return Err(ServiceError::BadRequest(
+            "Folder can not be moved into itself!".into(),
+        ));
+    }
+
+    let (root, path) = (config.playlist.path.clone(), source_path.clone());
+    let references = web::block(move || playlist_references(&root, &path)).await?;
+
+    if !references.is_empty() && !obj.rewrite && !obj.force {
+        return Err(ServiceError::Conflict(format!(
+            "Used in playlists from: {}. Rename with rewrite to update them, \
+            or with force to keep them.",
+            reference_dates(&references)
+        )));
+    }
+
+    if let Err(e) = fs::rename(&source_path, &target_path) {
+        error!("{e}");
+        return Err(ServiceError::BadRequest("Rename failed!".into()));
+    }
+
+    if let Ok(target) = target_path.strip_prefix(&config.storage.path) {
+        move_thumbs(
+            &config.storage.path,
+            &relative,
+            Some(&target.to_string_lossy()),
+        );
+    }
+
+    let mut rewritten = vec![];
+
+    if obj.rewrite && !references.is_empty() {
+        rewritten = rewrite_references(id, &references, &source_path, &target_path, user).await?;
     }
 
-    Err(ServiceError::InternalServerError)
+    Ok(RenameReport {
+        source: file_name(&source_path),
+        target: file_name(&target_path),
+        rewritten,
+    })
 }
 
-pub async fn remove_file_or_folder(id: i32, source_path: &str) -> Result<(), ServiceError> {
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RemoveObject {
+    pub source: String,
+    /// Confirms to delete a folder with all its content.
+    #[serde(default)]
+    pub recursive: bool,
+    /// Delete also, when the file is used in playlists from today on.
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Move file or folder to trash. Files which are used in current or future playlists
+/// are only removed with the `force` flag, otherwise the error lists the affected dates.
+pub async fn remove_file_or_folder(
+    id: i32,
+    obj: &RemoveObject,
+    user: &LoginUser,
+) -> Result<TrashItem, ServiceError> {
     let (config, _) = playout_config(&id).await?;
-    let (source, _, _) = norm_abs_path(&config.storage.path, source_path);
+    let (source, _, relative) = norm_abs_path(&config.storage.path, &obj.source);
 
     if !source.exists() {
         return Err(ServiceError::BadRequest("Source does not exists!".into()));
     }
 
-    if source.is_dir() {
-        match fs::remove_dir(source) {
-            Ok(_) => return Ok(()),
-            Err(e) => {
-                error!("{e}");
-                return Err(ServiceError::BadRequest(
-                    "Delete folder failed! (Folder must be empty)".into(),
-                ));
-            }
-        };
+    if relative.is_empty() {
+        return Err(ServiceError::BadRequest(
+            "Storage root can not be deleted!".into(),
+        ));
     }
 
-    if source.is_file() {
-        match fs::remove_file(source) {
-            Ok(_) => return Ok(()),
-            Err(e) => {
-                error!("{e}");
-                return Err(ServiceError::BadRequest("Delete file failed!".into()));
-            }
+    check_hidden(&config.storage.path, &[&source])?;
+
+    if source.is_dir() && !obj.recursive && fs::read_dir(&source)?.next().is_some() {
+        return Err(ServiceError::BadRequest(
+            "Folder is not empty, delete it with the recursive flag!".into(),
+        ));
+    }
+
+    if !obj.force {
+        let (root, path) = (config.playlist.path.clone(), source.clone());
+        let references = web::block(move || playlist_references(&root, &path)).await?;
+
+        if !references.is_empty() {
+            return Err(ServiceError::Conflict(format!(
+                "Used in playlists from: {}. Delete with force to remove it anyway.",
+                reference_dates(&references)
+            )));
+        }
+    }
+
+    move_to_trash(&config.storage.path, &source, &relative, &user.username)
+}
+
+/// Source and target for a copy, `target` can be an existing folder or the new name.
+pub async fn copy_paths(id: i32, obj: &MoveObject) -> Result<(PathBuf, PathBuf), ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+    let (source, _, relative) = norm_abs_path(&config.storage.path, &obj.source);
+    let (mut target, _, _) = norm_abs_path(&config.storage.path, &obj.target);
+
+    if !source.exists() || relative.is_empty() {
+        return Err(ServiceError::BadRequest("Source does not exists!".into()));
+    }
+
+    if target.is_dir() {
+        target = target.join(source.file_name().unwrap_or_default());
+    }
+
+    check_hidden(&config.storage.path, &[&source, &target])?;
+
+    if target.exists() {
+        return Err(ServiceError::BadRequest("Target already exists!".into()));
+    }
+
+    if target.starts_with(&source) {
+        return Err(ServiceError::BadRequest(
+            "Folder can not be copied into itself!".into(),
+        ));
+    }
+
+    if !target.parent().map(|p| p.is_dir()).unwrap_or(false) {
+        return Err(ServiceError::BadRequest("Target folder not exists!".into()));
+    }
+
+    Ok((source, target))
+}
+
+/// Copy file or folder with its content, the progress goes by the copied bytes.
+///
+/// When `progress` returns false, the copy stops and the partial copy gets removed.
+pub fn copy_files(
+    source: &Path,
+    target: &Path,
+    progress: &mut dyn FnMut(f64) -> bool,
+) -> Result<(), io::Error> {
+    let entries: Vec<_> = WalkDir::new(source)
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .map_err(io::Error::from)?;
+    let total: u64 = entries
+        .iter()
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum();
+    let mut copied = 0;
+
+    for entry in entries {
+        let dest = match entry.path().strip_prefix(source) {
+            Ok(rel) if !rel.as_os_str().is_empty() => target.join(rel),
+            _ => target.to_path_buf(),
+        };
+
+        let mut result = if entry.file_type().is_dir() {
+            fs::create_dir_all(&dest)
+        } else {
+            fs::copy(entry.path(), &dest).map(|size| copied += size)
         };
+
+        if result.is_ok() && !progress(copied as f64 / total.max(1) as f64) {
+            result = Err(io::Error::new(io::ErrorKind::Interrupted, "Copy stopped"));
+        }
+
+        if let Err(e) = result {
+            if target.is_dir() {
+                fs::remove_dir_all(target)?;
+            } else if target.is_file() {
+                fs::remove_file(target)?;
+            }
+
+            return Err(e);
+        }
     }
 
-    Err(ServiceError::InternalServerError)
+    info!(
+        "Copy <b><magenta>{}</></b> to <b><magenta>{}</></b>",
+        source.display(),
+        target.display()
+    );
+
+    Ok(())
 }
 
 async fn valid_path(id: i32, path: &str) -> Result<PathBuf, ServiceError> {
@@ -273,12 +448,59 @@ async fn valid_path(id: i32, path: &str) -> Result<PathBuf, ServiceError> {
     Ok(test_path)
 }
 
+/// Write multipart field to file, the size is checked against the upload limit while receiving.
+async fn write_field(
+    field: &mut Field,
+    path: PathBuf,
+    config: &PlayoutConfig,
+) -> Result<(), ServiceError> {
+    let mut f = web::block(|| std::fs::File::create(path)).await??;
+    let mut size = 0;
+
+    while let Some(chunk) = field.try_next().await? {
+        size += chunk.len() as u64;
+        check_size(config, size)?;
+
+        f = web::block(move || f.write_all(&chunk).map(|_| f)).await??;
+    }
+
+    Ok(())
+}
+
+/// Start the background work for a new media file in storage: thumbnails,
+/// and the conform job when it is enabled in the channel config.
+/// Nothing of it delays the upload response.
+pub fn after_upload(config: &PlayoutConfig, id: i32, filepath: PathBuf) {
+    if !file_extension(&filepath)
+        .map(|e| config.storage.extensions.contains(&e.to_lowercase()))
+        .unwrap_or(false)
+    {
+        return;
+    }
+
+    if config.conform.enable {
+        let task = JobTask::Conform {
+            source: filepath.to_string_lossy().to_string(),
+        };
+
+        actix_web::rt::spawn(async move {
+            if let Err(e) = submit_job(id, None, &task).await {
+                error!("Add conform job failed: {e}");
+            }
+        });
+    }
+
+    actix_web::rt::spawn(create_thumbs(config.storage.path.clone(), filepath));
+}
+
 pub async fn upload(
     id: i32,
     mut payload: Multipart,
     path: &str,
     abs_path: bool,
 ) -> Result<HttpResponse, ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+
     while let Some(mut field) = payload.try_next().await? {
         let content_disposition = field.content_disposition();
         debug!("{content_disposition}");
@@ -304,10 +526,21 @@ pub async fn upload(
             return Err(ServiceError::BadRequest("Target already exists!".into()));
         }
 
-        let mut f = web::block(|| std::fs::File::create(filepath)).await??;
+        // write to a hidden temp file, a broken upload should not end up with the final name
+        let temp = filepath.with_file_name(format!(
+            ".{}.part",
+            filepath.file_name().unwrap_or_default().to_string_lossy()
+        ));
+
+        if let Err(e) = write_field(&mut field, temp.clone(), &config).await {
+            fs::remove_file(&temp)?;
+            return Err(e);
+        }
+
+        fs::rename(&temp, &filepath)?;
 
-        while let Some(chunk) = field.try_next().await? {
-            f = web::block(move || f.write_all(&chunk).map(|_| f)).await??;
+        if !abs_path {
+            after_upload(&config, id, filepath);
         }
     }
 
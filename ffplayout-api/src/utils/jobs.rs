@@ -0,0 +1,378 @@
+/// Background Jobs
+///
+/// Long running work, like playlist generation, imports, copies and transcoding, runs as a job
+/// outside of the request. Jobs are stored in the database, so queued jobs survive a restart
+/// of the API. Clients poll a job for its progress and result.
+///
+/// Every channel has its own queue, where the jobs run one after another. Transcoding has a
+/// separate queue, so a long conform job not holds back the other jobs from the channel.
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use actix_web::{rt::time::sleep, web};
+use chrono::{NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use simplelog::*;
+
+use crate::db::{
+    handles,
+    models::{Job, LoginUser},
+};
+use crate::utils::{
+    errors::ServiceError,
+    files::{copy_files, copy_paths, norm_abs_path, MoveObject},
+    library::scan_library,
+    playlist::{playlist_references, store_revision, EDIT_LOCK},
+    playout_config,
+};
+use ffplayout_lib::utils::{conform::conform_file, generate_with_progress, import::import_file};
+
+/// Finished jobs are removed after 30 days.
+const KEEP_SECONDS: i64 = 30 * 24 * 3600;
+
+/// Longest date range for one generate job.
+const MAX_GENERATE_DAYS: i64 = 366;
+
+/// Progress and cancel flag from the running job.
+static RUNNING: Lazy<Mutex<HashMap<i64, Arc<JobControl>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Queues with a running job, see `queue`.
+static BUSY: Lazy<Mutex<HashSet<(i32, bool)>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum JobTask {
+    /// Generate playlists from `start` to `end`, or only for `start`.
+    Generate {
+        start: String,
+        #[serde(default)]
+        end: Option<String>,
+    },
+    /// Import playlist file for `date`, the file gets removed afterwards.
+    Import { file: String, date: String },
+    /// Transcode media file to house format, `source` is relative to storage.
+    Conform { source: String },
+    /// Copy file or folder, `target` is an existing folder or the new name.
+    Copy { source: String, target: String },
+    /// Bring the media library in line with the storage.
+    Scan,
+}
+
+impl JobTask {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Generate { .. } => "generate",
+            Self::Import { .. } => "import",
+            Self::Conform { .. } => "conform",
+            Self::Copy { .. } => "copy",
+            Self::Scan => "scan",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct JobControl {
+    progress: Mutex<f64>,
+    cancel: AtomicBool,
+}
+
+impl JobControl {
+    /// Store progress (0.0 to 1.0) and tell the work, if it should go on.
+    fn report(&self, progress: f64) -> bool {
+        *self.progress.lock().unwrap() = (progress * 1000.0).round() / 10.0;
+
+        !self.cancel.load(Ordering::SeqCst)
+    }
+}
+
+fn check_date(date: &str) -> Result<NaiveDate, ServiceError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| ServiceError::BadRequest(format!("Wrong date format: {date}")))
+}
+
+/// Queue from the job: the channel, and if it is a conform job.
+fn queue(job: &Job) -> (i32, bool) {
+    (job.channel_id, job.kind == "conform")
+}
+
+/// Add job to the queue, `user` is `None` for jobs which the API starts by itself.
+pub async fn submit_job(
+    channel_id: i32,
+    user: Option<&LoginUser>,
+    task: &JobTask,
+) -> Result<Job, ServiceError> {
+    let (config, _) = playout_config(&channel_id).await?;
+
+    match task {
+        JobTask::Generate { start, end } => {
+            let first = check_date(start)?;
+
+            if let Some(end) = end {
+                let days = (check_date(end)? - first).num_days();
+
+                if !(0..MAX_GENERATE_DAYS).contains(&days) {
+                    return Err(ServiceError::BadRequest(format!(
+                        "Date range must be in order and not longer than {MAX_GENERATE_DAYS} days!"
+                    )));
+                }
+            }
+        }
+        JobTask::Conform { source } => {
+            if !norm_abs_path(&config.storage.path, source).0.is_file() {
+                return Err(ServiceError::BadRequest("Source file not exists!".into()));
+            }
+        }
+        JobTask::Copy { source, target } => {
+            let obj = MoveObject {
+                source: source.clone(),
+                target: target.clone(),
+            };
+            copy_paths(channel_id, &obj).await?;
+        }
+        JobTask::Import { date, .. } => {
+            check_date(date)?;
+        }
+        JobTask::Scan => {}
+    }
+
+    let job = Job {
+        channel_id,
+        kind: task.kind().to_string(),
+        task: serde_json::to_string(task).map_err(|e| e.to_string())?,
+        user_id: user.map(|u| u.id).unwrap_or_default(),
+        username: user.map(|u| u.username.clone()).unwrap_or_default(),
+        created: Utc::now().timestamp(),
+        ..Default::default()
+    };
+
+    Ok(handles::insert_job(&job).await?)
+}
+
+pub async fn get_job(channel_id: i32, id: i64) -> Result<Job, ServiceError> {
+    handles::select_job(&channel_id, &id)
+        .await
+        .map_err(|_| ServiceError::NoContent(format!("Job {id} not exists!")))
+}
+
+/// Cancel job, a running job stops at its next progress step.
+pub async fn cancel_job(channel_id: i32, id: i64) -> Result<Job, ServiceError> {
+    let job = get_job(channel_id, id).await?;
+
+    if job.status == "queued"
+        && handles::cancel_queued_job(&channel_id, &id, Utc::now().timestamp())
+            .await?
+            .rows_affected()
+            > 0
+    {
+        return get_job(channel_id, id).await;
+    }
+
+    if job.kind == "import" || job.kind == "scan" {
+        return Err(ServiceError::Conflict(format!(
+            "A running {} job can not be cancelled!",
+            job.kind
+        )));
+    }
+
+    match RUNNING.lock().unwrap().get(&id) {
+        Some(control) => control.cancel.store(true, Ordering::SeqCst),
+        None => return Err(ServiceError::Conflict("Job is already finished!".into())),
+    }
+
+    get_job(channel_id, id).await
+}
+
+async fn execute(job: &Job, control: Arc<JobControl>) -> Result<Value, ServiceError> {
+    let task: JobTask = serde_json::from_str(&job.task).map_err(|e| e.to_string())?;
+    let (mut config, channel) = playout_config(&job.channel_id).await?;
+    let user = LoginUser::new(job.user_id, job.username.clone(), String::new());
+
+    match task {
+        JobTask::Generate { start, end } => {
+            // no playlist edit runs between writing and storing the revisions
+            let _lock = EDIT_LOCK.lock().await;
+            config.general.generate = Some(match end {
+                Some(end) => vec![start, "-".to_string(), end],
+                None => vec![start],
+            });
+
+            let playlists = web::block(move || {
+                generate_with_progress(&config, Some(channel.name), &mut |p| control.report(p))
+            })
+            .await?
+            .map_err(|e| ServiceError::Conflict(e.to_string()))?;
+            let dates: Vec<String> = playlists.into_iter().map(|p| p.date).collect();
+
+            for date in &dates {
+                store_revision(job.channel_id, date, &user, "generate").await?;
+            }
+
+            Ok(json!({ "dates": dates }))
+        }
+        JobTask::Import { file, date } => {
+            let _lock = EDIT_LOCK.lock().await;
+
+            // playlists from before the revision history, keep their content before the import
+            if handles::select_revisions(&job.channel_id, &date)
+                .await?
+                .is_empty()
+            {
+                store_revision(job.channel_id, &date, &user, "initial").await?;
+            }
+
+            let (path, day) = (file.clone(), date.clone());
+            let report =
+                web::block(move || import_file(&config, &day, Some(channel.name), &path)).await?;
+
+            if let Err(e) = fs::remove_file(&file) {
+                error!("Remove import file <b><magenta>{file}</></b> failed: {e}");
+            }
+
+            let report = report.map_err(|e| ServiceError::BadRequest(e.to_string()))?;
+            store_revision(job.channel_id, &date, &user, "import").await?;
+
+            Ok(json!(report))
+        }
+        JobTask::Conform { source } => {
+            let (source, _, _) = norm_abs_path(&config.storage.path, &source);
+            let (playlist_root, mut keep) =
+                (config.playlist.path.clone(), config.conform.keep_original);
+            let path = source.clone();
+            let target =
+                web::block(move || conform_file(&config, &path, &mut |p| control.report(p)))
+                    .await?
+                    .map_err(|e| ServiceError::Conflict(e.to_string()))?;
+
+            // playlists which use the original would break, so it stays in this case
+            if !keep && target != source {
+                let path = source.clone();
+                let references =
+                    web::block(move || playlist_references(&playlist_root, &path)).await?;
+
+                if references.is_empty() {
+                    fs::remove_file(&source)?;
+                } else {
+                    keep = true;
+                    warn!(
+                        "Keep original <b><magenta>{}</></b>, it is used in playlists",
+                        source.display()
+                    );
+                }
+            }
+
+            Ok(json!({ "target": target, "original_kept": keep }))
+        }
+        JobTask::Copy { source, target } => {
+            let (source, target) =
+                copy_paths(job.channel_id, &MoveObject { source, target }).await?;
+            let path = target.clone();
+            web::block(move || copy_files(&source, &target, &mut |p| control.report(p)))
+                .await?
+                .map_err(|e| ServiceError::Conflict(e.to_string()))?;
+
+            Ok(json!({ "target": path }))
+        }
+        JobTask::Scan => Ok(json!(scan_library(job.channel_id).await?)),
+    }
+}
+
+/// Write the progress to the database, while the job runs.
+async fn progress_writer(id: i64, control: Arc<JobControl>) {
+    let mut last = 0.0;
+
+    loop {
+        sleep(Duration::from_secs(1)).await;
+        let progress = *control.progress.lock().unwrap();
+
+        if progress != last {
+            if let Err(e) = handles::update_job_progress(&id, progress).await {
+                error!("Update progress from job <yellow>{id}</> failed: {e}");
+            }
+
+            last = progress;
+        }
+    }
+}
+
+async fn run_job(job: Job) {
+    let control = Arc::new(JobControl::default());
+    RUNNING.lock().unwrap().insert(job.id, control.clone());
+
+    if let Err(e) = handles::start_job(&job.id, Utc::now().timestamp()).await {
+        error!("Start job <yellow>{}</> failed: {e}", job.id);
+    }
+
+    info!(
+        "Run <yellow>{}</> job <yellow>{}</> from channel <yellow>{}</>",
+        job.kind, job.id, job.channel_id
+    );
+
+    let writer = actix_web::rt::spawn(progress_writer(job.id, control.clone()));
+    let result = execute(&job, control.clone()).await;
+    writer.abort();
+    RUNNING.lock().unwrap().remove(&job.id);
+
+    let cancelled = control.cancel.load(Ordering::SeqCst);
+    let (status, value, message) = match result {
+        Ok(value) if cancelled => ("cancelled", value.to_string(), String::new()),
+        Ok(value) => ("done", value.to_string(), String::new()),
+        Err(_) if cancelled => ("cancelled", String::new(), String::new()),
+        Err(e) => {
+            error!("Job <yellow>{}</> failed: {e}", job.id);
+            ("failed", String::new(), e.to_string())
+        }
+    };
+
+    if let Err(e) =
+        handles::finish_job(&job.id, status, &value, &message, Utc::now().timestamp()).await
+    {
+        error!("Finish job <yellow>{}</> failed: {e}", job.id);
+    }
+}
+
+/// Run queued jobs, in parallel when they are in different queues.
+pub async fn job_runner() {
+    let now = Utc::now().timestamp();
+
+    if let Err(e) = handles::interrupt_jobs(now).await {
+        error!("Reset running jobs failed: {e}");
+    }
+
+    if let Err(e) = handles::delete_old_jobs(now - KEEP_SECONDS).await {
+        error!("Remove old jobs failed: {e}");
+    }
+
+    loop {
+        match handles::select_queued_jobs().await {
+            Ok(jobs) => {
+                for job in jobs {
+                    let queue = queue(&job);
+
+                    // the oldest job from a free queue starts, the others wait
+                    if BUSY.lock().unwrap().insert(queue) {
+                        actix_web::rt::spawn(async move {
+                            run_job(job).await;
+                            BUSY.lock().unwrap().remove(&queue);
+                        });
+                    }
+                }
+
+                sleep(Duration::from_secs(2)).await;
+            }
+            Err(e) => {
+                error!("Read job queue failed: {e}");
+                sleep(Duration::from_secs(10)).await;
+            }
+        }
+    }
+}
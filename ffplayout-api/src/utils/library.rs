@@ -0,0 +1,336 @@
+/// Media Library
+///
+/// Index from the media files of a channel storage, with duration, codecs, resolution,
+/// category and tags. A scan brings the index in line with the storage, after that a
+/// file watcher keeps it up to date.
+///
+/// The same task asks the playout for the current clip, to know when a file was played last.
+use std::{
+    collections::{HashMap, HashSet},
+    fs::Metadata,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use actix_web::{
+    rt::time::{sleep, timeout},
+    web,
+};
+use chrono::Utc;
+use notify::{
+    watcher,
+    DebouncedEvent::{Create, Remove, Rename, Write},
+    RecursiveMode, Watcher,
+};
+use serde::{Deserialize, Serialize};
+use simplelog::*;
+use walkdir::WalkDir;
+
+use crate::db::{handles, models::MediaItem};
+use crate::utils::{control::media_info, errors::ServiceError, playout_config};
+use ffplayout_lib::utils::{config::Storage, file_extension, is_hidden, MediaProbe};
+
+#[derive(Debug, Default, Serialize)]
+pub struct ScanReport {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MediaUpdate {
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// Tags in lower case, without duplicates and joined by comma.
+pub fn normalize_tags(tags: &[String]) -> String {
+    let mut list: Vec<String> = vec![];
+
+    for tag in tags {
+        let tag = tag.replace(',', " ").trim().to_lowercase();
+
+        if !tag.is_empty() && !list.contains(&tag) {
+            list.push(tag);
+        }
+    }
+
+    list.join(",")
+}
+
+fn modified_secs(meta: &Metadata) -> i64 {
+    meta.modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Media file in storage, files in hidden folders like trash or unfinished uploads are skipped.
+fn is_media(storage: &Storage, path: &Path) -> bool {
+    !is_hidden(&storage.path, path)
+        && file_extension(path)
+            .map(|e| storage.extensions.contains(&e.to_lowercase()))
+            .unwrap_or(false)
+}
+
+/// Read file infos and media data, `None` when the file can not be read.
+pub fn probe_media(channel_id: i32, path: &Path) -> Option<MediaItem> {
+    let meta = path.metadata().ok().filter(|m| m.is_file())?;
+    let probe = MediaProbe::new(&path.to_string_lossy());
+    let video = probe.video_streams.first();
+    let audio = probe.audio_streams.first();
+
+    Some(MediaItem {
+        channel_id,
+        path: path.to_string_lossy().to_string(),
+        name: path.file_name()?.to_string_lossy().to_string(),
+        size: meta.len() as i64,
+        modified: modified_secs(&meta),
+        duration: probe
+            .format
+            .and_then(|f| f.duration)
+            .and_then(|d| d.parse().ok())
+            .unwrap_or_default(),
+        video_codec: video.and_then(|v| v.codec_name.clone()).unwrap_or_default(),
+        audio_codec: audio.and_then(|a| a.codec_name.clone()).unwrap_or_default(),
+        width: video.and_then(|v| v.width).unwrap_or_default(),
+        height: video.and_then(|v| v.height).unwrap_or_default(),
+        ..Default::default()
+    })
+}
+
+/// Indexed duration from file, when the file has not changed since then.
+pub fn indexed_duration(item: &MediaItem, path: &Path) -> Option<f64> {
+    let meta = path.metadata().ok()?;
+
+    if item.size == meta.len() as i64 && item.modified == modified_secs(&meta) {
+        return Some(item.duration);
+    }
+
+    None
+}
+
+async fn index_file(id: i32, path: PathBuf) -> Result<(), ServiceError> {
+    if let Some(item) = web::block(move || probe_media(id, &path)).await? {
+        handles::upsert_media(&item).await?;
+    }
+
+    Ok(())
+}
+
+async fn index_folder(id: i32, path: PathBuf, storage: &Storage) -> Result<(), ServiceError> {
+    let files: Vec<PathBuf> = WalkDir::new(path)
+        .into_iter()
+        .flat_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|p| p.is_file() && is_media(storage, p))
+        .collect();
+    let mut result = Ok(());
+
+    for file in files {
+        result = result.and(index_file(id, file).await);
+    }
+
+    result
+}
+
+/// Bring library in line with the storage, only new and changed files get probed.
+pub async fn scan_library(id: i32) -> Result<ScanReport, ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+    let mut report = ScanReport::default();
+    let mut indexed: HashMap<String, MediaItem> = handles::select_media_files(&id, None)
+        .await?
+        .into_iter()
+        .map(|m| (m.path.clone(), m))
+        .collect();
+    let storage = config.storage.clone();
+    let files: Vec<PathBuf> = web::block(move || {
+        WalkDir::new(&storage.path)
+            .into_iter()
+            .flat_map(|e| e.ok())
+            .map(|e| e.into_path())
+            .filter(|p| p.is_file() && is_media(&storage, p))
+            .collect()
+    })
+    .await?;
+
+    for file in files {
+        match indexed.remove(&file.to_string_lossy().to_string()) {
+            Some(item) if indexed_duration(&item, &file).is_some() => continue,
+            Some(_) => report.updated += 1,
+            None => report.added += 1,
+        }
+
+        index_file(id, file).await?;
+    }
+
+    for path in indexed.keys() {
+        handles::delete_media(&id, path).await?;
+        report.removed += 1;
+    }
+
+    info!(
+        "Scan media library from channel <yellow>{id}</>: {} added, {} updated, {} removed",
+        report.added, report.updated, report.removed
+    );
+
+    Ok(report)
+}
+
+/// All tags from library, with the number of files they are used.
+pub async fn library_tags(id: i32) -> Result<Vec<TagCount>, ServiceError> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    for tags in handles::select_media_tags(&id).await? {
+        let unique: HashSet<&str> = tags.split(',').collect();
+
+        for tag in unique {
+            *counts.entry(tag.to_string()).or_default() += 1;
+        }
+    }
+
+    let mut list: Vec<TagCount> = counts
+        .into_iter()
+        .map(|(tag, count)| TagCount { tag, count })
+        .collect();
+    list.sort_by(|a, b| a.tag.cmp(&b.tag));
+
+    Ok(list)
+}
+
+pub async fn update_media(
+    id: i32,
+    media_id: i32,
+    update: &MediaUpdate,
+) -> Result<MediaItem, ServiceError> {
+    let item = handles::select_media_item(&id, &media_id).await?;
+    let category = update.category.clone().unwrap_or(item.category);
+    let tags = match &update.tags {
+        Some(t) => normalize_tags(t),
+        None => item.tags,
+    };
+
+    handles::update_media_info(&id, &media_id, category.trim(), &tags).await?;
+
+    Ok(handles::select_media_item(&id, &media_id).await?)
+}
+
+async fn sync_event(id: i32, storage: &Storage, event: notify::DebouncedEvent) {
+    let result = match event {
+        Create(path) | Write(path) if is_media(storage, &path) => index_file(id, path).await,
+        // files moved in with a folder get no own events
+        Create(path) if path.is_dir() && !is_hidden(&storage.path, &path) => {
+            index_folder(id, path, storage).await
+        }
+        Remove(path) => handles::delete_media(&id, &path.to_string_lossy())
+            .await
+            .map(|_| ())
+            .map_err(ServiceError::from),
+        Rename(old, new)
+            if (new.is_dir() && !is_hidden(&storage.path, &new)) || is_media(storage, &new) =>
+        {
+            let name = new.file_name().unwrap_or_default().to_string_lossy();
+
+            let result =
+                handles::rename_media(&id, &old.to_string_lossy(), &new.to_string_lossy(), &name)
+                    .await;
+
+            match result {
+                // files from hidden folders, like finished uploads or restored trash,
+                // are not indexed yet
+                Ok(r) if r.rows_affected() == 0 && new.is_file() => index_file(id, new).await,
+                Ok(r) if r.rows_affected() == 0 => index_folder(id, new, storage).await,
+                Ok(_) => Ok(()),
+                Err(e) => Err(ServiceError::from(e)),
+            }
+        }
+        Rename(old, _) => handles::delete_media(&id, &old.to_string_lossy())
+            .await
+            .map(|_| ())
+            .map_err(ServiceError::from),
+        _ => Ok(()),
+    };
+
+    if let Err(e) = result {
+        error!("Update media library from channel <yellow>{id}</> failed: {e}");
+    }
+}
+
+/// Source from the clip which plays now, `None` when the playout is not reachable.
+async fn current_source(id: i32) -> Option<String> {
+    let request = async {
+        let response = media_info(id, "current".into()).await.ok()?;
+        response.text().await.ok()
+    };
+
+    // a hanging playout must not stop the library sync
+    let text = timeout(Duration::from_secs(3), request).await.ok()??;
+    let value: serde_json::Value = serde_json::from_str(&text).ok()?;
+
+    value["result"]["current_media"]["source"]
+        .as_str()
+        .map(|s| s.to_string())
+}
+
+fn watch_storage(
+    path: &str,
+) -> Option<(notify::RecommendedWatcher, Receiver<notify::DebouncedEvent>)> {
+    let (tx, rx) = channel();
+    let mut watch = watcher(tx, Duration::from_secs(2)).ok()?;
+
+    if let Err(e) = watch.watch(path, RecursiveMode::Recursive) {
+        error!("Watch storage <b><magenta>{path}</></b> failed: {e}");
+        return None;
+    }
+
+    Some((watch, rx))
+}
+
+/// Scan the library of a channel and keep it in sync, until the channel gets deleted.
+pub async fn library_sync(id: i32) {
+    let config = match playout_config(&id).await {
+        Ok((c, _)) => c,
+        Err(_) => return,
+    };
+
+    if let Err(e) = scan_library(id).await {
+        error!("Scan media library from channel <yellow>{id}</> failed: {e}");
+    }
+
+    // keep the watcher alive, the events come over the receiver
+    let (_watch, rx) = match watch_storage(&config.storage.path) {
+        Some(w) => w,
+        None => return,
+    };
+    let mut last_source = String::new();
+
+    while handles::select_channel(&id).await.is_ok() {
+        while let Ok(event) = rx.try_recv() {
+            sync_event(id, &config.storage, event).await;
+        }
+
+        if let Some(source) = current_source(id).await {
+            if source != last_source {
+                if let Err(e) =
+                    handles::update_last_played(&id, &source, Utc::now().timestamp()).await
+                {
+                    error!("Update last played time failed: {e}");
+                }
+
+                last_source = source;
+            }
+        }
+
+        sleep(Duration::from_secs(5)).await;
+    }
+}
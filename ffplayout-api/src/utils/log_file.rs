@@ -0,0 +1,484 @@
+/// Log File Reader
+///
+/// Read the engine log from a channel, filter it by level, time range, process unit
+/// and text, and follow new lines for the live view.
+/// Plain text logs and JSON lines logs are both supported.
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+use actix_web::{
+    rt::time::sleep,
+    web::{self, Bytes},
+};
+use futures_util::{stream, Stream};
+use serde::{Deserialize, Serialize};
+use simplelog::*;
+
+use crate::utils::{errors::ServiceError, playout_config};
+use ffplayout_lib::utils::JsonLine;
+
+/// Interval in milliseconds, in which the live tail looks for new lines.
+const TAIL_INTERVAL: u64 = 1000;
+
+/// After this number of idle intervals, the live tail sends a keep alive comment.
+const KEEP_ALIVE: u32 = 15;
+
+/// Bytes from the end of the log, which are read first for the latest errors.
+const ERROR_WINDOW: u64 = 64 * 1024;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct LogFilter {
+    #[serde(default)]
+    pub date: String,
+    #[serde(default)]
+    pub level: Option<String>,
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub search: Option<String>,
+    #[serde(default)]
+    pub page: Option<usize>,
+    #[serde(default)]
+    pub per_page: Option<usize>,
+}
+
+/// Log entry without the raw text, for lists like the latest errors.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogMessage {
+    pub timestamp: String,
+    pub unit: String,
+    pub message: String,
+}
+
+/// One log entry, with its following lines (for example multi line ffmpeg errors).
+#[derive(Debug, Default, Clone)]
+struct LogEntry {
+    timestamp: String,
+    level: String,
+    unit: String,
+    message: String,
+    text: String,
+}
+
+fn level_rank(level: &str) -> u8 {
+    match level.trim().to_lowercase().as_str() {
+        "trace" => 0,
+        "debug" => 1,
+        "info" => 2,
+        "warn" | "warning" => 3,
+        _ => 4,
+    }
+}
+
+/// Bring timestamps from plain text and JSON logs to the form: `2022-06-20 12:00:00`.
+fn normalize_time(time: &str) -> String {
+    time.replacen('T', " ", 1).chars().take(19).collect()
+}
+
+/// Compare a log timestamp with a time filter, the filter can be a full date time or only a time.
+fn time_cmp(timestamp: &str, filter: &str) -> std::cmp::Ordering {
+    let filter = normalize_time(filter);
+
+    if filter.len() <= 8 {
+        let time = timestamp.split_once(' ').map(|t| t.1).unwrap_or(timestamp);
+        return time[..filter.len().min(time.len())].cmp(&filter);
+    }
+
+    timestamp[..filter.len().min(timestamp.len())].cmp(&filter)
+}
+
+/// Split message in process unit and message text, the unit is the prefix like "[Decoder]".
+fn split_unit(message: &str) -> (String, String) {
+    if let Some(rest) = message.strip_prefix('[') {
+        if let Some((unit, msg)) = rest.split_once("] ") {
+            if !unit.is_empty() && unit.chars().all(char::is_alphanumeric) {
+                return (unit.to_string(), msg.to_string());
+            }
+        }
+    }
+
+    (String::new(), message.to_string())
+}
+
+/// Parse a log line, in format: `[2022-06-20 12:00:00.12345] [ INFO] [Decoder] message`.
+///
+/// The timestamp is optional. Lines without level belongs to the entry before.
+fn parse_line(line: &str) -> Option<LogEntry> {
+    if line.starts_with('{') {
+        if let Ok(json) = serde_json::from_str::<JsonLine>(line) {
+            return Some(LogEntry {
+                timestamp: normalize_time(&json.timestamp),
+                level: json.level.to_uppercase(),
+                unit: json.unit.unwrap_or_default(),
+                message: json.message,
+                text: line.to_string(),
+            });
+        }
+    }
+
+    let mut rest = line;
+    let mut timestamp = String::new();
+
+    if rest.starts_with("[2") || rest.starts_with("[1") {
+        let (time, r) = rest[1..].split_once("] ")?;
+        timestamp = normalize_time(time);
+        rest = r;
+    }
+
+    let (level, message) = rest.strip_prefix('[')?.split_once("] ")?;
+
+    if !["TRACE", "DEBUG", "INFO", "WARN", "ERROR"].contains(&level.trim()) {
+        return None;
+    }
+
+    let (unit, message) = split_unit(message);
+
+    Some(LogEntry {
+        timestamp,
+        level: level.trim().to_string(),
+        unit,
+        message,
+        text: line.to_string(),
+    })
+}
+
+/// Parse log lines one by one and give every complete entry to `entry_fn`.
+fn parse_lines<I, F>(lines: I, mut entry_fn: F)
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+    F: FnMut(LogEntry),
+{
+    let mut current: Option<LogEntry> = None;
+
+    for line in lines {
+        let line = line.as_ref();
+
+        match parse_line(line) {
+            Some(entry) => {
+                if let Some(last) = current.replace(entry) {
+                    entry_fn(last);
+                }
+            }
+            None => match current.as_mut() {
+                Some(last) => {
+                    last.message.push('\n');
+                    last.message.push_str(line);
+                    last.text.push('\n');
+                    last.text.push_str(line);
+                }
+                None => {
+                    current = Some(LogEntry {
+                        message: line.to_string(),
+                        text: line.to_string(),
+                        ..Default::default()
+                    })
+                }
+            },
+        }
+    }
+
+    if let Some(last) = current {
+        entry_fn(last);
+    }
+}
+
+fn parse_log(content: &str) -> Vec<LogEntry> {
+    let mut entries = vec![];
+
+    parse_lines(content.lines(), |entry| entries.push(entry));
+
+    entries
+}
+
+impl LogFilter {
+    fn is_active(&self) -> bool {
+        self.level.is_some()
+            || self.from.is_some()
+            || self.to.is_some()
+            || self.unit.is_some()
+            || self.search.is_some()
+    }
+
+    fn matches(&self, entry: &LogEntry) -> bool {
+        if let Some(level) = &self.level {
+            if level_rank(&entry.level) < level_rank(level) {
+                return false;
+            }
+        }
+
+        if let Some(from) = &self.from {
+            if entry.timestamp.is_empty() || time_cmp(&entry.timestamp, from).is_lt() {
+                return false;
+            }
+        }
+
+        if let Some(to) = &self.to {
+            if entry.timestamp.is_empty() || time_cmp(&entry.timestamp, to).is_gt() {
+                return false;
+            }
+        }
+
+        if let Some(unit) = &self.unit {
+            if !entry.unit.eq_ignore_ascii_case(unit) {
+                return false;
+            }
+        }
+
+        if let Some(search) = &self.search {
+            if !entry
+                .message
+                .to_lowercase()
+                .contains(&search.to_lowercase())
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Filter log lines and return the requested page, together with the number of all matches.
+    ///
+    /// The lines are read one by one, only the entries from the requested page are kept.
+    pub fn apply<R: BufRead>(&self, reader: R) -> Result<(String, usize), std::io::Error> {
+        let mut read_error = None;
+        let lines = reader.split(b'\n').map_while(|line| match line {
+            Ok(line) => Some(
+                String::from_utf8_lossy(&line)
+                    .trim_end_matches('\r')
+                    .to_string(),
+            ),
+            Err(e) => {
+                read_error = Some(e);
+                None
+            }
+        });
+
+        let mut total = 0;
+        let mut page_entries = vec![];
+
+        if !self.is_active() && self.per_page.is_none() {
+            for line in lines {
+                page_entries.push(line);
+                total += 1;
+            }
+        } else {
+            let per_page = self.per_page.unwrap_or(usize::MAX).max(1);
+            let skip = (self.page.unwrap_or(1).max(1) - 1).saturating_mul(per_page);
+
+            parse_lines(lines, |entry| {
+                if self.matches(&entry) {
+                    if total >= skip && page_entries.len() < per_page {
+                        page_entries.push(entry.text);
+                    }
+
+                    total += 1;
+                }
+            });
+        }
+
+        if let Some(e) = read_error {
+            return Err(e);
+        }
+
+        Ok((page_entries.join("\n"), total))
+    }
+}
+
+async fn log_path(channel_id: &i32, date: &str) -> Result<String, ServiceError> {
+    let (config, _) = playout_config(channel_id).await?;
+    let mut log_path = Path::new(&config.logging.log_path)
+        .join("ffplayout.log")
+        .display()
+        .to_string();
+
+    if !date.is_empty() {
+        log_path.push('.');
+        log_path.push_str(date);
+    }
+
+    Ok(log_path)
+}
+
+/// Newest error entries from the log file, the file is read from the end, until enough are found.
+fn tail_errors(path: &str, count: usize) -> Result<Vec<LogMessage>, std::io::Error> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut window = ERROR_WINDOW;
+
+    loop {
+        let start = size.saturating_sub(window);
+        let mut buffer = vec![];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_to_end(&mut buffer)?;
+
+        let mut content = String::from_utf8_lossy(&buffer).to_string();
+
+        // the first line can be cut
+        if start > 0 {
+            let cut = content.find('\n').map_or(content.len(), |i| i + 1);
+            content.drain(..cut);
+        }
+
+        let errors: Vec<LogMessage> = parse_log(&content)
+            .into_iter()
+            .rev()
+            .filter(|e| e.level == "ERROR")
+            .take(count)
+            .map(|e| LogMessage {
+                timestamp: e.timestamp,
+                unit: e.unit,
+                message: e.message,
+            })
+            .collect();
+
+        if errors.len() >= count || start == 0 {
+            return Ok(errors);
+        }
+
+        window *= 4;
+    }
+}
+
+/// Newest error entries from the current log, newest first.
+pub async fn latest_errors(
+    channel_id: &i32,
+    count: usize,
+) -> Result<Vec<LogMessage>, ServiceError> {
+    let path = log_path(channel_id, "").await?;
+    let errors = web::block(move || tail_errors(&path, count)).await??;
+
+    Ok(errors)
+}
+
+/// Read log file from channel and apply the filter, returns the log text and the number of entries.
+pub async fn read_log_file(
+    channel_id: &i32,
+    filter: &LogFilter,
+) -> Result<(String, usize), ServiceError> {
+    if let Ok(log_path) = log_path(channel_id, &filter.date).await {
+        let file = File::open(log_path)?;
+
+        return Ok(filter.apply(BufReader::new(file))?);
+    }
+
+    Err(ServiceError::NoContent(
+        "Requested log file not exists, or not readable.".to_string(),
+    ))
+}
+
+struct TailState {
+    path: String,
+    position: u64,
+    rest: Vec<u8>,
+    idle: u32,
+    filter: LogFilter,
+}
+
+impl TailState {
+    /// Read new content from log file, begin again from the start when the file got rotated.
+    fn read_new(&mut self) -> Result<String, std::io::Error> {
+        let mut file = File::open(&self.path)?;
+        let len = file.metadata()?.len();
+
+        if len < self.position {
+            self.position = 0;
+            self.rest.clear();
+        }
+
+        file.seek(SeekFrom::Start(self.position))?;
+        self.position += file.read_to_end(&mut self.rest)? as u64;
+
+        // keep unfinished line for the next round, it can also end with an incomplete character
+        let content = match self.rest.iter().rposition(|b| *b == b'\n') {
+            Some(pos) => {
+                let content = String::from_utf8_lossy(&self.rest[..pos]).to_string();
+                self.rest.drain(..=pos);
+                content
+            }
+            None => String::new(),
+        };
+
+        Ok(content)
+    }
+
+    fn event(&mut self) -> Option<Bytes> {
+        let content = match self.read_new() {
+            Ok(content) => content,
+            Err(e) => {
+                error!(
+                    "Unable to read log file <b><magenta>{}</></b>: {e}",
+                    self.path
+                );
+                String::new()
+            }
+        };
+        let mut event = String::new();
+
+        for entry in parse_log(&content) {
+            if self.filter.matches(&entry) {
+                for line in entry.text.lines() {
+                    event.push_str(&format!("data: {line}\n"));
+                }
+
+                event.push('\n');
+            }
+        }
+
+        if !event.is_empty() {
+            self.idle = 0;
+            return Some(Bytes::from(event));
+        }
+
+        self.idle += 1;
+
+        if self.idle >= KEEP_ALIVE {
+            self.idle = 0;
+            return Some(Bytes::from_static(b": keep-alive\n\n"));
+        }
+
+        None
+    }
+}
+
+/// Follow the current log file and send new lines as server-sent events.
+///
+/// The stream starts at the end of the file, every log entry is one event.
+pub async fn tail_log_file(
+    channel_id: &i32,
+    filter: LogFilter,
+) -> Result<impl Stream<Item = Result<Bytes, std::io::Error>>, ServiceError> {
+    let path = log_path(channel_id, "").await?;
+    let position = fs::metadata(&path)
+        .map_err(|_| {
+            ServiceError::NoContent("Requested log file not exists, or not readable.".to_string())
+        })?
+        .len();
+
+    let state = TailState {
+        path,
+        position,
+        rest: vec![],
+        idle: 0,
+        filter,
+    };
+
+    Ok(stream::unfold(state, |mut state| async move {
+        loop {
+            sleep(Duration::from_millis(TAIL_INTERVAL)).await;
+
+            if let Some(event) = state.event() {
+                return Some((Ok(event), state));
+            }
+        }
+    }))
+}
@@ -1,6 +1,6 @@
 use std::{
     error::Error,
-    fs::{self, File},
+    fs::File,
     io::{stdin, stdout, Write},
     path::Path,
 };
@@ -12,19 +12,40 @@ use rpassword::read_password;
 use simplelog::*;
 
 pub mod args_parse;
+pub mod audit;
 pub mod channels;
 pub mod control;
 pub mod errors;
 pub mod files;
+pub mod jobs;
+pub mod library;
+pub mod log_file;
+pub mod overview;
 pub mod playlist;
+pub mod playlist_edit;
+#[cfg(target_os = "linux")]
+pub mod supervisor;
+pub mod thumbnails;
+pub mod trash;
+pub mod upload;
+pub mod users;
 
 use crate::db::{
-    handles::{db_init, insert_user, select_channel, select_global},
-    models::{Channel, User},
+    handles::{
+        db_init, insert_user, select_channel, select_global, select_user, select_user_channel,
+    },
+    migrations::{db_status, run_migrations},
+    models::{Channel, LoginUser, User},
+};
+use crate::utils::{
+    args_parse::Args,
+    errors::ServiceError,
+    users::{change_user, list_users, remove_user, UserUpdate},
 };
-use crate::utils::{args_parse::Args, errors::ServiceError};
 use ffplayout_lib::utils::PlayoutConfig;
 
+pub use ffplayout_lib::utils::access::ChannelRole;
+
 #[derive(Clone, Eq, PartialEq)]
 pub enum Role {
     Admin,
@@ -42,6 +63,29 @@ impl Role {
     }
 }
 
+/// Check if the user has at least the needed role for the channel.
+///
+/// Global admins have access to every channel.
+pub async fn check_channel(
+    user: &LoginUser,
+    channel_id: &i32,
+    needed: ChannelRole,
+) -> Result<(), ServiceError> {
+    if Role::set_role(&user.role) == Role::Admin {
+        return Ok(());
+    }
+
+    if let Ok(permission) = select_user_channel(&user.id, channel_id).await {
+        if ChannelRole::allows(&permission.role, needed) {
+            return Ok(());
+        }
+    }
+
+    Err(ServiceError::Forbidden(format!(
+        "No permission for channel {channel_id}!"
+    )))
+}
+
 #[derive(Debug, sqlx::FromRow)]
 pub struct GlobalSettings {
     pub secret: String,
@@ -88,7 +132,111 @@ pub fn db_path() -> Result<String, Box<dyn std::error::Error>> {
     Ok(db_path)
 }
 
+/// Run user management from command line.
+async fn manage_users(args: &Args) -> Result<(), ServiceError> {
+    run_migrations().await?;
+
+    if args.list_users {
+        for user in list_users().await? {
+            println!(
+                "{:>4}  {:<20} {:<30} {:<6} {}",
+                user.id,
+                user.username,
+                user.mail,
+                user.role.unwrap_or_default(),
+                if user.disabled { "disabled" } else { "" }
+            );
+        }
+    }
+
+    if let Some(username) = &args.delete_user {
+        let user = select_user(username).await?;
+        remove_user(user.id, None).await?;
+    }
+
+    if let Some(username) = &args.change_role {
+        let user = select_user(username).await?;
+        let data = UserUpdate {
+            role: Some(args.role.clone().ok_or_else(|| {
+                ServiceError::BadRequest("New role is missing, set it with --role!".to_string())
+            })?),
+            ..Default::default()
+        };
+
+        change_user(user.id, None, &data).await?;
+    }
+
+    if let Some(username) = &args.reset_password {
+        let user = select_user(username).await?;
+        let password = match args.password.clone() {
+            Some(p) => p,
+            None => {
+                print!("New password: ");
+                stdout().flush().unwrap();
+                read_password()?
+            }
+        };
+        let data = UserUpdate {
+            password: Some(password),
+            ..Default::default()
+        };
+
+        change_user(user.id, None, &data).await?;
+    }
+
+    for (name, disabled) in [(&args.disable_user, true), (&args.enable_user, false)] {
+        if let Some(username) = name {
+            let user = select_user(username).await?;
+            let data = UserUpdate {
+                disabled: Some(disabled),
+                ..Default::default()
+            };
+
+            change_user(user.id, None, &data).await?;
+        }
+    }
+
+    Ok(())
+}
+
 pub async fn run_args(mut args: Args) -> Result<(), i32> {
+    if args.migrate {
+        match run_migrations().await {
+            Ok(0) => info!("Database is up to date"),
+            Ok(count) => info!("Database migrated, {count} migration(s) applied"),
+            Err(e) => {
+                error!("Migrate database failed: {e}");
+                return Err(1);
+            }
+        }
+
+        return Err(0);
+    }
+
+    if args.db_status {
+        if let Err(e) = db_status().await {
+            error!("Read database status failed: {e}");
+            return Err(1);
+        }
+
+        return Err(0);
+    }
+
+    if args.list_users
+        || args.delete_user.is_some()
+        || args.change_role.is_some()
+        || args.reset_password.is_some()
+        || args.disable_user.is_some()
+        || args.enable_user.is_some()
+    {
+        if let Err(e) = manage_users(&args).await {
+            error!("{e}");
+            return Err(1);
+        }
+
+        return Err(0);
+    }
+
     if !args.init && args.listen.is_none() && !args.ask && args.username.is_none() {
         error!("Wrong number of arguments! Run ffpapi --help for more information.");
 
@@ -157,7 +305,11 @@ pub async fn run_args(mut args: Args) -> Result<(), i32> {
             salt: None,
             role_id: Some(1),
             channel_id: Some(1),
+            channel_role: None,
             token: None,
+            refresh_token: None,
+            disabled: false,
+            tokens_valid_after: 0,
         };
 
         if let Err(e) = insert_user(user).await {
@@ -192,33 +344,6 @@ pub async fn playout_config(channel_id: &i32) -> Result<(PlayoutConfig, Channel)
     ))
 }
 
-pub async fn read_log_file(channel_id: &i32, date: &str) -> Result<String, ServiceError> {
-    if let Ok(channel) = select_channel(channel_id).await {
-        let mut date_str = "".to_string();
-
-        if !date.is_empty() {
-            date_str.push('.');
-            date_str.push_str(date);
-        }
-
-        if let Ok(config) = read_playout_config(&channel.config_path) {
-            let mut log_path = Path::new(&config.logging.log_path)
-                .join("ffplayout.log")
-                .display()
-                .to_string();
-            log_path.push_str(&date_str);
-
-            let file = fs::read_to_string(log_path)?;
-
-            return Ok(file);
-        }
-    }
-
-    Err(ServiceError::NoContent(
-        "Requested log file not exists, or not readable.".to_string(),
-    ))
-}
-
 pub fn local_utc_offset() -> i32 {
     let mut offset = Local::now().format("%:z").to_string();
     let operator = offset.remove(0);
@@ -0,0 +1,101 @@
+/// Channel Overview
+///
+/// State from all channels of a user in one response: service, current and next clip,
+/// ingest, sync delta and the latest errors. The channels are asked in parallel and every
+/// call has a timeout, a channel which does not answer gets empty values.
+use std::time::Duration;
+
+use actix_web::rt::time::timeout;
+use futures_util::future::{join4, join_all};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::db::{
+    handles,
+    models::{Channel, LoginUser},
+};
+use crate::utils::{
+    control::{control_service, media_info},
+    errors::ServiceError,
+    log_file::{latest_errors, LogMessage},
+    Role,
+};
+
+/// Seconds to wait for a channel, for each call.
+const TIMEOUT: u64 = 3;
+
+/// Number of errors from the log.
+const ERROR_COUNT: usize = 5;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ChannelOverview {
+    pub id: i32,
+    pub name: String,
+    /// Service state, `unknown` when it is not readable.
+    pub state: String,
+    pub current: Option<Value>,
+    pub next: Option<Value>,
+    pub remaining_sec: Option<f64>,
+    pub ingest_runs: Option<bool>,
+    pub sync_delta: Option<f64>,
+    pub errors: Vec<LogMessage>,
+}
+
+/// Result from the playout RPC server, `None` when there is no clip or no answer.
+async fn media_result(id: i32, command: &str) -> Option<Value> {
+    let request = async {
+        let response = media_info(id, command.to_string()).await.ok()?;
+        response.json::<Value>().await.ok()
+    };
+
+    timeout(Duration::from_secs(TIMEOUT), request)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|v| v.get("result").filter(|r| r.is_object()).cloned())
+}
+
+async fn service_state(id: i32) -> String {
+    match timeout(Duration::from_secs(TIMEOUT), control_service(id, "status")).await {
+        Ok(Ok(state)) => state,
+        _ => "unknown".to_string(),
+    }
+}
+
+async fn overview(channel: Channel) -> ChannelOverview {
+    let (state, current, next, errors) = join4(
+        service_state(channel.id),
+        media_result(channel.id, "current"),
+        media_result(channel.id, "next"),
+        timeout(
+            Duration::from_secs(TIMEOUT),
+            latest_errors(&channel.id, ERROR_COUNT),
+        ),
+    )
+    .await;
+    let current = current.unwrap_or_default();
+
+    ChannelOverview {
+        id: channel.id,
+        name: channel.name,
+        state,
+        current: current.get("current_media").cloned(),
+        next: next.and_then(|n| n.get("current_media").cloned()),
+        remaining_sec: current["remaining_sec"].as_f64(),
+        ingest_runs: current["ingest_runs"].as_bool(),
+        sync_delta: current["sync_delta"].as_f64(),
+        errors: errors.ok().and_then(|e| e.ok()).unwrap_or_default(),
+    }
+}
+
+/// Overview from all channels, which the user may see.
+pub async fn channel_overview(user: &LoginUser) -> Result<Vec<ChannelOverview>, ServiceError> {
+    let mut channels = handles::select_all_channels().await?;
+
+    if Role::set_role(&user.role) != Role::Admin {
+        let permissions = handles::select_user_channels(&user.id).await?;
+        channels.retain(|c| permissions.iter().any(|p| p.channel_id == c.id));
+    }
+
+    Ok(join_all(channels.into_iter().map(overview)).await)
+}
@@ -1,21 +1,210 @@
-use std::{fs, path::PathBuf};
+use std::{fs, path::Path};
 
+use actix_web::web;
+use chrono::{Local, NaiveDate, Utc};
+use futures_util::lock::Mutex;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use simplelog::*;
+use walkdir::WalkDir;
 
+use crate::db::{
+    handles::{insert_revision, select_revision, select_revisions},
+    models::{LoginUser, PlaylistRevision},
+};
 use crate::utils::{errors::ServiceError, playout_config};
 use ffplayout_lib::utils::{
-    generate_playlist as playlist_generator, json_reader, json_writer, JsonPlaylist,
+    json_reader, json_writer,
+    playlist_copy::{copy_program, date_range, playlist_path, CopyOptions, CopyReport},
+    JsonPlaylist, Media,
 };
 
+// Read, check and write from a playlist must not run in parallel.
+pub static EDIT_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Copy or move request, `target` is a date or a range like: `["2022-06-21", "2022-06-26"]`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CopyRequest {
+    pub date: String,
+    pub target: Vec<String>,
+    #[serde(default, rename = "move")]
+    pub remove_source: bool,
+    #[serde(flatten)]
+    pub options: CopyOptions,
+}
+
+/// One changed clip between two playlist revisions.
+///
+/// Removed clips have the index from the older revision, added clips from the newer one.
+#[derive(Debug, Serialize)]
+pub struct ClipChange {
+    pub op: String,
+    pub index: usize,
+    pub clip: Media,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevisionDiff {
+    pub from: i32,
+    pub to: i32,
+    pub changes: Vec<ClipChange>,
+}
+
+/// Clip from a playlist, which uses a file from storage.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistReference {
+    pub date: String,
+    pub index: usize,
+    pub source: String,
+}
+
+/// Source is the path itself, or a file inside of it when the path is a folder.
+pub fn uses_path(source: &str, path: &str) -> bool {
+    let path = path.trim_end_matches('/');
+
+    source == path
+        || source
+            .strip_prefix(path)
+            .map(|rest| rest.starts_with('/'))
+            .unwrap_or(false)
+}
+
+/// Find clips in the playlists from today on, which use `path`.
+/// Older playlists are not checked, they are played already.
+pub fn playlist_references(playlist_root: &str, path: &Path) -> Vec<PlaylistReference> {
+    let today = Local::now().naive_local().date();
+    let path = path.to_string_lossy();
+    let mut references = vec![];
+
+    let mut files: Vec<_> = WalkDir::new(playlist_root)
+        .into_iter()
+        .flat_map(|e| e.ok())
+        .filter_map(|e| {
+            if e.path().extension()? != "json" {
+                return None;
+            }
+
+            let date = e.path().file_stem()?.to_string_lossy().to_string();
+            let day = NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()?;
+
+            (day >= today).then(|| (date, e.into_path()))
+        })
+        .collect();
+    files.sort();
+
+    for (date, file) in files {
+        if let Ok(playlist) = json_reader(&file) {
+            for (index, clip) in playlist.program.iter().enumerate() {
+                if uses_path(&clip.source, &path) {
+                    references.push(PlaylistReference {
+                        date: date.clone(),
+                        index,
+                        source: clip.source.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    references
+}
+
+/// Dates from the references, for error messages.
+pub fn reference_dates(references: &[PlaylistReference]) -> String {
+    let mut dates: Vec<&str> = references.iter().map(|r| r.date.as_str()).collect();
+    dates.dedup();
+
+    dates.join(", ")
+}
+
+async fn add_revision(
+    id: i32,
+    date: &str,
+    user: &LoginUser,
+    action: &str,
+    playlist: &JsonPlaylist,
+) -> Result<(), ServiceError> {
+    let revision = PlaylistRevision {
+        id: 0,
+        channel_id: id,
+        date: date.to_string(),
+        timestamp: Utc::now().timestamp(),
+        user_id: user.id,
+        username: user.username.clone(),
+        action: action.to_string(),
+        data: serde_json::to_string(playlist).map_err(|e| e.to_string())?,
+    };
+
+    insert_revision(&revision).await?;
+
+    Ok(())
+}
+
+/// Save the current playlist file as revision, for playlists which are not written by the API,
+/// like generated or imported ones.
+pub async fn store_revision(
+    id: i32,
+    date: &str,
+    user: &LoginUser,
+    action: &str,
+) -> Result<(), ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+    let path = playlist_path(&config.playlist.path, date);
+
+    if let Ok(playlist) = json_reader(&path) {
+        add_revision(id, date, user, action, &playlist).await?;
+    }
+
+    Ok(())
+}
+
+/// Point the clips from `references` to the new path, after a file or folder was moved.
+/// Each changed playlist gets a revision, so the change can be undone.
+///
+/// Returns the dates from the changed playlists.
+pub async fn rewrite_references(
+    id: i32,
+    references: &[PlaylistReference],
+    old: &Path,
+    new: &Path,
+    user: &LoginUser,
+) -> Result<Vec<String>, ServiceError> {
+    // the playlists are read again under the lock, so no parallel edit gets lost
+    let _lock = EDIT_LOCK.lock().await;
+    let (config, _) = playout_config(&id).await?;
+    let old = old.to_string_lossy().trim_end_matches('/').to_string();
+    let new = new.to_string_lossy().trim_end_matches('/').to_string();
+    let mut dates: Vec<String> = references.iter().map(|r| r.date.clone()).collect();
+    dates.dedup();
+
+    for date in &dates {
+        let path = playlist_path(&config.playlist.path, date);
+        let mut playlist =
+            json_reader(&path).map_err(|e| ServiceError::NoContent(e.to_string()))?;
+
+        // playlists from before the revision history, keep their content before the change
+        if select_revisions(&id, date).await?.is_empty() {
+            add_revision(id, date, user, "initial", &playlist).await?;
+        }
+
+        for clip in playlist.program.iter_mut() {
+            if uses_path(&clip.source, &old) {
+                clip.source = format!("{new}{}", &clip.source[old.len()..]);
+            }
+        }
+
+        json_writer(&path, playlist.clone())?;
+        add_revision(id, date, user, "rewrite", &playlist).await?;
+
+        info!("Rewrite references to <b><magenta>{old}</></b> in playlist from <yellow>{date}</>");
+    }
+
+    Ok(dates)
+}
+
 pub async fn read_playlist(id: i32, date: String) -> Result<JsonPlaylist, ServiceError> {
     let (config, _) = playout_config(&id).await?;
-    let mut playlist_path = PathBuf::from(&config.playlist.path);
-    let d: Vec<&str> = date.split('-').collect();
-    playlist_path = playlist_path
-        .join(d[0])
-        .join(d[1])
-        .join(date.clone())
-        .with_extension("json");
+    let playlist_path = playlist_path(&config.playlist.path, &date);
 
     match json_reader(&playlist_path) {
         Ok(p) => Ok(p),
@@ -23,16 +212,14 @@ pub async fn read_playlist(id: i32, date: String) -> Result<JsonPlaylist, Servic
     }
 }
 
-pub async fn write_playlist(id: i32, json_data: JsonPlaylist) -> Result<String, ServiceError> {
+pub async fn write_playlist(
+    id: i32,
+    json_data: JsonPlaylist,
+    user: &LoginUser,
+) -> Result<String, ServiceError> {
     let (config, _) = playout_config(&id).await?;
     let date = json_data.date.clone();
-    let mut playlist_path = PathBuf::from(&config.playlist.path);
-    let d: Vec<&str> = date.split('-').collect();
-    playlist_path = playlist_path
-        .join(d[0])
-        .join(d[1])
-        .join(date.clone())
-        .with_extension("json");
+    let playlist_path = playlist_path(&config.playlist.path, &date);
     let mut file_exists = false;
 
     if let Some(p) = playlist_path.parent() {
@@ -47,11 +234,20 @@ pub async fn write_playlist(id: i32, json_data: JsonPlaylist) -> Result<String,
                     "Playlist from {date}, already exists!"
                 )));
             }
+
+            // playlists from before the revision history, keep their content before overwrite
+            if select_revisions(&id, &date).await?.is_empty() {
+                add_revision(id, &date, user, "initial", &existing_data).await?;
+            }
         }
     }
 
+    let revision_data = json_data.clone();
+
     match json_writer(&playlist_path, json_data) {
         Ok(_) => {
+            add_revision(id, &date, user, "save", &revision_data).await?;
+
             let mut msg = format!("Write playlist from {date} success!");
 
             if file_exists {
@@ -68,38 +264,22 @@ pub async fn write_playlist(id: i32, json_data: JsonPlaylist) -> Result<String,
     Err(ServiceError::InternalServerError)
 }
 
-pub async fn generate_playlist(id: i32, date: String) -> Result<JsonPlaylist, ServiceError> {
-    let (mut config, channel) = playout_config(&id).await?;
-    config.general.generate = Some(vec![date.clone()]);
+/// Delete playlist, the last content stays as revision and can be restored.
+pub async fn delete_playlist(id: i32, date: &str, user: &LoginUser) -> Result<(), ServiceError> {
+    let _lock = EDIT_LOCK.lock().await;
 
-    match playlist_generator(&config, Some(channel.name)) {
-        Ok(playlists) => {
-            if !playlists.is_empty() {
-                Ok(playlists[0].clone())
-            } else {
-                Err(ServiceError::Conflict(
-                    "Playlist could not be written, possible already exists!".into(),
-                ))
-            }
-        }
-        Err(e) => {
-            error!("{e}");
-            Err(ServiceError::InternalServerError)
-        }
-    }
+    remove_playlist(id, date, user).await
 }
 
-pub async fn delete_playlist(id: i32, date: &str) -> Result<(), ServiceError> {
+async fn remove_playlist(id: i32, date: &str, user: &LoginUser) -> Result<(), ServiceError> {
     let (config, _) = playout_config(&id).await?;
-    let mut playlist_path = PathBuf::from(&config.playlist.path);
-    let d: Vec<&str> = date.split('-').collect();
-    playlist_path = playlist_path
-        .join(d[0])
-        .join(d[1])
-        .join(date)
-        .with_extension("json");
+    let playlist_path = playlist_path(&config.playlist.path, date);
 
     if playlist_path.is_file() {
+        if let Ok(playlist) = json_reader(&playlist_path) {
+            add_revision(id, date, user, "delete", &playlist).await?;
+        }
+
         if let Err(e) = fs::remove_file(playlist_path) {
             error!("{e}");
             return Err(ServiceError::InternalServerError);
@@ -108,3 +288,134 @@ pub async fn delete_playlist(id: i32, date: &str) -> Result<(), ServiceError> {
 
     Ok(())
 }
+
+pub async fn list_revisions(id: i32, date: &str) -> Result<Vec<PlaylistRevision>, ServiceError> {
+    Ok(select_revisions(&id, date).await?)
+}
+
+async fn revision_playlist(id: i32, revision: i32) -> Result<JsonPlaylist, ServiceError> {
+    let revision = select_revision(&id, &revision)
+        .await
+        .map_err(|_| ServiceError::NoContent(format!("Revision {revision} not exists!")))?;
+
+    serde_json::from_str(&revision.data).map_err(|e| ServiceError::Conflict(e.to_string()))
+}
+
+/// Compare the clips from two revisions, based on the longest common subsequence.
+pub async fn diff_revisions(id: i32, from: i32, to: i32) -> Result<RevisionDiff, ServiceError> {
+    let old = revision_playlist(id, from).await?.program;
+    let new = revision_playlist(id, to).await?.program;
+    let mut lcs = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = vec![];
+    let (mut i, mut j) = (0, 0);
+
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            changes.push(ClipChange {
+                op: "added".to_string(),
+                index: j,
+                clip: new[j].clone(),
+            });
+            j += 1;
+        } else {
+            changes.push(ClipChange {
+                op: "removed".to_string(),
+                index: i,
+                clip: old[i].clone(),
+            });
+            i += 1;
+        }
+    }
+
+    Ok(RevisionDiff { from, to, changes })
+}
+
+/// Write the playlist from a revision back to its date, the restore becomes a new revision.
+pub async fn restore_revision(
+    id: i32,
+    revision: i32,
+    user: &LoginUser,
+) -> Result<String, ServiceError> {
+    let _lock = EDIT_LOCK.lock().await;
+    let (config, _) = playout_config(&id).await?;
+    let playlist = revision_playlist(id, revision).await?;
+    let date = playlist.date.clone();
+    let path = playlist_path(&config.playlist.path, &date);
+
+    if let Some(p) = path.parent() {
+        fs::create_dir_all(p)?;
+    }
+
+    add_revision(id, &date, user, "restore", &playlist).await?;
+
+    if let Err(e) = json_writer(&path, playlist) {
+        error!("{e}");
+        return Err(ServiceError::InternalServerError);
+    }
+
+    Ok(format!(
+        "Restore playlist from {date} with revision {revision} success!"
+    ))
+}
+
+/// Copy playlist to one or more dates, every written playlist gets its own revision.
+///
+/// With `move` the source playlist gets deleted, when it was copied at least once.
+pub async fn copy_playlist(
+    id: i32,
+    request: CopyRequest,
+    user: &LoginUser,
+) -> Result<CopyReport, ServiceError> {
+    let _lock = EDIT_LOCK.lock().await;
+    let targets =
+        date_range(&request.target).map_err(|e| ServiceError::BadRequest(e.to_string()))?;
+    let playlist = read_playlist(id, request.date.clone()).await?;
+    let options = request.options.clone();
+    let source = request.date.clone();
+
+    // probing media files can take a while
+    let template = web::block(move || copy_program(&playlist, &source, &options)).await?;
+    let mut report = CopyReport::default();
+
+    for date in targets {
+        if date == request.date
+            || (!request.options.overwrite && read_playlist(id, date.clone()).await.is_ok())
+        {
+            report.skipped.push(date);
+            continue;
+        }
+
+        let mut copy = template.clone();
+        copy.date = date.clone();
+
+        match write_playlist(id, copy, user).await {
+            Ok(_) => report.written.push(date),
+            Err(ServiceError::Conflict(_)) => report.skipped.push(date),
+            Err(e) => return Err(e),
+        }
+    }
+
+    // the source stays, when it is also one of the targets
+    if request.remove_source
+        && !report.written.is_empty()
+        && !report.skipped.contains(&request.date)
+    {
+        remove_playlist(id, &request.date, user).await?;
+    }
+
+    Ok(report)
+}
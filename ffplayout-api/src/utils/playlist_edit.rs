@@ -0,0 +1,172 @@
+/// Playlist Editing
+///
+/// Insert, move, replace, trim and delete single items from a day's playlist.
+/// After each edit the begin times are recomputed from `day_start` and the playlist
+/// is checked against `playlist.length`.
+///
+/// Every playlist version has an ETag, edits must send it back in the `If-Match` header.
+/// When the playlist was changed in the meantime the edit gets rejected.
+use serde::Serialize;
+
+use crate::db::models::LoginUser;
+use crate::utils::{
+    errors::ServiceError,
+    playlist::{read_playlist, write_playlist, EDIT_LOCK},
+    playout_config,
+};
+use ffplayout_lib::utils::{
+    playlist_edit::{apply_edit, check_etag, playlist_etag, EtagError, PlaylistEdit},
+    sec_to_time, time_to_sec, JsonPlaylist, PlayoutConfig,
+};
+
+#[derive(Debug, Serialize)]
+pub struct ItemTiming {
+    pub index: usize,
+    pub begin: f64,
+    pub begin_time: String,
+    pub length: f64,
+    pub source: String,
+    pub overrun: bool,
+}
+
+/// Begin times and length check from a playlist, all values are in seconds.
+///
+/// `gap` is the time which is not filled until the playlist ends,
+/// `overrun` the time which is too much.
+#[derive(Debug, Serialize)]
+pub struct PlaylistTiming {
+    pub day_start: f64,
+    pub length: f64,
+    pub total: f64,
+    pub gap: f64,
+    pub overrun: f64,
+    pub items: Vec<ItemTiming>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EditResult {
+    pub playlist: JsonPlaylist,
+    pub timing: PlaylistTiming,
+}
+
+/// Compare the If-Match header with the ETag from the current playlist.
+fn etag_matches(
+    playlist: Option<&JsonPlaylist>,
+    if_match: Option<&str>,
+    required: bool,
+) -> Result<(), ServiceError> {
+    check_etag(playlist, if_match, required).map_err(|e| match e {
+        EtagError::Missing => ServiceError::PreconditionRequired(
+            "If-Match header is missing, read the playlist first!".to_string(),
+        ),
+        EtagError::Changed => ServiceError::PreconditionFailed(
+            "Playlist was changed in the meantime, reload it and try again!".to_string(),
+        ),
+    })
+}
+
+pub fn playlist_timing(config: &PlayoutConfig, playlist: &JsonPlaylist) -> PlaylistTiming {
+    let day_start = if config.playlist.day_start.contains(':') {
+        time_to_sec(&config.playlist.day_start)
+    } else {
+        0.0
+    };
+    let length = if config.playlist.length.contains(':') {
+        time_to_sec(&config.playlist.length)
+    } else {
+        86400.0
+    };
+    let mut begin = day_start;
+    let mut items = vec![];
+
+    for (index, clip) in playlist.program.iter().enumerate() {
+        let clip_length = clip.out - clip.seek;
+
+        items.push(ItemTiming {
+            index,
+            begin,
+            begin_time: sec_to_time(begin),
+            length: clip_length,
+            source: clip.source.clone(),
+            overrun: begin + clip_length > day_start + length,
+        });
+
+        begin += clip_length;
+    }
+
+    let total = begin - day_start;
+
+    PlaylistTiming {
+        day_start,
+        length,
+        total,
+        gap: (length - total).max(0.0),
+        overrun: (total - length).max(0.0),
+        items,
+    }
+}
+
+/// Current playlist with its timing and ETag.
+pub async fn read_timing(id: i32, date: String) -> Result<(EditResult, String), ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+    let playlist = read_playlist(id, date).await?;
+    let etag = playlist_etag(&playlist);
+
+    Ok((
+        EditResult {
+            timing: playlist_timing(&config, &playlist),
+            playlist,
+        },
+        etag,
+    ))
+}
+
+/// Apply one edit to the playlist and save it, returns the new playlist with timing and ETag.
+pub async fn edit_playlist(
+    id: i32,
+    date: String,
+    edit: PlaylistEdit,
+    if_match: Option<&str>,
+    user: &LoginUser,
+) -> Result<(EditResult, String), ServiceError> {
+    let _lock = EDIT_LOCK.lock().await;
+    let (config, _) = playout_config(&id).await?;
+    let mut playlist = read_playlist(id, date).await?;
+
+    etag_matches(Some(&playlist), if_match, true)?;
+
+    let original = playlist.clone();
+    apply_edit(&mut playlist.program, edit).map_err(ServiceError::BadRequest)?;
+
+    if playlist != original {
+        write_playlist(id, playlist.clone(), user).await?;
+    }
+
+    let etag = playlist_etag(&playlist);
+
+    Ok((
+        EditResult {
+            timing: playlist_timing(&config, &playlist),
+            playlist,
+        },
+        etag,
+    ))
+}
+
+/// Write the whole playlist, when the playlist exists already `If-Match` must match it.
+pub async fn save_checked(
+    id: i32,
+    playlist: JsonPlaylist,
+    if_match: Option<&str>,
+    user: &LoginUser,
+) -> Result<(String, String), ServiceError> {
+    let _lock = EDIT_LOCK.lock().await;
+    let existing = read_playlist(id, playlist.date.clone()).await.ok();
+
+    etag_matches(existing.as_ref(), if_match, existing.is_some())?;
+
+    let etag = playlist_etag(&playlist);
+    let msg = write_playlist(id, playlist, user).await?;
+
+    Ok((msg, etag))
+}
@@ -0,0 +1,576 @@
+/// Process Supervisor
+///
+/// On hosts without systemd, like in containers, ffpapi can run the playout by itself.
+/// With `--supervise` every channel gets its own `ffplayout` process, which is started again
+/// when it stops without a stop command. Exit code, uptime and restarts are kept for the status.
+/// Channels which are enabled start together with the API, like an enabled systemd service.
+///
+/// The processes run in their own process group and their pids are kept in files. Processes which
+/// are left from an API which ended without stopping them, get stopped before the first start.
+///
+/// The process states come from `/proc`, so the supervisor is only available on Linux.
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, DirBuilder, OpenOptions, Permissions},
+    io::{self, ErrorKind, Write},
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt},
+        process::{CommandExt, ExitStatusExt},
+    },
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use actix_web::{rt::time::sleep, web};
+use chrono::Utc;
+use libc::{c_int, SIGKILL, SIGTERM};
+use once_cell::sync::Lazy;
+use simplelog::*;
+
+use crate::db::handles;
+use crate::utils::{control::ProcessStatus, db_path, errors::ServiceError};
+use ffplayout_lib::utils::restart::{RestartPolicy, MAX_BACKOFF};
+
+/// Seconds to wait for a clean stop, before the process gets killed.
+const STOP_TIMEOUT: u64 = 10;
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+static PROCESSES: Lazy<Mutex<HashMap<i32, ChannelProcess>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Default)]
+struct ChannelProcess {
+    child: Option<Child>,
+    /// Process should run, the monitor starts it again when it stops.
+    wanted: bool,
+    /// Process could not be started.
+    failed: bool,
+    started: i64,
+    policy: RestartPolicy,
+    retry_at: i64,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+}
+
+impl ChannelProcess {
+    fn exited(&mut self, id: i32, status: ExitStatus) {
+        self.child = None;
+        self.exit_code = status.code();
+        self.signal = status.signal();
+
+        if let Err(e) = fs::remove_file(pid_file(id)) {
+            error!("Remove pid file from channel <yellow>{id}</> failed: {e}");
+        }
+    }
+}
+
+/// Let ffpapi run the playout processes, instead of systemd.
+pub fn activate() {
+    ACTIVE.store(true, Ordering::SeqCst);
+}
+
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+/// ffplayout binary next to ffpapi, or the one from `PATH`.
+fn engine_path() -> String {
+    env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|d| d.join("ffplayout")))
+        .filter(|p| p.is_file())
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|| "ffplayout".to_string())
+}
+
+/// Files with the pids from the running processes, under `$XDG_RUNTIME_DIR` or next to the database.
+fn pid_folder() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join("ffplayout"),
+        None => db_path()
+            .ok()
+            .and_then(|p| Path::new(&p).parent().map(|d| d.join("pids")))
+            .unwrap_or_else(|| PathBuf::from("pids")),
+    }
+}
+
+fn pid_file(id: i32) -> PathBuf {
+    pid_folder().join(format!("channel_{id}.pid"))
+}
+
+/// The pids decide which processes get a signal, so only the API user can write there.
+/// An existing folder must be a real folder from this user, not a symlink.
+fn private_folder() -> io::Result<PathBuf> {
+    let folder = pid_folder();
+
+    match fs::symlink_metadata(&folder) {
+        Ok(meta) => {
+            if !meta.is_dir() || meta.uid() != unsafe { libc::getuid() } {
+                return Err(io::Error::new(
+                    ErrorKind::PermissionDenied,
+                    format!("{} is not a private folder", folder.display()),
+                ));
+            }
+
+            if meta.mode() & 0o077 != 0 {
+                fs::set_permissions(&folder, Permissions::from_mode(0o700))?;
+            }
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&folder)?,
+        Err(e) => return Err(e),
+    }
+
+    Ok(folder)
+}
+
+/// Pid and config path from the process, the config path makes sure,
+/// that a reused pid from another process gets no signal.
+fn write_pid_file(id: i32, pid: u32, config_path: &str) -> io::Result<()> {
+    private_folder()?;
+    let path = pid_file(id);
+
+    // a left file gets replaced, `create_new` follows no symlink
+    if let Err(e) = fs::remove_file(&path) {
+        if e.kind() != ErrorKind::NotFound {
+            return Err(e);
+        }
+    }
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?;
+
+    write!(file, "{pid}\n{config_path}")
+}
+
+/// Process has ended, but is not reaped yet.
+///
+/// The process group gets only killed before its leader is reaped,
+/// after the reap the pid can belong to a new process.
+fn is_zombie(pid: u32) -> bool {
+    fs::read_to_string(format!("/proc/{pid}/stat"))
+        .ok()
+        .and_then(|stat| {
+            stat.rsplit_once(')')
+                .and_then(|(_, rest)| rest.trim_start().chars().next())
+        })
+        .map_or(false, |state| state == 'Z')
+}
+
+/// Process is the playout with this config, like it was started by `launch`.
+/// It gets checked, before a process from a pid file gets a signal.
+fn is_playout(pid: u32, config_path: &str) -> bool {
+    let cmdline = match fs::read(format!("/proc/{pid}/cmdline")) {
+        Ok(cmd) => cmd,
+        Err(_) => return false,
+    };
+    let args: Vec<String> = cmdline
+        .split(|b| *b == 0)
+        .filter(|a| !a.is_empty())
+        .map(|a| String::from_utf8_lossy(a).to_string())
+        .collect();
+
+    args.len() == 3
+        && Path::new(&args[0]).file_name().and_then(|n| n.to_str()) == Some("ffplayout")
+        && args[1] == "-c"
+        && args[2] == config_path
+}
+
+/// Send signal to the process group, so the ffmpeg processes from the playout get it too.
+fn signal_group(pid: u32, signal: c_int) {
+    // the playout is the leader from its group, so the group id is its pid
+    if unsafe { libc::killpg(pid as libc::pid_t, signal) } == -1 {
+        let e = io::Error::last_os_error();
+
+        // the group is already gone
+        if e.raw_os_error() != Some(libc::ESRCH) {
+            error!("Send signal {signal} to process group <yellow>{pid}</> failed: {e}");
+        }
+    }
+}
+
+fn launch(id: i32, config_path: &str, process: &mut ChannelProcess) -> Result<(), ServiceError> {
+    let child = Command::new(engine_path())
+        .args(["-c", config_path])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn();
+
+    match child {
+        Ok(child) => {
+            info!(
+                "Start playout from channel <yellow>{id}</>, pid <yellow>{}</>",
+                child.id()
+            );
+
+            if let Err(e) = write_pid_file(id, child.id(), config_path) {
+                error!("Write pid file from channel <yellow>{id}</> failed: {e}");
+            }
+
+            process.child = Some(child);
+            process.started = Utc::now().timestamp();
+            process.failed = false;
+
+            Ok(())
+        }
+        Err(e) => {
+            process.wanted = false;
+            process.failed = true;
+
+            Err(ServiceError::ServiceUnavailable(format!(
+                "Start playout failed: {e}"
+            )))
+        }
+    }
+}
+
+pub async fn start(id: i32) -> Result<String, ServiceError> {
+    let channel = handles::select_channel(&id).await?;
+    let mut processes = PROCESSES.lock().unwrap();
+    let process = processes.entry(id).or_default();
+
+    process.wanted = true;
+    process.policy.reset();
+
+    if process.child.is_none() {
+        launch(id, &channel.config_path, process)?;
+    }
+
+    Ok("Success".to_string())
+}
+
+/// Check if the process has stopped, `true` when no process is left.
+fn reap(id: i32) -> bool {
+    let mut processes = PROCESSES.lock().unwrap();
+
+    match processes.get_mut(&id) {
+        Some(process) => match process.child.as_mut().map(|c| c.try_wait()) {
+            Some(Ok(Some(status))) => {
+                process.exited(id, status);
+                true
+            }
+            Some(_) => false,
+            None => true,
+        },
+        None => true,
+    }
+}
+
+fn child_pid(id: i32) -> Option<u32> {
+    PROCESSES
+        .lock()
+        .unwrap()
+        .get(&id)
+        .and_then(|p| p.child.as_ref().map(|c| c.id()))
+}
+
+/// Kill the process group, as long as its leader is not reaped.
+fn kill_group(id: i32, pid: u32) {
+    // the monitor reaps only under the lock, so the pid can not change in between
+    let processes = PROCESSES.lock().unwrap();
+
+    if processes
+        .get(&id)
+        .and_then(|p| p.child.as_ref().map(|c| c.id()))
+        == Some(pid)
+    {
+        signal_group(pid, SIGKILL);
+    }
+}
+
+/// Stop playout with SIGTERM, after the timeout it gets killed.
+pub async fn stop(id: i32) -> Result<String, ServiceError> {
+    let pid = match PROCESSES.lock().unwrap().get_mut(&id) {
+        Some(process) => {
+            process.wanted = false;
+            process.child.as_ref().map(|c| c.id())
+        }
+        None => None,
+    };
+
+    if let Some(pid) = pid {
+        signal_group(pid, SIGTERM);
+
+        for _ in 0..STOP_TIMEOUT * 10 {
+            if is_zombie(pid) || child_pid(id) != Some(pid) {
+                break;
+            }
+
+            sleep(Duration::from_millis(100)).await;
+        }
+
+        // kill the ffmpeg processes too, when only the playout has stopped
+        kill_group(id, pid);
+
+        while !reap(id) {
+            sleep(Duration::from_millis(100)).await;
+        }
+
+        info!("Stop playout from channel <yellow>{id}</>");
+    }
+
+    Ok("Success".to_string())
+}
+
+pub async fn restart(id: i32) -> Result<String, ServiceError> {
+    stop(id).await?;
+    start(id).await
+}
+
+/// Start playout together with the API, or not.
+pub async fn set_autostart(id: i32, enable: bool) -> Result<String, ServiceError> {
+    if handles::update_autostart(&id, enable)
+        .await?
+        .rows_affected()
+        == 0
+    {
+        return Err(ServiceError::NoContent(format!("Channel {id} not exists!")));
+    }
+
+    Ok("Success".to_string())
+}
+
+pub async fn status(id: i32) -> Result<ProcessStatus, ServiceError> {
+    let enabled = handles::select_autostart_channels().await?.contains(&id);
+    let processes = PROCESSES.lock().unwrap();
+    let process = match processes.get(&id) {
+        Some(p) => p,
+        None => {
+            return Ok(ProcessStatus {
+                state: "inactive".to_string(),
+                enabled,
+                ..Default::default()
+            })
+        }
+    };
+
+    let state = match &process.child {
+        Some(_) => "active",
+        None if process.wanted => "activating",
+        None if process.failed => "failed",
+        None => "inactive",
+    };
+
+    Ok(ProcessStatus {
+        state: state.to_string(),
+        enabled,
+        pid: process.child.as_ref().map(|c| c.id()),
+        uptime: match process.child {
+            Some(_) => Utc::now().timestamp() - process.started,
+            None => 0,
+        },
+        restarts: process.policy.restarts,
+        exit_code: process.exit_code,
+        signal: process.signal,
+    })
+}
+
+/// Look for stopped processes, returns the channels which should be started again now.
+fn check_processes() -> Vec<i32> {
+    let now = Utc::now().timestamp();
+    let mut due = vec![];
+
+    for (id, process) in PROCESSES.lock().unwrap().iter_mut() {
+        let child = match process.child.as_mut() {
+            Some(child) => child,
+            None => {
+                if process.wanted && process.retry_at <= now {
+                    due.push(*id);
+                }
+
+                continue;
+            }
+        };
+
+        let pid = child.id();
+
+        // ffmpeg processes which are left from the playout
+        if is_zombie(pid) {
+            signal_group(pid, SIGKILL);
+        }
+
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                process.exited(*id, status);
+
+                if process.wanted {
+                    let wait = process.policy.exited(now - process.started);
+                    process.retry_at = now + wait;
+
+                    warn!(
+                        "Playout from channel <yellow>{id}</> stopped with {status}, restart in <yellow>{wait}</>s"
+                    );
+                }
+            }
+            Ok(None) => {}
+            Err(e) => error!("Check playout from channel <yellow>{id}</> failed: {e}"),
+        }
+    }
+
+    due
+}
+
+async fn restart_stopped(id: i32) -> Result<(), ServiceError> {
+    let channel = handles::select_channel(&id).await?;
+    let mut processes = PROCESSES.lock().unwrap();
+
+    if let Some(process) = processes.get_mut(&id) {
+        if process.wanted && process.child.is_none() {
+            launch(id, &channel.config_path, process)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Stop the process groups from the pid files, which are left from an earlier run.
+fn stop_orphans() {
+    let folder = match private_folder() {
+        Ok(folder) => folder,
+        Err(e) => {
+            error!("Pid folder can not be used: {e}");
+            return;
+        }
+    };
+    let files = match fs::read_dir(folder) {
+        Ok(files) => files,
+        Err(_) => return,
+    };
+
+    for file in files.flatten() {
+        // symlinks and other files are only removed
+        let content = match file.file_type() {
+            Ok(t) if t.is_file() => fs::read_to_string(file.path()).unwrap_or_default(),
+            _ => String::new(),
+        };
+        let (pid, config_path) = content.split_once('\n').unwrap_or_default();
+
+        if let Ok(pid) = pid.trim().parse::<u32>() {
+            if is_playout(pid, config_path) {
+                warn!("Stop playout <yellow>{pid}</> from an earlier run");
+                signal_group(pid, SIGTERM);
+
+                let started = Instant::now();
+
+                while is_playout(pid, config_path)
+                    && started.elapsed() < Duration::from_secs(STOP_TIMEOUT)
+                {
+                    thread::sleep(Duration::from_millis(100));
+                }
+
+                if is_playout(pid, config_path) {
+                    signal_group(pid, SIGKILL);
+                }
+            }
+        }
+
+        if let Err(e) = fs::remove_file(file.path()) {
+            error!(
+                "Remove <b><magenta>{}</></b> failed: {e}",
+                file.path().display()
+            );
+        }
+    }
+}
+
+/// Stop all processes, when the API ends.
+pub fn shutdown() {
+    let pids: Vec<u32> = PROCESSES
+        .lock()
+        .unwrap()
+        .values_mut()
+        .filter_map(|p| {
+            p.wanted = false;
+            p.child.as_ref().map(|c| c.id())
+        })
+        .collect();
+
+    for pid in &pids {
+        signal_group(*pid, SIGTERM);
+    }
+
+    let started = Instant::now();
+
+    while started.elapsed() < Duration::from_secs(STOP_TIMEOUT) {
+        let mut processes = PROCESSES.lock().unwrap();
+        let mut running = false;
+
+        for (id, process) in processes.iter_mut() {
+            if let Some(child) = process.child.as_mut() {
+                if is_zombie(child.id()) {
+                    signal_group(child.id(), SIGKILL);
+                }
+
+                match child.try_wait() {
+                    Ok(Some(status)) => process.exited(*id, status),
+                    _ => running = true,
+                }
+            }
+        }
+
+        if !running {
+            return;
+        }
+
+        drop(processes);
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    for (id, process) in PROCESSES.lock().unwrap().iter_mut() {
+        if let Some(mut child) = process.child.take() {
+            signal_group(child.id(), SIGKILL);
+
+            match child.wait() {
+                Ok(status) => process.exited(*id, status),
+                Err(e) => error!("Stop playout from channel <yellow>{id}</> failed: {e}"),
+            }
+        }
+    }
+}
+
+/// Start the enabled channels and keep their processes running.
+pub async fn supervisor() {
+    if let Err(e) = web::block(stop_orphans).await {
+        error!("Stop processes from an earlier run failed: {e}");
+    }
+
+    match handles::select_autostart_channels().await {
+        Ok(ids) => {
+            for id in ids {
+                if let Err(e) = start(id).await {
+                    error!("Start playout from channel <yellow>{id}</> failed: {e}");
+                }
+            }
+        }
+        Err(e) => error!("Read enabled channels failed: {e}"),
+    }
+
+    loop {
+        sleep(Duration::from_secs(1)).await;
+
+        for id in check_processes() {
+            if let Err(e) = restart_stopped(id).await {
+                error!("Restart playout from channel <yellow>{id}</> failed: {e}");
+
+                if let Some(process) = PROCESSES.lock().unwrap().get_mut(&id) {
+                    process.retry_at = Utc::now().timestamp() + MAX_BACKOFF;
+                }
+            }
+        }
+    }
+}
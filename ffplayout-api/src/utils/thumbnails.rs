@@ -0,0 +1,261 @@
+/// Thumbnails
+///
+/// A poster frame and a strip of small thumbnails for each media file, created with ffmpeg.
+/// They are cached in the hidden `.thumbs` folder from storage, with the same folder structure
+/// as the media files. When the media file is newer than its thumbnail, it gets created again.
+/// Rename, trash and restore move or drop the cached images, so a new file under an old name
+/// never shows the thumbnail of its predecessor.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Condvar, Mutex,
+    },
+};
+
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use simplelog::*;
+
+use crate::utils::{errors::ServiceError, files::norm_abs_path, playout_config};
+use ffplayout_lib::utils::MediaProbe;
+
+/// Number of thumbnails in the preview strip.
+pub const SPRITE_TILES: usize = 10;
+
+/// Maximal number of ffmpeg processes for thumbnails at the same time.
+pub const MAX_THUMB_JOBS: usize = 2;
+
+const THUMB_FOLDER: &str = ".thumbs";
+
+// Number for the temp files, parallel requests for the same thumbnail must not share one.
+static TEMP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+static THUMB_JOBS: JobSlots = JobSlots {
+    running: Mutex::new(0),
+    free: Condvar::new(),
+};
+
+/// Counting semaphore for the ffmpeg processes, it blocks the calling thread,
+/// so it is only used inside of `web::block`.
+struct JobSlots {
+    running: Mutex<usize>,
+    free: Condvar,
+}
+
+struct JobSlot<'a>(&'a JobSlots);
+
+impl JobSlots {
+    fn acquire(&self) -> JobSlot<'_> {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+
+        while *running >= MAX_THUMB_JOBS {
+            running = self.free.wait(running).unwrap_or_else(|e| e.into_inner());
+        }
+
+        *running += 1;
+
+        JobSlot(self)
+    }
+}
+
+impl Drop for JobSlot<'_> {
+    fn drop(&mut self) {
+        let mut running = self.0.running.lock().unwrap_or_else(|e| e.into_inner());
+        *running -= 1;
+        self.0.free.notify_one();
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbKind {
+    Poster,
+    Sprite,
+}
+
+impl Default for ThumbKind {
+    fn default() -> Self {
+        Self::Poster
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ThumbQuery {
+    pub path: String,
+    #[serde(default)]
+    pub kind: ThumbKind,
+}
+
+fn thumb_path(root: &str, relative: &str, kind: ThumbKind) -> PathBuf {
+    let suffix = match kind {
+        ThumbKind::Poster => "poster",
+        ThumbKind::Sprite => "sprite",
+    };
+
+    Path::new(root)
+        .join(THUMB_FOLDER)
+        .join(format!("{relative}_{suffix}.jpg"))
+}
+
+/// Thumbnail exists and is not older than the media file.
+fn is_fresh(thumb: &Path, source: &Path) -> bool {
+    match (
+        thumb.metadata().and_then(|m| m.modified()),
+        source.metadata().and_then(|m| m.modified()),
+    ) {
+        (Ok(t), Ok(s)) => t >= s,
+        _ => false,
+    }
+}
+
+/// Move the cached thumbnails of a file or folder to the new relative path,
+/// or delete them when `target` is `None`. Thumbnails are only a cache, so errors are only logged.
+pub fn move_thumbs(root: &str, source: &str, target: Option<&str>) {
+    let folder = Path::new(root).join(THUMB_FOLDER);
+    let mut paths = vec![(folder.join(source), target.map(|t| folder.join(t)))];
+
+    for kind in [ThumbKind::Poster, ThumbKind::Sprite] {
+        paths.push((
+            thumb_path(root, source, kind),
+            target.map(|t| thumb_path(root, t, kind)),
+        ));
+    }
+
+    for (from, to) in paths {
+        if !from.exists() {
+            continue;
+        }
+
+        let result = match to {
+            Some(to) => to
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::rename(&from, to)),
+            None if from.is_dir() => fs::remove_dir_all(&from),
+            None => fs::remove_file(&from),
+        };
+
+        if let Err(e) = result {
+            error!(
+                "Update thumbnail <b><magenta>{}</></b> failed: {e}",
+                from.display()
+            );
+        }
+    }
+}
+
+/// Create thumbnail from media file, the image gets written to its own temp file first,
+/// so a parallel request never reads or writes a half written image.
+pub fn create_thumb(
+    root: &str,
+    source: &Path,
+    relative: &str,
+    kind: ThumbKind,
+) -> Result<PathBuf, ServiceError> {
+    let thumb = thumb_path(root, relative, kind);
+
+    if is_fresh(&thumb, source) {
+        return Ok(thumb);
+    }
+
+    let duration: f64 = MediaProbe::new(&source.to_string_lossy())
+        .format
+        .and_then(|f| f.duration)
+        .and_then(|d| d.parse().ok())
+        .unwrap_or_default();
+    let src = source.to_string_lossy().to_string();
+    let mut args = vec!["-hide_banner", "-nostats", "-v", "error"];
+    let seek = format!("{:.3}", duration * 0.1);
+    let sprite_filter =
+        format!("fps={SPRITE_TILES}/{duration:.3},scale=160:-2,tile={SPRITE_TILES}x1:padding=2");
+
+    match kind {
+        ThumbKind::Poster if duration > 0.0 => {
+            args.extend(["-ss", seek.as_str(), "-i", src.as_str()]);
+            args.extend(["-vf", "scale=320:-2"]);
+        }
+        // still images have no duration
+        ThumbKind::Poster => {
+            args.extend(["-i", src.as_str(), "-vf", "scale=320:-2"]);
+        }
+        ThumbKind::Sprite if duration > 0.0 => {
+            // decoding only key frames is fast enough for long files
+            args.extend(["-skip_frame", "nokey", "-i", src.as_str()]);
+            args.extend(["-vf", sprite_filter.as_str(), "-vsync", "vfr"]);
+        }
+        ThumbKind::Sprite => {
+            args.extend(["-i", src.as_str(), "-vf", "scale=160:-2"]);
+        }
+    }
+
+    if let Some(parent) = thumb.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let count = TEMP_COUNT.fetch_add(1, Ordering::SeqCst);
+    let temp = thumb.with_extension(format!("{count}.part"));
+    let temp_str = temp.to_string_lossy().to_string();
+    args.extend(["-frames:v", "1", "-f", "image2", "-y", temp_str.as_str()]);
+
+    let slot = THUMB_JOBS.acquire();
+
+    // a parallel request could have created it while waiting
+    if is_fresh(&thumb, source) {
+        return Ok(thumb);
+    }
+
+    let output = Command::new("ffmpeg").args(&args).output()?;
+    drop(slot);
+
+    if !output.status.success() || !temp.is_file() {
+        let _ = fs::remove_file(&temp);
+        error!(
+            "Create thumbnail from <b><magenta>{}</></b> failed: {}",
+            source.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+
+        return Err(ServiceError::InternalServerError);
+    }
+
+    fs::rename(&temp, &thumb)?;
+
+    Ok(thumb)
+}
+
+/// Create poster and sprite from an uploaded file, errors are only logged.
+pub async fn create_thumbs(root: String, source: PathBuf) {
+    let relative = match source.strip_prefix(&root) {
+        Ok(r) => r.to_string_lossy().to_string(),
+        Err(_) => return,
+    };
+
+    for kind in [ThumbKind::Poster, ThumbKind::Sprite] {
+        let (root, source, relative) = (root.clone(), source.clone(), relative.clone());
+
+        match web::block(move || create_thumb(&root, &source, &relative, kind)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => error!("{e}"),
+            Err(e) => error!("{e}"),
+        }
+    }
+}
+
+/// Get thumbnail image from media file in storage, it gets created on first request.
+pub async fn thumbnail(id: i32, query: &ThumbQuery) -> Result<Vec<u8>, ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+    let (source, _, relative) = norm_abs_path(&config.storage.path, &query.path);
+
+    if !source.is_file() {
+        return Err(ServiceError::NoContent("File not exists!".into()));
+    }
+
+    let root = config.storage.path.clone();
+    let kind = query.kind;
+    let thumb = web::block(move || create_thumb(&root, &source, &relative, kind)).await??;
+
+    Ok(fs::read(thumb)?)
+}
@@ -0,0 +1,193 @@
+/// Trash Bin
+///
+/// Deleted files and folders are moved to the hidden `.trash` folder of the channel storage,
+/// from there they can be restored or purged. Each entry gets a random id as name, the original
+/// path and the deletion time are stored next to it in `{id}.json`.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use actix_web::web;
+use chrono::Utc;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use simplelog::*;
+
+use crate::utils::{
+    errors::ServiceError, files::norm_abs_path, playout_config, thumbnails::move_thumbs,
+};
+
+const TRASH_FOLDER: &str = ".trash";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TrashItem {
+    #[serde(default)]
+    pub id: String,
+    /// Original path, relative to storage.
+    pub source: String,
+    pub is_dir: bool,
+    pub deleted: i64,
+    pub username: String,
+}
+
+fn entry_paths(root: &str, id: &str) -> Result<(PathBuf, PathBuf), ServiceError> {
+    if id.len() != 20 || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(ServiceError::BadRequest("Wrong trash id!".into()));
+    }
+
+    let folder = Path::new(root).join(TRASH_FOLDER);
+
+    Ok((folder.join(format!("{id}.json")), folder.join(id)))
+}
+
+fn read_item(root: &str, id: &str) -> Result<(TrashItem, PathBuf, PathBuf), ServiceError> {
+    let (meta, entry) = entry_paths(root, id)?;
+
+    if !meta.is_file() {
+        return Err(ServiceError::NoContent("Trash item not exists!".into()));
+    }
+
+    let mut item: TrashItem = serde_json::from_str(&fs::read_to_string(&meta)?)
+        .map_err(|e| ServiceError::BadRequest(e.to_string()))?;
+    item.id = id.to_string();
+
+    Ok((item, meta, entry))
+}
+
+/// Move file or folder to trash, `relative` is its path relative to storage.
+pub fn move_to_trash(
+    root: &str,
+    source: &Path,
+    relative: &str,
+    username: &str,
+) -> Result<TrashItem, ServiceError> {
+    let id: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(20)
+        .map(char::from)
+        .collect();
+    let (meta, entry) = entry_paths(root, &id)?;
+    let item = TrashItem {
+        id,
+        source: relative.to_string(),
+        is_dir: source.is_dir(),
+        deleted: Utc::now().timestamp(),
+        username: username.to_string(),
+    };
+
+    if let Some(p) = meta.parent() {
+        fs::create_dir_all(p)?;
+    }
+
+    fs::write(
+        &meta,
+        serde_json::to_string(&item).map_err(|e| ServiceError::BadRequest(e.to_string()))?,
+    )?;
+
+    if let Err(e) = fs::rename(source, &entry) {
+        fs::remove_file(meta)?;
+        error!(
+            "Move <b><magenta>{}</></b> to trash failed: {e}",
+            source.display()
+        );
+
+        return Err(ServiceError::BadRequest("Delete failed!".into()));
+    }
+
+    move_thumbs(root, relative, None);
+
+    info!("Move to trash: <b><magenta>{}</></b>", source.display());
+
+    Ok(item)
+}
+
+/// Trash items, newest first.
+pub async fn list_trash(id: i32) -> Result<Vec<TrashItem>, ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+    let folder = Path::new(&config.storage.path).join(TRASH_FOLDER);
+    let mut items = vec![];
+
+    for entry in fs::read_dir(folder).into_iter().flatten().flatten() {
+        let path = entry.path();
+
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+
+        if let Ok((item, _, _)) = read_item(&config.storage.path, &name) {
+            items.push(item);
+        }
+    }
+
+    items.sort_by(|a, b| b.deleted.cmp(&a.deleted));
+
+    Ok(items)
+}
+
+/// Move trash item back to its original path, missing parent folders are created.
+pub async fn restore_trash(id: i32, trash_id: &str) -> Result<TrashItem, ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+    let (item, meta, entry) = read_item(&config.storage.path, trash_id)?;
+    let (target, _, _) = norm_abs_path(&config.storage.path, &item.source);
+
+    if target.exists() {
+        return Err(ServiceError::Conflict(format!(
+            "Target already exists: {}",
+            item.source
+        )));
+    }
+
+    if let Some(p) = target.parent() {
+        fs::create_dir_all(p)?;
+    }
+
+    fs::rename(entry, &target)?;
+    fs::remove_file(meta)?;
+    move_thumbs(&config.storage.path, &item.source, None);
+
+    info!(
+        "Restore from trash: <b><magenta>{}</></b>",
+        target.display()
+    );
+
+    Ok(item)
+}
+
+fn purge_item(root: &str, trash_id: &str) -> Result<TrashItem, ServiceError> {
+    let (item, meta, entry) = read_item(root, trash_id)?;
+
+    if entry.is_dir() {
+        fs::remove_dir_all(&entry)?;
+    } else if entry.exists() {
+        fs::remove_file(&entry)?;
+    }
+
+    fs::remove_file(meta)?;
+
+    Ok(item)
+}
+
+/// Delete trash item for ever, or the whole trash when `trash_id` is `None`.
+pub async fn purge_trash(id: i32, trash_id: Option<String>) -> Result<usize, ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+    let ids = match trash_id {
+        Some(t) => vec![t],
+        None => list_trash(id).await?.into_iter().map(|i| i.id).collect(),
+    };
+    let root = config.storage.path.clone();
+
+    let count = web::block(move || {
+        ids.iter()
+            .map(|t| purge_item(&root, t))
+            .collect::<Result<Vec<TrashItem>, ServiceError>>()
+            .map(|items| items.len())
+    })
+    .await??;
+
+    info!("Purge <yellow>{count}</> item(s) from trash of channel <yellow>{id}</>");
+
+    Ok(count)
+}
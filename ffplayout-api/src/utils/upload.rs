@@ -0,0 +1,308 @@
+/// Resumable Uploads
+///
+/// Big files are uploaded in chunks. First a session gets created with target folder, file name,
+/// size and an optional sha256 checksum, then the chunks are sent together with their offset.
+/// When the connection breaks, the client asks for the current offset and continues from there.
+///
+/// The data goes to a temp file in the hidden `.uploads` folder of the storage, so a broken upload
+/// never shows up under its final name. After the last chunk the checksum gets verified
+/// and the file is renamed to its target.
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use actix_web::web;
+use chrono::Utc;
+use futures_util::TryStreamExt as _;
+use once_cell::sync::Lazy;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use simplelog::*;
+
+use crate::utils::{
+    errors::ServiceError,
+    files::{after_upload, norm_abs_path},
+    playout_config,
+};
+use ffplayout_lib::utils::{
+    upload::{
+        append_chunk, check_offset, normalize_checksum, session_paths, verify_checksum,
+        UPLOAD_FOLDER,
+    },
+    PlayoutConfig,
+};
+
+/// Unfinished uploads older than this are removed, when a new upload starts.
+const STALE_SECONDS: i64 = 7 * 24 * 3600;
+
+/// Sessions which receive data at the moment, only one request can write to a session.
+static ACTIVE: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UploadRequest {
+    /// Target folder, relative to storage.
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    /// sha256 hex digest from the whole file.
+    #[serde(default)]
+    pub checksum: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct UploadSession {
+    target: PathBuf,
+    size: u64,
+    checksum: Option<String>,
+    created: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadStatus {
+    pub id: String,
+    pub offset: u64,
+    pub size: u64,
+    pub complete: bool,
+}
+
+/// Marks a session as active, until the request is done or its connection is dropped.
+struct ActiveUpload(String);
+
+impl ActiveUpload {
+    fn lock(id: &str) -> Result<Self, ServiceError> {
+        if !ACTIVE.lock().unwrap().insert(id.to_string()) {
+            return Err(ServiceError::Conflict("Upload is in progress!".into()));
+        }
+
+        Ok(Self(id.to_string()))
+    }
+}
+
+impl Drop for ActiveUpload {
+    fn drop(&mut self) {
+        ACTIVE.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Check file size against the upload limit from channel config.
+pub fn check_size(config: &PlayoutConfig, size: u64) -> Result<(), ServiceError> {
+    let limit = config.storage.upload_limit * 1024 * 1024;
+
+    if limit > 0 && size > limit {
+        return Err(ServiceError::BadRequest(format!(
+            "File is bigger than the upload limit of {} MB!",
+            config.storage.upload_limit
+        )));
+    }
+
+    Ok(())
+}
+
+fn read_session(root: &str, id: &str) -> Result<(UploadSession, PathBuf, PathBuf), ServiceError> {
+    let (meta, part) = session_paths(root, id)?;
+
+    if !meta.is_file() || !part.is_file() {
+        return Err(ServiceError::NoContent("Upload not exists!".into()));
+    }
+
+    let session: UploadSession = serde_json::from_str(&fs::read_to_string(&meta)?)
+        .map_err(|e| ServiceError::BadRequest(e.to_string()))?;
+
+    Ok((session, meta, part))
+}
+
+fn remove_stale(root: &str) {
+    let folder = Path::new(root).join(UPLOAD_FOLDER);
+    let now = Utc::now().timestamp();
+
+    for entry in fs::read_dir(folder).into_iter().flatten().flatten() {
+        let path = entry.path();
+
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+
+        let stale = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<UploadSession>(&s).ok())
+            .map(|s| now - s.created > STALE_SECONDS)
+            .unwrap_or(true);
+
+        if stale {
+            info!(
+                "Remove unfinished upload <b><magenta>{}</></b>",
+                path.display()
+            );
+            let _ = fs::remove_file(path.with_extension("part"));
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+pub async fn create_upload(id: i32, req: &UploadRequest) -> Result<UploadStatus, ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+    check_size(&config, req.size)?;
+
+    let checksum = req
+        .checksum
+        .as_deref()
+        .map(normalize_checksum)
+        .transpose()?;
+
+    let (folder, _, _) = norm_abs_path(&config.storage.path, &req.path);
+    let name = sanitize_filename::sanitize(&req.name);
+
+    if !folder.is_dir() {
+        return Err(ServiceError::BadRequest("Target folder not exists!".into()));
+    }
+
+    if name.is_empty() || name.starts_with('.') {
+        return Err(ServiceError::BadRequest("Wrong file name!".into()));
+    }
+
+    let target = folder.join(name);
+
+    if target.exists() {
+        return Err(ServiceError::BadRequest("Target already exists!".into()));
+    }
+
+    remove_stale(&config.storage.path);
+
+    let session_id: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(20)
+        .map(char::from)
+        .collect();
+    let (meta, part) = session_paths(&config.storage.path, &session_id)?;
+    let session = UploadSession {
+        target,
+        size: req.size,
+        checksum,
+        created: Utc::now().timestamp(),
+    };
+
+    if let Some(p) = meta.parent() {
+        fs::create_dir_all(p)?;
+    }
+
+    File::create(part)?;
+    fs::write(
+        meta,
+        serde_json::to_string(&session).map_err(|e| ServiceError::BadRequest(e.to_string()))?,
+    )?;
+
+    Ok(UploadStatus {
+        id: session_id,
+        offset: 0,
+        size: req.size,
+        complete: false,
+    })
+}
+
+pub async fn upload_status(id: i32, session_id: &str) -> Result<UploadStatus, ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+    let (session, _, part) = read_session(&config.storage.path, session_id)?;
+
+    Ok(UploadStatus {
+        id: session_id.to_string(),
+        offset: part.metadata()?.len(),
+        size: session.size,
+        complete: false,
+    })
+}
+
+/// Verify the complete file and move it to its target.
+async fn finish_upload(
+    config: &PlayoutConfig,
+    id: i32,
+    session: UploadSession,
+    meta: PathBuf,
+    part: PathBuf,
+) -> Result<(), ServiceError> {
+    if let Some(expected) = session.checksum {
+        let (m, p) = (meta.clone(), part.clone());
+        web::block(move || verify_checksum(&m, &p, &expected)).await??;
+    }
+
+    if session.target.exists() {
+        return Err(ServiceError::Conflict("Target already exists!".into()));
+    }
+
+    // temp file is in the same storage, so this is an atomic move
+    fs::rename(&part, &session.target)?;
+    fs::remove_file(meta)?;
+
+    info!(
+        "Upload complete: <b><magenta>{}</></b>",
+        session.target.display()
+    );
+
+    after_upload(config, id, session.target);
+
+    Ok(())
+}
+
+/// Append chunk to upload, `offset` must be the size which was received so far.
+pub async fn upload_chunk(
+    id: i32,
+    session_id: &str,
+    offset: u64,
+    mut payload: web::Payload,
+) -> Result<UploadStatus, ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+    let _active = ActiveUpload::lock(session_id)?;
+    let (session, meta, part) = read_session(&config.storage.path, session_id)?;
+    let size = session.size;
+    let mut received = part.metadata()?.len();
+
+    check_offset(offset, received).map_err(ServiceError::Conflict)?;
+
+    let file = part.clone();
+    let mut f = web::block(move || OpenOptions::new().append(true).open(file)).await??;
+
+    while let Some(chunk) = payload
+        .try_next()
+        .await
+        .map_err(|e| ServiceError::BadRequest(e.to_string()))?
+    {
+        let (file, new_size) =
+            web::block(move || append_chunk(&mut f, received, size, &chunk).map(|r| (f, r)))
+                .await?
+                .map_err(|e| match e.kind() {
+                    io::ErrorKind::InvalidInput => ServiceError::BadRequest(e.to_string()),
+                    _ => e.into(),
+                })?;
+        f = file;
+        received = new_size;
+    }
+
+    drop(f);
+
+    let complete = received == size;
+
+    if complete {
+        finish_upload(&config, id, session, meta, part).await?;
+    }
+
+    Ok(UploadStatus {
+        id: session_id.to_string(),
+        offset: received,
+        size,
+        complete,
+    })
+}
+
+pub async fn cancel_upload(id: i32, session_id: &str) -> Result<(), ServiceError> {
+    let (config, _) = playout_config(&id).await?;
+    let _active = ActiveUpload::lock(session_id)?;
+    let (_, meta, part) = read_session(&config.storage.path, session_id)?;
+
+    fs::remove_file(part)?;
+    fs::remove_file(meta)?;
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, SaltString},
+    Argon2, PasswordHasher,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use simplelog::*;
+
+use crate::db::{
+    handles::{
+        delete_user, revoke_user_tokens, select_role_id, select_user_by_id, select_users,
+        update_user,
+    },
+    models::{UserFields, UserInfo},
+};
+use crate::utils::errors::ServiceError;
+
+/// Fields for the admin user update, all of them are optional.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UserUpdate {
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub disabled: Option<bool>,
+}
+
+/// Hash password and set it, together with the salt, to the update fields.
+pub fn set_password(fields: &mut UserFields, password: &str) {
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap();
+
+    fields.password = Some(password_hash.to_string());
+    fields.salt = Some(salt.to_string());
+}
+
+/// Update user, after a password or role change all existing tokens from the user are revoked.
+pub async fn save_user(id: i32, fields: &UserFields) -> Result<(), ServiceError> {
+    update_user(id, fields).await?;
+
+    if fields.password.is_some() || fields.role_id.is_some() {
+        revoke_user_tokens(&id, Utc::now().timestamp()).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn list_users() -> Result<Vec<UserInfo>, ServiceError> {
+    Ok(select_users().await?)
+}
+
+/// Change role, password and disabled state from user.
+///
+/// `current_id` is the user who makes the change, so nobody can lock out the own account.
+pub async fn change_user(
+    id: i32,
+    current_id: Option<i32>,
+    data: &UserUpdate,
+) -> Result<(), ServiceError> {
+    let user = select_user_by_id(&id)
+        .await
+        .map_err(|_| ServiceError::NoContent(format!("User with id {id} not exists!")))?;
+    let mut fields = UserFields::default();
+
+    if let Some(role) = &data.role {
+        let role_id = select_role_id(role)
+            .await
+            .map_err(|_| ServiceError::BadRequest(format!("Unknown role: {role}")))?;
+
+        if current_id == Some(id) && role != "admin" {
+            return Err(ServiceError::Conflict(
+                "Own admin role can not be removed!".to_string(),
+            ));
+        }
+
+        fields.role_id = Some(role_id);
+    }
+
+    if let Some(password) = &data.password {
+        if password.is_empty() {
+            return Err(ServiceError::BadRequest("Password is empty!".to_string()));
+        }
+
+        set_password(&mut fields, password);
+    }
+
+    if let Some(disabled) = data.disabled {
+        if current_id == Some(id) && disabled {
+            return Err(ServiceError::Conflict(
+                "Own account can not be disabled!".to_string(),
+            ));
+        }
+
+        fields.disabled = Some(disabled);
+    }
+
+    if fields.role_id.is_none() && fields.password.is_none() && fields.disabled.is_none() {
+        return Err(ServiceError::BadRequest("Nothing to update!".to_string()));
+    }
+
+    save_user(id, &fields).await?;
+
+    info!("Update user <b><magenta>{}</></b>", user.username);
+
+    Ok(())
+}
+
+pub async fn remove_user(id: i32, current_id: Option<i32>) -> Result<(), ServiceError> {
+    if current_id == Some(id) {
+        return Err(ServiceError::Conflict(
+            "Own account can not be deleted!".to_string(),
+        ));
+    }
+
+    let user = select_user_by_id(&id)
+        .await
+        .map_err(|_| ServiceError::NoContent(format!("User with id {id} not exists!")))?;
+
+    delete_user(&id).await?;
+
+    info!("Delete user <b><magenta>{}</></b>", user.username);
+
+    Ok(())
+}
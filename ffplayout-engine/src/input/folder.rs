@@ -15,7 +15,7 @@ use notify::{
 };
 use simplelog::*;
 
-use ffplayout_lib::utils::{include_file, Media, PlayoutConfig};
+use ffplayout_lib::utils::{include_file, is_hidden, Media, PlayoutConfig};
 
 /// Create a watcher, which monitor file changes.
 /// When a change is register, update the current file list.
@@ -35,7 +35,7 @@ pub fn watchman(
     }
 
     let mut watcher = watcher(tx, Duration::from_secs(1)).unwrap();
-    watcher.watch(path, RecursiveMode::Recursive).unwrap();
+    watcher.watch(&path, RecursiveMode::Recursive).unwrap();
 
     while !is_terminated.load(Ordering::SeqCst) {
         if let Ok(res) = rx.try_recv() {
@@ -44,13 +44,13 @@ pub fn watchman(
                     let index = sources.lock().unwrap().len();
                     let media = Media::new(index, &new_path.to_string_lossy(), false);
 
-                    if include_file(config.clone(), &new_path) {
+                    if include_file(config.clone(), &new_path) && !is_hidden(&path, &new_path) {
                         sources.lock().unwrap().push(media);
                         info!("Create new file: <b><magenta>{new_path:?}</></b>");
                     }
                 }
                 Remove(old_path) => {
-                    if include_file(config.clone(), &old_path) {
+                    if include_file(config.clone(), &old_path) && !is_hidden(&path, &old_path) {
                         sources
                             .lock()
                             .unwrap()
@@ -59,17 +59,30 @@ pub fn watchman(
                     }
                 }
                 Rename(old_path, new_path) => {
-                    let index = sources
-                        .lock()
-                        .unwrap()
+                    let mut list = sources.lock().unwrap();
+                    let index = list
                         .iter()
-                        .position(|x| *x.source == old_path.display().to_string())
-                        .unwrap();
+                        .position(|x| *x.source == old_path.display().to_string());
+                    // uploads and deletes to trash are renames from or to hidden files
+                    let playable =
+                        include_file(config.clone(), &new_path) && !is_hidden(&path, &new_path);
 
-                    let media = Media::new(index, &new_path.to_string_lossy(), false);
-                    sources.lock().unwrap()[index] = media;
-
-                    info!("Rename file: <b><magenta>{old_path:?}</></b> to <b><magenta>{new_path:?}</></b>");
+                    match (index, playable) {
+                        (Some(index), true) => {
+                            list[index] = Media::new(index, &new_path.to_string_lossy(), false);
+                            info!("Rename file: <b><magenta>{old_path:?}</></b> to <b><magenta>{new_path:?}</></b>");
+                        }
+                        (Some(index), false) => {
+                            list.remove(index);
+                            info!("Remove file: <b><magenta>{old_path:?}</></b>");
+                        }
+                        (None, true) => {
+                            let media = Media::new(list.len(), &new_path.to_string_lossy(), false);
+                            list.push(media);
+                            info!("Create new file: <b><magenta>{new_path:?}</></b>");
+                        }
+                        (None, false) => {}
+                    }
                 }
                 _ => (),
             }
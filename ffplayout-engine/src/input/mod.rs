@@ -8,7 +8,9 @@ use std::{
 
 use simplelog::*;
 
-use ffplayout_lib::utils::{Media, PlayoutConfig, PlayoutStatus, ProcessMode::*};
+use ffplayout_lib::utils::{
+    read_status, Media, PlayoutConfig, PlayoutStatus, ProcessMode::*, ResumeMode,
+};
 
 pub mod folder;
 pub mod ingest;
@@ -37,7 +39,13 @@ pub fn source_generator(
             );
 
             let config_clone = config.clone();
-            let folder_source = FolderSource::new(&config, playout_stat.chain, current_list, index);
+            let mut folder_source =
+                FolderSource::new(&config, playout_stat.chain, current_list, index);
+
+            if config.general.resume_mode == ResumeMode::Resume {
+                folder_source.resume(&read_status(&config.general.stat_file));
+            }
+
             let node_clone = folder_source.nodes.clone();
 
             // Spawn a thread to monitor folder for file changes.
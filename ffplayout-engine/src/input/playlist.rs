@@ -1,5 +1,4 @@
 use std::{
-    fs,
     path::Path,
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
@@ -7,13 +6,12 @@ use std::{
     },
 };
 
-use serde_json::json;
 use simplelog::*;
 
 use ffplayout_lib::utils::{
     check_sync, gen_dummy, get_delta, get_sec, is_close, is_remote, json_serializer::read_json,
-    loop_filler, loop_image, modified_time, seek_and_length, valid_source, Media, MediaProbe,
-    PlayoutConfig, PlayoutStatus, DUMMY_LEN, IMAGE_FORMAT,
+    loop_filler, loop_image, modified_time, seek_and_length, valid_source, write_status, Media,
+    MediaProbe, PlayoutConfig, PlayoutStatus, DUMMY_LEN, IMAGE_FORMAT,
 };
 
 /// Struct for current playlist.
@@ -51,15 +49,7 @@ impl CurrentProgram {
         *playout_stat.current_date.lock().unwrap() = json.date.clone();
 
         if *playout_stat.date.lock().unwrap() != json.date {
-            let data = json!({
-                "time_shift": 0.0,
-                "date": json.date,
-            });
-
-            let json: String = serde_json::to_string(&data).expect("Serialize status data failed");
-            if let Err(e) = fs::write(config.general.stat_file.clone(), &json) {
-                error!("Unable to write status file: {e}");
-            };
+            write_status(config, &json.date, 0.0);
         }
 
         Self {
@@ -165,19 +155,9 @@ impl CurrentProgram {
                 info!("Read Playlist: <b><magenta>{}</></b>", file);
             }
 
-            let data = json!({
-                "time_shift": 0.0,
-                "date": json.date,
-            });
-
             *self.playout_stat.current_date.lock().unwrap() = json.date.clone();
             *self.playout_stat.time_shift.lock().unwrap() = 0.0;
-            let status_data: String =
-                serde_json::to_string(&data).expect("Serialize status data failed");
-
-            if let Err(e) = fs::write(self.config.general.stat_file.clone(), &status_data) {
-                error!("Unable to write status file: {e}");
-            };
+            write_status(&self.config, &json.date, 0.0);
 
             self.json_path = json.current_file.clone();
             self.json_mod = json.modified;
@@ -428,6 +408,7 @@ fn timed_source(
             debug!("Delta: <yellow>{shifted_delta:.3}</>");
         }
 
+        *playout_stat.sync_delta.lock().unwrap() = Some(shifted_delta);
         let sync = check_sync(config, shifted_delta);
 
         if !sync {
@@ -1,15 +1,7 @@
-use std::{
-    fs::{self, File},
-    path::PathBuf,
-    process::exit,
-    sync::{Arc, Mutex},
-    thread,
-};
+use std::{fs, path::PathBuf, process::exit, thread};
 
 #[cfg(debug_assertions)]
 use chrono::prelude::*;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
 use simplelog::*;
 
 use ffplayout::{
@@ -19,8 +11,14 @@ use ffplayout::{
 };
 
 use ffplayout_lib::utils::{
-    generate_playlist, import::import_file, init_logging, send_mail, validate_ffmpeg,
-    OutputMode::*, PlayerControl, PlayoutStatus, ProcessControl,
+    epg::epg_writer,
+    export::export_playlist,
+    playlist_copy::{copy_playlist, CopyOptions},
+};
+use ffplayout_lib::utils::{
+    generate_playlist, import::import_file, init_logging, read_status, state_saver, status,
+    validate_ffmpeg, write_status, AlertDispatcher, OutputMode::*, PlayerControl, PlayoutConfig,
+    PlayoutStatus, ProcessControl, ProcessMode::*,
 };
 
 #[cfg(debug_assertions)]
@@ -29,42 +27,36 @@ use ffplayout::utils::Args;
 #[cfg(debug_assertions)]
 use ffplayout_lib::utils::{mock_time, time_now};
 
-#[derive(Serialize, Deserialize)]
-struct StatusData {
-    time_shift: f64,
-    date: String,
-}
-
 /// Here we create a status file in temp folder.
 /// We need this for reading/saving program status.
 /// For example when we skip a playing file,
 /// we save the time difference, so we stay in sync.
 ///
 /// When file not exists we create it, and when it exists we get its values.
-fn status_file(stat_file: &str, playout_stat: &PlayoutStatus) {
+/// In resume mode the time shift is calculated from the last saved playout position.
+fn status_file(config: &PlayoutConfig, playout_stat: &PlayoutStatus) {
+    let stat_file = &config.general.stat_file;
     debug!("Status file path: <b><magenta>{stat_file}</></b>");
 
     if !PathBuf::from(stat_file).exists() {
-        let data = json!({
-            "time_shift": 0.0,
-            "date": String::new(),
-        });
-
-        let json: String = serde_json::to_string(&data).expect("Serialize status data failed");
-        if let Err(e) = fs::write(stat_file, &json) {
-            error!("Unable to write to status file <b><magenta>{stat_file}</></b>: {e}");
-        };
+        write_status(config, "", 0.0);
     } else {
-        let stat_file = File::options()
-            .read(true)
-            .write(false)
-            .open(&stat_file)
-            .expect("Could not open status file");
-
-        let data: StatusData =
-            serde_json::from_reader(stat_file).expect("Could not read status file.");
+        let data = read_status(stat_file);
+        let mut time_shift = data.time_shift;
+
+        if config.processing.mode == Playlist {
+            if let Some(shift) = status::resume_shift(config, &data) {
+                info!(
+                    "Resume playlist from <yellow>{}</> at <b><magenta>{}</></b>",
+                    data.date, data.source
+                );
+
+                time_shift = shift;
+                write_status(config, &data.date, shift);
+            }
+        }
 
-        *playout_stat.time_shift.lock().unwrap() = data.time_shift;
+        *playout_stat.time_shift.lock().unwrap() = time_shift;
         *playout_stat.date.lock().unwrap() = data.date;
     }
 }
@@ -95,9 +87,15 @@ fn main() {
     let play_stat = playout_stat.clone();
     let proc_ctl1 = proc_control.clone();
     let proc_ctl2 = proc_control.clone();
-    let messages = Arc::new(Mutex::new(Vec::new()));
-
-    let logging = init_logging(&config, Some(proc_ctl1), Some(messages.clone()));
+    let proc_ctl3 = proc_control.clone();
+    let alerts = AlertDispatcher::new(&config);
+
+    let logging = init_logging(
+        &config,
+        Some(proc_ctl1),
+        Some(play_control.clone()),
+        Some(alerts.clone()),
+    );
     CombinedLogger::init(logging).unwrap();
 
     if let Err(e) = validate_ffmpeg(&config) {
@@ -122,10 +120,76 @@ fn main() {
             exit(1);
         }
 
-        // convert text/m3u file to playlist
+        // convert text/m3u/csv/xspf file to playlist
         match import_file(&config, &args.date.unwrap(), None, &path) {
-            Ok(m) => {
-                info!("{m}");
+            Ok(report) => {
+                for e in &report.errors {
+                    warn!("Line {}: {}", e.line, e.message);
+                }
+
+                info!("{}", report.message);
+                exit(0);
+            }
+            Err(e) => {
+                error!("{e}");
+                exit(1);
+            }
+        }
+    }
+
+    if let Some(format) = args.export {
+        let date = match args.date.clone() {
+            Some(d) => d,
+            None => {
+                error!("Export needs date parameter!");
+
+                exit(1);
+            }
+        };
+        let path = args
+            .export_file
+            .clone()
+            .unwrap_or_else(|| format!("{date}.{}", format.extension()));
+
+        match export_playlist(&config, &date, format).and_then(|content| fs::write(&path, content))
+        {
+            Ok(_) => {
+                info!("Export playlist from {date} to <b><magenta>{path}</></b>");
+                exit(0);
+            }
+            Err(e) => {
+                error!("{e}");
+                exit(1);
+            }
+        }
+    }
+
+    if let Some(source) = args.copy.clone().or_else(|| args.move_playlist.clone()) {
+        if args.target.is_none() {
+            error!("Copy/move needs target parameter!");
+
+            exit(1);
+        }
+
+        let options = CopyOptions {
+            overwrite: args.overwrite,
+            shift: args.shift.unwrap_or_default(),
+            probe: args.probe,
+        };
+
+        match copy_playlist(
+            &config,
+            &source,
+            &args.target.unwrap(),
+            &options,
+            args.move_playlist.is_some(),
+        ) {
+            Ok(report) => {
+                if !report.skipped.is_empty() {
+                    warn!("Skip existing playlists: {}", report.skipped.join(", "));
+                }
+
+                info!("Copy playlist to {} date(s) done", report.written.len());
                 exit(0);
             }
             Err(e) => {
@@ -140,7 +204,22 @@ fn main() {
         thread::spawn(move || json_rpc_server(config_clone, play_ctl, play_stat, proc_ctl2));
     }
 
-    status_file(&config.general.stat_file, &playout_stat);
+    status_file(&config, &playout_stat);
+
+    let config_clone = config.clone();
+    let play_ctl = play_control.clone();
+    let play_stat = playout_stat.clone();
+
+    // Save playout state periodically, to be able to resume after a crash.
+    thread::spawn(move || state_saver(config_clone, play_ctl, play_stat, proc_ctl3));
+
+    if let Some(path) = args.epg {
+        let config_clone = config.clone();
+        let channel = args.channel.unwrap_or_else(|| "Channel 1".to_string());
+
+        // Keep the XMLTV file up to date for external EPG consumers.
+        thread::spawn(move || epg_writer(config_clone, channel, args.epg_days, path));
+    }
 
     match config.out.mode {
         // write files/playlist to HLS m3u8 playlist
@@ -151,11 +230,6 @@ fn main() {
 
     info!("Playout done...");
 
-    let msg = messages.lock().unwrap();
-
-    if msg.len() > 0 {
-        send_mail(&config, msg.join("\n"));
-    }
-
-    drop(msg);
+    // send remaining alerts
+    alerts.dispatch(true);
 }
@@ -227,7 +227,14 @@ pub fn json_rpc_server(
             // get infos about current clip
             if map.contains_key("media") && &map["media"] == "current" {
                 if let Some(media) = play_control.current_media.lock().unwrap().clone() {
-                    let data_map = get_data_map(&config, media);
+                    let mut data_map = get_data_map(&config, media);
+                    let sync_delta = *playout_stat.sync_delta.lock().unwrap();
+
+                    data_map.insert(
+                        "ingest_runs".to_string(),
+                        json!(proc.server_is_running.load(Ordering::SeqCst)),
+                    );
+                    data_map.insert("sync_delta".to_string(), json!(sync_delta));
 
                     return Ok(Value::Object(data_map));
                 };
@@ -1,6 +1,6 @@
 use clap::Parser;
 
-use ffplayout_lib::utils::{OutputMode, ProcessMode};
+use ffplayout_lib::utils::{export::ExportFormat, OutputMode, ProcessMode};
 
 #[derive(Parser, Debug, Clone)]
 #[clap(version,
@@ -36,16 +36,80 @@ pub struct Args {
     #[clap(
         short,
         long,
-        help = "Target date (YYYY-MM-DD) for text/m3u to playlist import"
+        help = "Target date (YYYY-MM-DD) for playlist import, or for export"
     )]
     pub date: Option<String>,
 
     #[clap(
         long,
-        help = "Import a given text/m3u file and create a playlist from it"
+        help = "Import a given text/m3u/csv/xspf file and create a playlist from it"
     )]
     pub import: Option<String>,
 
+    #[clap(
+        long,
+        help = "Export playlist from --date to format: m3u, csv, html",
+        value_name = "FORMAT"
+    )]
+    pub export: Option<ExportFormat>,
+
+    #[clap(
+        long,
+        help = "File path for export, default is <DATE>.<FORMAT> in current folder",
+        value_name = "PATH"
+    )]
+    pub export_file: Option<String>,
+
+    #[clap(
+        long,
+        help = "Copy playlist from date (YYYY-MM-DD) to --target",
+        value_name = "YYYY-MM-DD"
+    )]
+    pub copy: Option<String>,
+
+    #[clap(
+        long = "move",
+        help = "Move playlist from date (YYYY-MM-DD) to --target",
+        value_name = "YYYY-MM-DD"
+    )]
+    pub move_playlist: Option<String>,
+
+    #[clap(
+        long,
+        help = "Target date for copy/move, or range like: 2022-01-01 - 2022-01-10",
+        value_name = "YYYY-MM-DD",
+        multiple_values = true
+    )]
+    pub target: Option<Vec<String>>,
+
+    #[clap(long, help = "Overwrite existing playlists on copy/move")]
+    pub overwrite: bool,
+
+    #[clap(
+        long,
+        help = "Shift items on copy/move by seconds, negative values move them earlier",
+        allow_hyphen_values = true
+    )]
+    pub shift: Option<f64>,
+
+    #[clap(long, help = "Read durations from media files again on copy/move")]
+    pub probe: bool,
+
+    #[clap(
+        long,
+        help = "Write XMLTV EPG file from the playlists, it gets updated every day",
+        value_name = "PATH"
+    )]
+    pub epg: Option<String>,
+
+    #[clap(
+        long,
+        help = "Number of days in the EPG file",
+        value_name = "DAYS",
+        default_value = "7"
+    )]
+    pub epg_days: i64,
+
     #[clap(short, long, help = "Path from playlist")]
     pub playlist: Option<String>,
 
@@ -15,7 +15,7 @@ use ffplayout_lib::{
 
 /// Read command line arguments, and override the config with them.
 pub fn get_config(args: Args) -> PlayoutConfig {
-    let cfg_path = match args.channel {
+    let cfg_path = match args.channel.clone() {
         Some(c) => {
             let path = PathBuf::from(format!("/etc/ffplayout/{c}.yml"));
 
@@ -33,6 +33,10 @@ pub fn get_config(args: Args) -> PlayoutConfig {
 
     let mut config = PlayoutConfig::new(cfg_path);
 
+    if let Some(channel) = args.channel {
+        config.general.channel = channel;
+    }
+
     if let Some(gen) = args.generate {
         config.general.generate = Some(gen);
     }
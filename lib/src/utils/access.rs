@@ -0,0 +1,81 @@
+/// Channel Access
+///
+/// Roles from users for a single channel, the config view for users which are not allowed
+/// to see the secrets from the channel, and the checks for API tokens.
+use crate::utils::{alert::AlertKind, PlayoutConfig};
+
+/// Role from user for a single channel, every role includes the rights from the roles before.
+///
+/// - **viewer:** read playlists, logs, files and the playout state
+/// - **editor:** edit playlists, files and text presets
+/// - **operator:** control the playout and send text messages
+/// - **admin:** read the playout config with its secrets
+///
+/// Changing the playout config is only allowed for global admins.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum ChannelRole {
+    Viewer,
+    Editor,
+    Operator,
+    Admin,
+}
+
+impl ChannelRole {
+    pub fn set_role(role: &str) -> Option<Self> {
+        match role {
+            "viewer" => Some(ChannelRole::Viewer),
+            "editor" => Some(ChannelRole::Editor),
+            "operator" => Some(ChannelRole::Operator),
+            "admin" => Some(ChannelRole::Admin),
+            _ => None,
+        }
+    }
+
+    /// Role from the database is at least the needed one, unknown roles have no rights.
+    pub fn allows(role: &str, needed: ChannelRole) -> bool {
+        Self::set_role(role).map_or(false, |r| r >= needed)
+    }
+}
+
+/// GET requests which write data or start processes, not allowed for tokens with read scope.
+const SIDE_EFFECT_PATHS: [&str; 1] = ["/thumb"];
+
+/// Token state from the database.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokenState {
+    /// Access token is on the revocation list.
+    pub revoked: bool,
+    /// Refresh or API token exists in the tokens table.
+    pub stored: bool,
+    /// Tokens which are issued before this time are revoked.
+    pub valid_after: i64,
+}
+
+/// Access tokens are revoked when they are on the revocation list, refresh and API tokens
+/// when they are deleted. A password or role change revokes all tokens issued before.
+pub fn is_revoked(kind: &str, issued: i64, state: &TokenState) -> bool {
+    let listed = match kind {
+        "access" => state.revoked,
+        _ => !state.stored,
+    };
+
+    listed || issued < state.valid_after
+}
+
+/// Tokens with read scope are only allowed to get data.
+pub fn scope_allows(scope: Option<&str>, method: &str, path: &str) -> bool {
+    scope != Some("read")
+        || (method == "GET" && !SIDE_EFFECT_PATHS.iter().any(|p| path.contains(p)))
+}
+
+/// Remove passwords, tokens and alert targets from the config.
+pub fn redact_secrets(config: &mut PlayoutConfig) {
+    config.rpc_server.authorization.clear();
+    config.mail.sender_pass.clear();
+
+    for target in config.alert.targets.iter_mut() {
+        if target.kind != AlertKind::Mail {
+            target.target.clear();
+        }
+    }
+}
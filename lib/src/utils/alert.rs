@@ -0,0 +1,382 @@
+/// Alert Dispatcher
+///
+/// Collect log messages and send them to the configured alert targets:
+///
+/// - **webhook:** HTTP POST request with a JSON body
+/// - **command:** run a local command or script, the message comes over stdin
+/// - **mail:** send a mail over the SMTP settings from the mail section
+///
+/// Every target has its own level filter and template. Repeated messages are merged
+/// and counted, and a target sends at most one alert per interval.
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    sync::{atomic::Ordering, Arc, Mutex},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use chrono::prelude::*;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shlex::split;
+use simplelog::*;
+
+use super::logging::{clean_string, remove_style_tags, IGNORE_TARGETS};
+use crate::utils::{send_mail, PlayoutConfig, ProcessControl};
+
+pub const DEFAULT_TEMPLATE: &str = "[{time}] [{level}] {channel}: {message} ({count}x)";
+
+// Prefix for messages from the dispatcher itself, this messages we never send.
+const ALERT_PREFIX: &str = "<bright black>[Alert]</>";
+
+/// Seconds to wait for a webhook response or a command to finish.
+const SEND_TIMEOUT: u64 = 30;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertKind {
+    Webhook,
+    Command,
+    Mail,
+}
+
+/// Alert target from config.
+///
+/// `target` is the webhook url, the command line, or the mail recipients.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlertTarget {
+    #[serde(rename = "type")]
+    pub kind: AlertKind,
+    #[serde(default)]
+    pub target: String,
+    #[serde(default = "default_level")]
+    pub level: String,
+    #[serde(default)]
+    pub template: String,
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_level() -> String {
+    "ERROR".to_string()
+}
+
+fn default_interval() -> u64 {
+    30
+}
+
+/// Map level string from config to log level filter.
+pub fn level_filter(level: &str) -> LevelFilter {
+    match level.to_lowercase().as_str() {
+        "debug" => LevelFilter::Debug,
+        "info" => LevelFilter::Info,
+        "warning" => LevelFilter::Warn,
+        _ => LevelFilter::Error,
+    }
+}
+
+/// One alert message, repeated messages only increase the count.
+#[derive(Debug, Serialize, Clone)]
+pub struct AlertMessage {
+    pub time: String,
+    pub level: String,
+    pub message: String,
+    pub count: usize,
+}
+
+impl AlertMessage {
+    /// Fill template placeholders with message values.
+    fn render(&self, template: &str, channel: &str) -> String {
+        let template = match template.is_empty() {
+            true => DEFAULT_TEMPLATE,
+            false => template,
+        };
+
+        template
+            .replace("{time}", &self.time)
+            .replace("{level}", &self.level)
+            .replace("{channel}", channel)
+            .replace("{message}", &self.message)
+            .replace("{count}", &self.count.to_string())
+    }
+}
+
+#[derive(Debug)]
+struct AlertQueue {
+    target: AlertTarget,
+    filter: LevelFilter,
+    messages: Mutex<Vec<AlertMessage>>,
+    last_send: Mutex<Option<Instant>>,
+}
+
+impl AlertQueue {
+    fn new(target: AlertTarget) -> Self {
+        Self {
+            filter: level_filter(&target.level),
+            target,
+            messages: Mutex::new(vec![]),
+            last_send: Mutex::new(None),
+        }
+    }
+
+    fn push(&self, level: Level, message: &str) {
+        if level > self.filter {
+            return;
+        }
+
+        let mut messages = self.messages.lock().unwrap();
+
+        if let Some(msg) = messages.iter_mut().find(|m| m.message == message) {
+            msg.count += 1;
+        } else {
+            messages.push(AlertMessage {
+                time: Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+                level: level.to_string(),
+                message: message.to_string(),
+                count: 1,
+            });
+        }
+    }
+
+    /// Take the queued messages, when interval is over or when we force it.
+    fn take(&self, force: bool) -> Vec<AlertMessage> {
+        let mut last_send = self.last_send.lock().unwrap();
+
+        if !force
+            && last_send
+                .map(|t| t.elapsed() < Duration::from_secs(self.target.interval))
+                .unwrap_or(false)
+        {
+            return vec![];
+        }
+
+        let messages: Vec<AlertMessage> = self.messages.lock().unwrap().drain(..).collect();
+
+        if !messages.is_empty() {
+            *last_send = Some(Instant::now());
+        }
+
+        messages
+    }
+}
+
+/// Global alert dispatcher, shared between logger and sending thread.
+#[derive(Clone, Debug)]
+pub struct AlertDispatcher {
+    config: PlayoutConfig,
+    queues: Arc<Vec<AlertQueue>>,
+}
+
+impl AlertDispatcher {
+    /// Create dispatcher from alert targets in config.
+    ///
+    /// For compatibility, a mail target is also created from the mail section,
+    /// when a recipient is set there and no mail target is defined.
+    pub fn new(config: &PlayoutConfig) -> Self {
+        let mut targets = config.alert.targets.clone();
+
+        if config.mail.recipient.contains('@')
+            && config.mail.recipient.contains('.')
+            && !targets.iter().any(|t| t.kind == AlertKind::Mail)
+        {
+            targets.push(AlertTarget {
+                kind: AlertKind::Mail,
+                target: config.mail.recipient.clone(),
+                level: config.mail.mail_level.clone(),
+                template: String::new(),
+                interval: config.mail.interval,
+            });
+        }
+
+        Self {
+            config: config.clone(),
+            queues: Arc::new(targets.into_iter().map(AlertQueue::new).collect()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queues.is_empty()
+    }
+
+    /// Lowest level, which one of the targets wants to get.
+    pub fn level(&self) -> LevelFilter {
+        self.queues
+            .iter()
+            .map(|q| q.filter)
+            .max()
+            .unwrap_or(LevelFilter::Off)
+    }
+
+    pub fn push(&self, level: Level, message: &str) {
+        let message = clean_string(&remove_style_tags(message));
+
+        for queue in self.queues.iter() {
+            queue.push(level, &message);
+        }
+    }
+
+    /// Send all queued messages, when `force` is false only from targets with elapsed interval.
+    pub fn dispatch(&self, force: bool) {
+        for queue in self.queues.iter() {
+            let messages = queue.take(force);
+
+            if messages.is_empty() {
+                continue;
+            }
+
+            if let Err(e) = self.send(&queue.target, &messages) {
+                error!(
+                    "{ALERT_PREFIX} Sending alert to {:?} failed: {e}",
+                    queue.target.kind
+                );
+            }
+        }
+    }
+
+    fn send(&self, target: &AlertTarget, messages: &[AlertMessage]) -> Result<(), String> {
+        let channel = &self.config.general.channel;
+        let text = messages
+            .iter()
+            .map(|m| m.render(&target.template, channel))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        match target.kind {
+            AlertKind::Webhook => {
+                let body = json!({
+                    "channel": channel,
+                    "text": text,
+                    "messages": messages,
+                });
+
+                let response = reqwest::blocking::Client::builder()
+                    .timeout(Duration::from_secs(SEND_TIMEOUT))
+                    .build()
+                    .map_err(|e| e.to_string())?
+                    .post(&target.target)
+                    .json(&body)
+                    .send()
+                    .map_err(|e| e.to_string())?;
+
+                if !response.status().is_success() {
+                    return Err(format!("Webhook response: {}", response.status()));
+                }
+            }
+            AlertKind::Command => {
+                let cmd = split(&target.target).unwrap_or_default();
+
+                if cmd.is_empty() {
+                    return Err("No command defined!".to_string());
+                }
+
+                let mut proc = Command::new(&cmd[0])
+                    .args(&cmd[1..])
+                    .env("FFP_CHANNEL", channel)
+                    .env("FFP_LEVEL", &messages[0].level)
+                    .env("FFP_COUNT", messages.len().to_string())
+                    .stdin(Stdio::piped())
+                    .spawn()
+                    .map_err(|e| e.to_string())?;
+
+                if let Some(mut stdin) = proc.stdin.take() {
+                    stdin
+                        .write_all(text.as_bytes())
+                        .map_err(|e| e.to_string())?;
+                }
+
+                let started = Instant::now();
+
+                let status = loop {
+                    if let Some(status) = proc.try_wait().map_err(|e| e.to_string())? {
+                        break status;
+                    }
+
+                    if started.elapsed() > Duration::from_secs(SEND_TIMEOUT) {
+                        let _ = proc.kill();
+                        let _ = proc.wait();
+
+                        return Err(format!("Command timed out after {SEND_TIMEOUT}s"));
+                    }
+
+                    sleep(Duration::from_millis(100));
+                };
+
+                if !status.success() {
+                    return Err(format!("Command exit with: {status}"));
+                }
+            }
+            AlertKind::Mail => {
+                let mut config = self.config.clone();
+
+                if !target.target.is_empty() {
+                    config.mail.recipient = target.target.clone();
+                }
+
+                send_mail(&config, text)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Send alerts every second, from all targets which interval is over.
+pub fn alert_queue(dispatcher: AlertDispatcher, proc_ctl: ProcessControl) {
+    while !proc_ctl.is_terminated.load(Ordering::SeqCst) {
+        dispatcher.dispatch(false);
+
+        sleep(Duration::from_secs(1));
+    }
+}
+
+/// Alert logger, to put log messages to the dispatcher.
+pub struct AlertLogger {
+    level: LevelFilter,
+    dispatcher: AlertDispatcher,
+}
+
+impl AlertLogger {
+    pub fn new(dispatcher: AlertDispatcher) -> Box<AlertLogger> {
+        Box::new(AlertLogger {
+            level: dispatcher.level(),
+            dispatcher,
+        })
+    }
+}
+
+impl Log for AlertLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level
+            && !IGNORE_TARGETS
+                .iter()
+                .any(|t| metadata.target().starts_with(t))
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if self.enabled(record.metadata()) {
+            let message = record.args().to_string();
+
+            if !message.starts_with(ALERT_PREFIX) {
+                self.dispatcher.push(record.level(), &message);
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+impl SharedLogger for AlertLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        None
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
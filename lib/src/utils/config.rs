@@ -10,7 +10,7 @@ use serde::{Deserialize, Serialize};
 use shlex::split;
 
 use super::vec_strings;
-use crate::utils::{free_tcp_socket, home_dir, time_to_sec, OutputMode::*};
+use crate::utils::{alert::AlertTarget, free_tcp_socket, home_dir, time_to_sec, OutputMode::*};
 
 pub const DUMMY_LEN: f64 = 60.0;
 pub const IMAGE_FORMAT: [&str; 21] = [
@@ -77,6 +77,35 @@ impl FromStr for ProcessMode {
     }
 }
 
+/// Resume behavior after a restart of the playout.
+///
+/// - **Resume:** continue with the clip and position, where the playout has stopped
+/// - **Sync:** jump to the clip which belongs to the current time
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResumeMode {
+    Resume,
+    Sync,
+}
+
+impl Default for ResumeMode {
+    fn default() -> Self {
+        ResumeMode::Sync
+    }
+}
+
+impl FromStr for ResumeMode {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "resume" => Ok(Self::Resume),
+            "sync" => Ok(Self::Sync),
+            _ => Err("Use 'resume' or 'sync'".to_string()),
+        }
+    }
+}
+
 /// Global Config
 ///
 /// This we init ones, when ffplayout is starting and use them globally in the hole program.
@@ -85,11 +114,15 @@ pub struct PlayoutConfig {
     pub general: General,
     pub rpc_server: RpcServer,
     pub mail: Mail,
+    #[serde(default)]
+    pub alert: Alert,
     pub logging: Logging,
     pub processing: Processing,
     pub ingest: Ingest,
     pub playlist: Playlist,
     pub storage: Storage,
+    #[serde(default)]
+    pub conform: Conform,
     pub text: Text,
     pub out: Out,
 }
@@ -98,6 +131,11 @@ pub struct PlayoutConfig {
 pub struct General {
     pub help_text: String,
     pub stop_threshold: f64,
+    #[serde(default)]
+    pub resume_mode: ResumeMode,
+
+    #[serde(skip_serializing, skip_deserializing)]
+    pub channel: String,
 
     #[serde(skip_serializing, skip_deserializing)]
     pub generate: Option<Vec<String>>,
@@ -127,6 +165,14 @@ pub struct Mail {
     pub interval: u64,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct Alert {
+    #[serde(default)]
+    pub help_text: String,
+    #[serde(default)]
+    pub targets: Vec<AlertTarget>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Logging {
     pub help_text: String,
@@ -137,6 +183,8 @@ pub struct Logging {
     pub log_path: String,
     pub log_level: String,
     pub ffmpeg_level: String,
+    #[serde(default)]
+    pub json_format: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -171,7 +219,7 @@ pub struct Processing {
 pub struct Ingest {
     pub help_text: String,
     pub enable: bool,
-    input_param: String,
+    pub input_param: String,
 
     #[serde(skip_serializing, skip_deserializing)]
     pub input_cmd: Option<Vec<String>>,
@@ -201,6 +249,40 @@ pub struct Storage {
     pub filler_clip: String,
     pub extensions: Vec<String>,
     pub shuffle: bool,
+    /// Max size for uploads in MB, 0 means no limit.
+    #[serde(default)]
+    pub upload_limit: u64,
+}
+
+/// House format for uploaded files, resolution, aspect, fps and loudness come from processing.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Conform {
+    pub help_text: String,
+    pub enable: bool,
+    pub video_codec: String,
+    pub video_param: String,
+    pub audio_codec: String,
+    pub audio_param: String,
+    pub audio_channels: u32,
+    pub extension: String,
+    pub keep_original: bool,
+}
+
+impl Default for Conform {
+    fn default() -> Self {
+        Self {
+            help_text: String::new(),
+            enable: false,
+            video_codec: "libx264".to_string(),
+            video_param: "-preset medium -crf 18".to_string(),
+            audio_codec: "aac".to_string(),
+            audio_param: "-b:a 192k".to_string(),
+            audio_channels: 2,
+            extension: "mp4".to_string(),
+            keep_original: true,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -211,10 +293,11 @@ pub struct Text {
     #[serde(skip_serializing, skip_deserializing)]
     pub node_pos: Option<usize>,
 
-    #[serde(skip_serializing, skip_deserializing)]
+    /// Fixed sockets for the text messages, free ones are picked at start when they are not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub zmq_stream_socket: Option<String>,
 
-    #[serde(skip_serializing, skip_deserializing)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub zmq_server_socket: Option<String>,
 
     pub fontfile: String,
@@ -262,6 +345,7 @@ impl PlayoutConfig {
 
         let mut config: PlayoutConfig =
             serde_yaml::from_reader(f).expect("Could not read config file.");
+        config.general.channel = "Channel 1".to_string();
         config.general.generate = None;
         config.general.stat_file = home_dir()
             .unwrap_or_else(env::temp_dir)
@@ -318,9 +402,16 @@ impl PlayoutConfig {
         // to get text messages from it
         if config.text.add_text && !config.text.text_from_filename {
             config.rpc_server.enable = true;
-            config.text.zmq_stream_socket = free_tcp_socket(String::new());
-            config.text.zmq_server_socket =
-                free_tcp_socket(config.text.zmq_stream_socket.clone().unwrap_or_default());
+
+            if config.text.zmq_stream_socket.is_none() {
+                config.text.zmq_stream_socket = free_tcp_socket(String::new());
+            }
+
+            if config.text.zmq_server_socket.is_none() {
+                config.text.zmq_server_socket =
+                    free_tcp_socket(config.text.zmq_stream_socket.clone().unwrap_or_default());
+            }
+
             config.text.node_pos = Some(2);
         } else {
             config.text.zmq_stream_socket = None;
@@ -0,0 +1,298 @@
+/// Conform media files to the house format
+///
+/// Files are scaled and padded to the processing resolution, converted to its fps
+/// and encoded with the codecs from the `conform` config. For the audio the loudness gets
+/// measured in a first pass, the second pass normalizes it with the measured values.
+/// The playout has then nothing more to adjust for these files.
+use std::{
+    fs,
+    io::{BufRead, BufReader, Error, ErrorKind, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+use shlex::split;
+use simplelog::*;
+
+use crate::utils::{MediaProbe, PlayoutConfig};
+use crate::vec_strings;
+
+/// Values from the first loudnorm pass.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Loudness {
+    pub input_i: String,
+    pub input_tp: String,
+    pub input_lra: String,
+    pub input_thresh: String,
+    pub target_offset: String,
+}
+
+/// Get the loudnorm json block from ffmpeg log output.
+pub fn loudness_from_log(log: &str) -> Option<Loudness> {
+    let start = log.rfind('{')?;
+    let end = log[start..].find('}')? + start;
+
+    serde_json::from_str(&log[start..=end]).ok()
+}
+
+/// Path for the conformed file. Only the source itself gets replaced, and only when the original
+/// should not be kept. Every other taken name gets a `_conform` suffix, with a number when needed.
+pub fn conform_target(config: &PlayoutConfig, source: &Path) -> PathBuf {
+    let extension = &config.conform.extension;
+    let target = source.with_extension(extension);
+
+    if target == source {
+        if !config.conform.keep_original {
+            return target;
+        }
+    } else if !target.exists() {
+        return target;
+    }
+
+    let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+    let mut target = source.with_file_name(format!("{stem}_conform.{extension}"));
+    let mut count = 1;
+
+    while target.exists() {
+        count += 1;
+        target = source.with_file_name(format!("{stem}_conform_{count}.{extension}"));
+    }
+
+    target
+}
+
+fn loudnorm(config: &PlayoutConfig) -> String {
+    format!(
+        "loudnorm=I={}:TP={}:LRA={}",
+        config.processing.loud_i, config.processing.loud_tp, config.processing.loud_lra
+    )
+}
+
+/// Create ffmpeg command for the second pass.
+pub fn conform_cmd(
+    config: &PlayoutConfig,
+    source: &str,
+    target: &str,
+    has_video: bool,
+    loudness: Option<&Loudness>,
+) -> Vec<String> {
+    let p = &config.processing;
+    let c = &config.conform;
+    let mut cmd = vec_strings![
+        "-hide_banner",
+        "-nostats",
+        "-v",
+        "error",
+        "-progress",
+        "pipe:1",
+        "-y",
+        "-i",
+        source
+    ];
+
+    if has_video {
+        cmd.append(&mut vec_strings!["-map", "0:v:0", "-vf"]);
+        cmd.push(format!(
+            "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,\
+            setdar={},fps={},format=yuv420p",
+            p.aspect,
+            p.fps,
+            w = p.width,
+            h = p.height
+        ));
+        cmd.append(&mut vec_strings!["-c:v", c.video_codec]);
+        cmd.append(&mut split(&c.video_param).unwrap_or_default());
+    }
+
+    if let Some(l) = loudness {
+        cmd.append(&mut vec_strings!["-map", "0:a:0", "-af"]);
+        cmd.push(format!(
+            "{}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true,\
+            aresample=48000",
+            loudnorm(config),
+            l.input_i,
+            l.input_tp,
+            l.input_lra,
+            l.input_thresh,
+            l.target_offset
+        ));
+        cmd.append(&mut vec_strings![
+            "-c:a",
+            c.audio_codec,
+            "-ac",
+            c.audio_channels
+        ]);
+        cmd.append(&mut split(&c.audio_param).unwrap_or_default());
+    }
+
+    cmd.push(target.to_string());
+
+    cmd
+}
+
+/// Run ffmpeg and give the progress from 0.0 to 1.0 back. The stderr output is returned.
+///
+/// When `progress` returns false, ffmpeg gets killed.
+fn run_ffmpeg(
+    cmd: &[String],
+    duration: f64,
+    progress: &mut dyn FnMut(f64) -> bool,
+) -> Result<String, Error> {
+    debug!(
+        "Conform command: <bright-blue>\"ffmpeg {}\"</>",
+        cmd.join(" ")
+    );
+
+    let mut proc = Command::new("ffmpeg")
+        .args(cmd)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // read stderr in its own thread, a full pipe would block ffmpeg
+    let mut stderr = proc.stderr.take().unwrap();
+    let err_reader = thread::spawn(move || {
+        let mut log = String::new();
+        stderr.read_to_string(&mut log).map(|_| log)
+    });
+
+    for line in BufReader::new(proc.stdout.take().unwrap()).lines() {
+        let line = line?;
+
+        if let Some(us) = line
+            .strip_prefix("out_time_us=")
+            .and_then(|t| t.parse::<f64>().ok())
+        {
+            if duration > 0.0 && !progress((us / 1_000_000.0 / duration).clamp(0.0, 1.0)) {
+                proc.kill()?;
+                proc.wait()?;
+
+                return Err(Error::new(ErrorKind::Interrupted, "Conform stopped"));
+            }
+        }
+    }
+
+    let status = proc.wait()?;
+    let log = err_reader.join().unwrap_or_else(|_| Ok(String::new()))?;
+
+    if !status.success() {
+        let last = log.lines().last().unwrap_or("unknown error").to_string();
+
+        return Err(Error::new(
+            ErrorKind::Other,
+            format!("ffmpeg failed: {last}"),
+        ));
+    }
+
+    Ok(log)
+}
+
+/// Measure loudness with the first loudnorm pass.
+pub fn measure_loudness(
+    config: &PlayoutConfig,
+    source: &str,
+    duration: f64,
+    progress: &mut dyn FnMut(f64) -> bool,
+) -> Result<Loudness, Error> {
+    let cmd = vec_strings![
+        "-hide_banner",
+        "-nostats",
+        "-progress",
+        "pipe:1",
+        "-i",
+        source,
+        "-map",
+        "0:a:0",
+        "-af",
+        format!("{}:print_format=json", loudnorm(config)),
+        "-f",
+        "null",
+        "-"
+    ];
+
+    let log = run_ffmpeg(&cmd, duration, progress)?;
+
+    loudness_from_log(&log)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "No loudness values found!"))
+}
+
+/// Transcode file to house format, progress goes from 0.0 to 1.0 over both passes.
+/// When `progress` returns false, the transcoding stops.
+///
+/// Returns the path of the conformed file. A different original is not removed here,
+/// the caller knows, if it is still in use.
+pub fn conform_file(
+    config: &PlayoutConfig,
+    source: &Path,
+    progress: &mut dyn FnMut(f64) -> bool,
+) -> Result<PathBuf, Error> {
+    let src = source.to_string_lossy().to_string();
+    let probe = MediaProbe::new(&src);
+    let duration: f64 = probe
+        .format
+        .as_ref()
+        .and_then(|f| f.duration.as_ref())
+        .and_then(|d| d.parse().ok())
+        .unwrap_or_default();
+
+    if probe.format.is_none() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Can't read media file: {src}"),
+        ));
+    }
+
+    let has_video = !probe.video_streams.is_empty();
+    let mut loudness = None;
+
+    if !probe.audio_streams.is_empty() {
+        loudness = Some(measure_loudness(config, &src, duration, &mut |p| {
+            progress(p * 0.5)
+        })?);
+    }
+
+    let offset = if loudness.is_some() { 0.5 } else { 0.0 };
+    let target = conform_target(config, source);
+    // temp file in the same folder, with the right extension for the muxer
+    let temp = target.with_file_name(format!(
+        ".{}.part.{}",
+        target.file_stem().unwrap_or_default().to_string_lossy(),
+        config.conform.extension
+    ));
+    let cmd = conform_cmd(
+        config,
+        &src,
+        &temp.to_string_lossy(),
+        has_video,
+        loudness.as_ref(),
+    );
+
+    if let Err(e) = run_ffmpeg(&cmd, duration, &mut |p| {
+        progress(offset + p * (1.0 - offset))
+    }) {
+        let _ = fs::remove_file(&temp);
+
+        return Err(e);
+    }
+
+    // the name could be taken while transcoding
+    if target != source && target.exists() {
+        let _ = fs::remove_file(&temp);
+
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("Target already exists: {}", target.display()),
+        ));
+    }
+
+    fs::rename(&temp, &target)?;
+
+    info!(
+        "Conform <b><magenta>{src}</></b> to <b><magenta>{}</></b>",
+        target.display()
+    );
+
+    Ok(target)
+}
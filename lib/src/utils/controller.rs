@@ -195,6 +195,8 @@ pub struct PlayoutStatus {
     pub date: Arc<Mutex<String>>,
     pub list_init: Arc<AtomicBool>,
     pub time_shift: Arc<Mutex<f64>>,
+    /// Delta between playlist and real time, from the start of the current clip.
+    pub sync_delta: Arc<Mutex<Option<f64>>>,
 }
 
 impl PlayoutStatus {
@@ -205,6 +207,7 @@ impl PlayoutStatus {
             date: Arc::new(Mutex::new(String::new())),
             list_init: Arc::new(AtomicBool::new(true)),
             time_shift: Arc::new(Mutex::new(0.0)),
+            sync_delta: Arc::new(Mutex::new(None)),
         }
     }
 }
@@ -0,0 +1,207 @@
+/// XMLTV EPG Export
+///
+/// Create an electronic program guide in XMLTV format from the playlists of a date range.
+/// The start times are calculated like in the playout, beginning with `day_start`.
+///
+/// The program title comes from the `title` field of a clip. When it is empty,
+/// the title gets extracted from the file name with `text.regex`, like the drawtext filter does.
+/// Advertisements get no own entry, they extend the program before.
+use std::{
+    ffi::OsStr,
+    fs::{create_dir_all, write},
+    io::Error,
+    path::Path,
+    thread::sleep,
+    time,
+};
+
+use chrono::{prelude::*, Duration};
+use regex::Regex;
+use simplelog::*;
+
+use crate::utils::{json_reader, playlist_copy::playlist_path, time_to_sec, Media, PlayoutConfig};
+
+#[derive(Debug, Clone)]
+pub struct EpgProgram {
+    pub start: DateTime<Local>,
+    pub stop: DateTime<Local>,
+    pub title: String,
+    pub category: String,
+}
+
+/// Regex for the title from the file name, compiled once for every export.
+pub fn title_regex(config: &PlayoutConfig) -> Option<Regex> {
+    Regex::new(&config.text.regex).ok()
+}
+
+/// Program title from clip, the `title` field has priority over the file name.
+pub fn media_title(regex: Option<&Regex>, media: &Media) -> String {
+    if !media.title.is_empty() {
+        return media.title.clone();
+    }
+
+    match regex
+        .and_then(|r| r.captures(&media.source))
+        .and_then(|c| c.get(1))
+    {
+        Some(t) => t.as_str().to_string(),
+        None => Path::new(&media.source)
+            .file_stem()
+            .unwrap_or_else(|| OsStr::new(&media.source))
+            .to_string_lossy()
+            .to_string(),
+    }
+}
+
+/// Day start in seconds, also when the config was only deserialized.
+pub fn day_start(config: &PlayoutConfig) -> f64 {
+    match config.playlist.start_sec {
+        Some(start) => start,
+        None if config.playlist.day_start.contains(':') => time_to_sec(&config.playlist.day_start),
+        None => 0.0,
+    }
+}
+
+/// Programs from the playlist of one date, empty when the playlist not exists.
+pub fn epg_programs(config: &PlayoutConfig, regex: Option<&Regex>, date: &str) -> Vec<EpgProgram> {
+    let mut programs: Vec<EpgProgram> = vec![];
+    let playlist = match json_reader(&playlist_path(&config.playlist.path, date)) {
+        Ok(p) => p,
+        Err(_) => return programs,
+    };
+    let midnight = match NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .and_then(|d| Local.from_local_datetime(&d).earliest())
+    {
+        Some(m) => m,
+        None => return programs,
+    };
+    let mut begin = day_start(config);
+
+    for item in playlist.program.iter() {
+        let length = item.out - item.seek;
+        let start = midnight + Duration::milliseconds((begin * 1000.0) as i64);
+        let stop = midnight + Duration::milliseconds(((begin + length) * 1000.0) as i64);
+        begin += length;
+
+        if item.category == "advertisement" {
+            if let Some(last) = programs.last_mut() {
+                last.stop = stop;
+                continue;
+            }
+        }
+
+        programs.push(EpgProgram {
+            start,
+            stop,
+            title: media_title(regex, item),
+            category: item.category.clone(),
+        });
+    }
+
+    programs
+}
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Channel id for XMLTV, from the channel name.
+pub fn channel_id(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Create XMLTV document from the playlists of the given dates.
+pub fn xmltv(config: &PlayoutConfig, channel_name: &str, dates: &[String]) -> String {
+    let id = escape_xml(&channel_id(channel_name));
+    let regex = title_regex(config);
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <tv generator-info-name=\"ffplayout\">\n  \
+        <channel id=\"{id}\">\n    \
+        <display-name>{}</display-name>\n  \
+        </channel>\n",
+        escape_xml(channel_name)
+    );
+
+    for date in dates {
+        for program in epg_programs(config, regex.as_ref(), date) {
+            xml.push_str(&format!(
+                "  <programme start=\"{}\" stop=\"{}\" channel=\"{id}\">\n    \
+                <title>{}</title>\n",
+                program.start.format("%Y%m%d%H%M%S %z"),
+                program.stop.format("%Y%m%d%H%M%S %z"),
+                escape_xml(&program.title)
+            ));
+
+            if !program.category.is_empty() {
+                xml.push_str(&format!(
+                    "    <category>{}</category>\n",
+                    escape_xml(&program.category)
+                ));
+            }
+
+            xml.push_str("  </programme>\n");
+        }
+    }
+
+    xml.push_str("</tv>\n");
+
+    xml
+}
+
+/// Write XMLTV file for today and the following days.
+///
+/// Yesterday is included, because its playlist can run over midnight.
+pub fn write_epg(
+    config: &PlayoutConfig,
+    channel_name: &str,
+    days: i64,
+    path: &str,
+) -> Result<(), Error> {
+    let today = Local::now().naive_local().date();
+    let dates: Vec<String> = (-1..days)
+        .map(|d| (today + Duration::days(d)).format("%Y-%m-%d").to_string())
+        .collect();
+
+    if let Some(parent) = Path::new(path).parent() {
+        if !parent.as_os_str().is_empty() {
+            create_dir_all(parent)?;
+        }
+    }
+
+    write(path, xmltv(config, channel_name, &dates))
+}
+
+/// Write the EPG file at start and again every time the date changes.
+pub fn epg_writer(config: PlayoutConfig, channel_name: String, days: i64, path: String) {
+    let mut last_date = String::new();
+
+    loop {
+        let date = Local::now().format("%Y-%m-%d").to_string();
+
+        if date != last_date {
+            match write_epg(&config, &channel_name, days, &path) {
+                Ok(_) => info!("Write EPG to <b><magenta>{path}</></b>"),
+                Err(e) => error!("Write EPG to <b><magenta>{path}</></b> failed: {e}"),
+            }
+
+            last_date = date;
+        }
+
+        sleep(time::Duration::from_secs(60));
+    }
+}
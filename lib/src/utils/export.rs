@@ -0,0 +1,177 @@
+/// Export playlist to extended M3U, CSV or a printable HTML run-down
+///
+/// The begin times are calculated from `day_start`, like in the playout.
+use std::{
+    io::{Error, ErrorKind},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{
+    epg::{day_start, escape_xml, media_title, title_regex},
+    json_reader,
+    playlist_copy::playlist_path,
+    sec_to_time, JsonPlaylist, PlayoutConfig,
+};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    M3u,
+    Csv,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::M3u => "m3u",
+            Self::Csv => "csv",
+            Self::Html => "html",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::M3u => "audio/x-mpegurl",
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "m3u" => Ok(Self::M3u),
+            "csv" => Ok(Self::Csv),
+            "html" => Ok(Self::Html),
+            _ => Err("Use 'm3u', 'csv' or 'html'".to_string()),
+        }
+    }
+}
+
+/// Begin time in seconds from every clip.
+fn begin_times(config: &PlayoutConfig, playlist: &JsonPlaylist) -> Vec<f64> {
+    let mut begin = day_start(config);
+
+    playlist
+        .program
+        .iter()
+        .map(|item| {
+            let start = begin;
+            begin += item.out - item.seek;
+            start
+        })
+        .collect()
+}
+
+fn to_m3u(config: &PlayoutConfig, playlist: &JsonPlaylist) -> String {
+    let regex = title_regex(config);
+    let mut m3u = String::from("#EXTM3U\n");
+
+    for item in playlist.program.iter() {
+        m3u.push_str(&format!(
+            "#EXTINF:{},{}\n{}\n",
+            (item.out - item.seek).round() as i64,
+            media_title(regex.as_ref(), item),
+            item.source
+        ));
+    }
+
+    m3u
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        return format!("\"{}\"", text.replace('"', "\"\""));
+    }
+
+    text.to_string()
+}
+
+fn to_csv(config: &PlayoutConfig, playlist: &JsonPlaylist) -> String {
+    let mut csv = String::from("begin,duration,in,out,source,category\n");
+
+    for (item, begin) in playlist.program.iter().zip(begin_times(config, playlist)) {
+        csv.push_str(&format!(
+            "{},{:.3},{:.3},{:.3},{},{}\n",
+            sec_to_time(begin),
+            item.out - item.seek,
+            item.seek,
+            item.out,
+            csv_field(&item.source),
+            csv_field(&item.category)
+        ));
+    }
+
+    csv
+}
+
+fn to_html(config: &PlayoutConfig, playlist: &JsonPlaylist) -> String {
+    let regex = title_regex(config);
+    let heading = escape_xml(&format!("{} - {}", playlist.channel, playlist.date));
+    let mut rows = String::new();
+
+    for (item, begin) in playlist.program.iter().zip(begin_times(config, playlist)) {
+        rows.push_str(&format!(
+            "      <tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            sec_to_time(begin),
+            sec_to_time(item.out - item.seek),
+            escape_xml(&media_title(regex.as_ref(), item)),
+            escape_xml(&item.category),
+            escape_xml(&item.source)
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset=\"utf-8\">
+    <title>{heading}</title>
+    <style>
+      body {{ font-family: sans-serif; font-size: 11pt; }}
+      table {{ border-collapse: collapse; width: 100%; }}
+      th, td {{ border-bottom: 1px solid #999; padding: 3px 6px; text-align: left; }}
+      td:nth-child(1), td:nth-child(2) {{ font-family: monospace; white-space: nowrap; }}
+      @media print {{ tr {{ page-break-inside: avoid; }} }}
+    </style>
+  </head>
+  <body>
+    <h1>{heading}</h1>
+    <table>
+      <tr><th>Begin</th><th>Duration</th><th>Title</th><th>Category</th><th>Source</th></tr>
+{rows}    </table>
+  </body>
+</html>
+"
+    )
+}
+
+/// Export playlist from date in the given format.
+pub fn export_playlist(
+    config: &PlayoutConfig,
+    date: &str,
+    format: ExportFormat,
+) -> Result<String, Error> {
+    let path = playlist_path(&config.playlist.path, date);
+
+    if !path.is_file() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("Playlist from {date} not exists!"),
+        ));
+    }
+
+    let playlist = json_reader(&path)?;
+
+    Ok(match format {
+        ExportFormat::M3u => to_m3u(config, &playlist),
+        ExportFormat::Csv => to_csv(config, &playlist),
+        ExportFormat::Html => to_html(config, &playlist),
+    })
+}
@@ -1,4 +1,5 @@
 use std::{
+    io::{Error, ErrorKind},
     path::Path,
     process::exit,
     sync::{
@@ -11,7 +12,9 @@ use rand::{seq::SliceRandom, thread_rng};
 use simplelog::*;
 use walkdir::WalkDir;
 
-use crate::utils::{get_sec, include_file, Media, PlayoutConfig};
+use crate::utils::{
+    get_sec, include_file, is_hidden, seek_and_length, Media, PlayoutConfig, StatusData,
+};
 
 /// Folder Sources
 ///
@@ -23,6 +26,7 @@ pub struct FolderSource {
     pub nodes: Arc<Mutex<Vec<Media>>>,
     current_node: Media,
     index: Arc<AtomicUsize>,
+    start_position: f64,
 }
 
 impl FolderSource {
@@ -32,19 +36,37 @@ impl FolderSource {
         current_list: Arc<Mutex<Vec<Media>>>,
         global_index: Arc<AtomicUsize>,
     ) -> Self {
+        match Self::try_new(config, filter_chain, current_list, global_index) {
+            Ok(source) => source,
+            Err(e) => {
+                error!("{e}");
+                exit(1);
+            }
+        }
+    }
+
+    /// Like `new`, but gives an error back when the storage has no playable files,
+    /// for callers which should not end the process.
+    pub fn try_new(
+        config: &PlayoutConfig,
+        filter_chain: Option<Arc<Mutex<Vec<String>>>>,
+        current_list: Arc<Mutex<Vec<Media>>>,
+        global_index: Arc<AtomicUsize>,
+    ) -> Result<Self, Error> {
         let mut media_list = vec![];
         let mut index: usize = 0;
 
         if !Path::new(&config.storage.path).is_dir() {
-            error!(
-                "Path not exists: <b><magenta>{}</></b>",
-                config.storage.path
-            );
-            exit(1);
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("Path not exists: {}", config.storage.path),
+            ));
         }
 
+        // hidden folders and files are thumbnails, trash and unfinished uploads or transcodes
         for entry in WalkDir::new(config.storage.path.clone())
             .into_iter()
+            .filter_entry(|e| !is_hidden(&config.storage.path, e.path()))
             .flat_map(|e| e.ok())
             .filter(|f| f.path().is_file())
         {
@@ -55,12 +77,10 @@ impl FolderSource {
         }
 
         if media_list.is_empty() {
-            error!(
-                "no playable files found under: <b><magenta>{}</></b>",
-                config.storage.path
-            );
-
-            exit(1);
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("no playable files found under: {}", config.storage.path),
+            ));
         }
 
         if config.storage.shuffle {
@@ -79,13 +99,50 @@ impl FolderSource {
 
         *current_list.lock().unwrap() = media_list;
 
-        Self {
+        Ok(Self {
             config: config.clone(),
             filter_chain,
             nodes: current_list,
             current_node: Media::new(0, "", false),
             index: global_index,
+            start_position: 0.0,
+        })
+    }
+
+    /// Restore file order and clip position from saved playout state.
+    ///
+    /// Files which are not exists anymore get removed, new files are added to the end.
+    pub fn resume(&mut self, state: &StatusData) {
+        let mut nodes = self.nodes.lock().unwrap();
+        let mut media_list = vec![];
+
+        for source in state.folder_list.iter() {
+            if let Some(pos) = nodes.iter().position(|m| &m.source == source) {
+                media_list.push(nodes.remove(pos));
+            }
+        }
+
+        media_list.append(&mut nodes);
+
+        for (index, item) in media_list.iter_mut().enumerate() {
+            item.index = Some(index);
+        }
+
+        if let Some(index) = media_list.iter().position(|m| m.source == state.source) {
+            info!(
+                "Resume <b><magenta>{}</></b> at <yellow>{:.2}</> seconds",
+                state.source, state.position
+            );
+
+            self.index.store(index, Ordering::SeqCst);
+            self.start_position = state.position;
+        } else if let Some(index) = state.index.filter(|i| *i < media_list.len()) {
+            info!("Resume folder list at index <yellow>{index}</>");
+
+            self.index.store(index, Ordering::SeqCst);
         }
+
+        *nodes = media_list;
     }
 
     fn shuffle(&mut self) {
@@ -119,9 +176,17 @@ impl Iterator for FolderSource {
             let i = self.index.load(Ordering::SeqCst);
             self.current_node = self.nodes.lock().unwrap()[i].clone();
             self.current_node.add_probe();
+
+            if self.start_position > 0.0 && self.start_position < self.current_node.out {
+                // continue clip at last saved position
+                self.current_node.seek = self.start_position;
+                self.current_node.cmd = Some(seek_and_length(&self.current_node));
+            }
+
             self.current_node
                 .add_filter(&self.config, &self.filter_chain);
-            self.current_node.begin = Some(get_sec());
+            self.current_node.begin = Some(get_sec() - self.current_node.seek);
+            self.start_position = 0.0;
 
             self.index.fetch_add(1, Ordering::SeqCst);
 
@@ -8,9 +8,8 @@
 /// Beside that it is really very basic, without any logic.
 use std::{
     fs::{create_dir_all, write},
-    io::Error,
+    io::{Error, ErrorKind},
     path::Path,
-    process::exit,
     sync::{atomic::AtomicUsize, Arc, Mutex},
 };
 
@@ -20,25 +19,20 @@ use simplelog::*;
 use super::folder::FolderSource;
 use crate::utils::{json_serializer::JsonPlaylist, time_to_sec, Media, PlayoutConfig};
 
+fn parse_date(date: &str) -> Result<NaiveDate, Error> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("date format error in: {date:?}"),
+        )
+    })
+}
+
 /// Generate a vector with dates, from given range.
-fn get_date_range(date_range: &[String]) -> Vec<String> {
+fn get_date_range(date_range: &[String]) -> Result<Vec<String>, Error> {
     let mut range = vec![];
-
-    let start = match NaiveDate::parse_from_str(&date_range[0], "%Y-%m-%d") {
-        Ok(s) => s,
-        Err(_) => {
-            error!("date format error in: <yellow>{:?}</>", date_range[0]);
-            exit(1);
-        }
-    };
-
-    let end = match NaiveDate::parse_from_str(&date_range[2], "%Y-%m-%d") {
-        Ok(e) => e,
-        Err(_) => {
-            error!("date format error in: <yellow>{:?}</>", date_range[2]);
-            exit(1);
-        }
-    };
+    let start = parse_date(&date_range[0])?;
+    let end = parse_date(&date_range[2])?;
 
     let duration = end.signed_duration_since(start);
     let days = duration.num_days() + 1;
@@ -47,13 +41,25 @@ fn get_date_range(date_range: &[String]) -> Vec<String> {
         range.push((start + Duration::days(day)).format("%Y-%m-%d").to_string());
     }
 
-    range
+    Ok(range)
 }
 
 /// Generate playlists
 pub fn generate_playlist(
     config: &PlayoutConfig,
     channel_name: Option<String>,
+) -> Result<Vec<JsonPlaylist>, Error> {
+    generate_with_progress(config, channel_name, &mut |_| true)
+}
+
+/// Generate playlists and report the progress from 0.0 to 1.0 after each date.
+///
+/// When `progress` returns false, the generation stops and the playlists
+/// which are written so far are returned.
+pub fn generate_with_progress(
+    config: &PlayoutConfig,
+    channel_name: Option<String>,
+    progress: &mut dyn FnMut(f64) -> bool,
 ) -> Result<Vec<JsonPlaylist>, Error> {
     let total_length = match config.playlist.length_sec {
         Some(length) => length,
@@ -77,12 +83,10 @@ pub fn generate_playlist(
     };
 
     if !playlist_root.is_dir() {
-        error!(
-            "Playlist folder <b><magenta>{}</></b> not exists!",
-            &config.playlist.path
-        );
-
-        exit(1);
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("Playlist folder {} not exists!", &config.playlist.path),
+        ));
     }
 
     if let Some(range) = config.general.generate.clone() {
@@ -90,13 +94,22 @@ pub fn generate_playlist(
     }
 
     if date_range.contains(&"-".to_string()) && date_range.len() == 3 {
-        date_range = get_date_range(&date_range)
+        date_range = get_date_range(&date_range)?
     }
 
-    let media_list = FolderSource::new(config, None, current_list, index);
+    let media_list = FolderSource::try_new(config, None, current_list, index)?;
     let list_length = media_list.nodes.lock().unwrap().len();
+    let dates = date_range.len();
+
+    for (num, date) in date_range.into_iter().enumerate() {
+        if !progress(num as f64 / dates as f64) {
+            info!("Playlist generation stopped");
+
+            return Ok(playlists);
+        }
+
+        parse_date(&date)?;
 
-    for date in date_range {
         let d: Vec<&str> = date.split('-').collect();
         let year = d[0];
         let month = d[1];
@@ -159,5 +172,7 @@ pub fn generate_playlist(
         write(playlist_file, &json)?;
     }
 
+    progress(1.0);
+
     Ok(playlists)
 }
@@ -1,21 +1,377 @@
-/// Import text/m3u file and create a playlist out of it
+/// Import text/m3u, csv or xspf file and create a playlist out of it
+///
+/// - text: one media path per line, lines with leading "#" are ignored
+/// - m3u: like text, but durations and titles from `#EXTINF` lines are used
+/// - csv: header line with column names, `source` is needed, other columns are optional:
+///   `in`, `out`, `duration`, `category`, `audio`, `custom_filter`, `title`
+/// - xspf: `location`, `title` and `duration` (in milliseconds) from every track
+///
+/// Lines with errors are skipped and reported, the rest gets imported.
+/// Clips without duration are probed.
 use std::{
-    //error::Error,
     fs::{create_dir_all, File},
-    io::{BufRead, BufReader, Error, ErrorKind},
+    io::{BufRead, BufReader, Error, ErrorKind, Read},
     path::Path,
 };
 
-use crate::utils::{json_reader, json_serializer::JsonPlaylist, json_writer, Media, PlayoutConfig};
+use chrono::NaiveDate;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::utils::{
+    json_reader, json_serializer::JsonPlaylist, json_writer, playlist_copy::playlist_path, Media,
+    PlayoutConfig,
+};
+
+static TRACK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<track>(.*?)</track>").unwrap());
+static LOCATION_REGEX: Lazy<Regex> = Lazy::new(|| xspf_tag("location"));
+static TITLE_REGEX: Lazy<Regex> = Lazy::new(|| xspf_tag("title"));
+static DURATION_REGEX: Lazy<Regex> = Lazy::new(|| xspf_tag("duration"));
+
+/// Error from one line, or from one track in xspf files.
+#[derive(Debug, Clone, Serialize)]
+pub struct LineError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub message: String,
+    pub items: usize,
+    pub errors: Vec<LineError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportFormat {
+    Text,
+    M3u,
+    Csv,
+    Xspf,
+}
+
+impl ImportFormat {
+    fn from_path(path: &str) -> Self {
+        match Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .as_deref()
+        {
+            Some("m3u") | Some("m3u8") => Self::M3u,
+            Some("csv") => Self::Csv,
+            Some("xspf") => Self::Xspf,
+            _ => Self::Text,
+        }
+    }
+}
+
+fn is_stream(source: &str) -> bool {
+    source.contains("://")
+}
+
+/// Create clip, the media file gets only probed when no duration is given.
+fn media_item(source: &str, duration: Option<f64>) -> Result<Media, String> {
+    match duration {
+        Some(d) => {
+            let mut media = Media::new(0, source, false);
+            media.duration = d;
+            media.out = d;
+
+            Ok(media)
+        }
+        None if is_stream(source) => Ok(Media::new(0, source, false)),
+        None if Path::new(source).is_file() => {
+            let media = Media::new(0, source, true);
+
+            if media.duration <= 0.0 {
+                return Err(format!("No duration found for: {source}"));
+            }
+
+            Ok(media)
+        }
+        None => Err(format!("File not exists: {source}")),
+    }
+}
+
+/// Read lines, invalid UTF-8 is reported for its line and does not stop the import.
+fn read_lines(path: &str, errors: &mut Vec<LineError>) -> Result<Vec<(usize, String)>, Error> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut lines = vec![];
+
+    for (i, line) in reader.split(b'\n').enumerate() {
+        match line {
+            Ok(bytes) => match String::from_utf8(bytes) {
+                Ok(l) => lines.push((i + 1, l.trim_end_matches('\r').to_string())),
+                Err(e) => errors.push(LineError {
+                    line: i + 1,
+                    message: e.to_string(),
+                }),
+            },
+            Err(e) => errors.push(LineError {
+                line: i + 1,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Parse text and m3u lines, `#EXTINF:<duration>,<title>` belongs to the next path.
+fn parse_m3u(lines: Vec<(usize, String)>, errors: &mut Vec<LineError>) -> Vec<Media> {
+    let mut program = vec![];
+    let mut extinf: Option<(f64, String)> = None;
+
+    for (number, line) in lines {
+        let line = line.trim();
+
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            let (duration, title) = info.split_once(',').unwrap_or((info, ""));
+            // attributes can follow the duration, like: #EXTINF:-1 tvg-id="..",Title
+            let duration = duration.split_whitespace().next().unwrap_or_default();
+
+            match duration.parse::<f64>() {
+                Ok(d) => extinf = Some((d, title.trim().to_string())),
+                Err(_) => errors.push(LineError {
+                    line: number,
+                    message: format!("Wrong duration in: {line}"),
+                }),
+            }
+
+            continue;
+        }
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (duration, title) = match extinf.take() {
+            Some((d, t)) if d > 0.0 => (Some(d), t),
+            Some((_, t)) => (None, t),
+            None => (None, String::new()),
+        };
+
+        match media_item(line, duration) {
+            Ok(mut media) => {
+                media.title = title;
+                program.push(media);
+            }
+            Err(message) => errors.push(LineError {
+                line: number,
+                message,
+            }),
+        }
+    }
+
+    program
+}
+
+/// Split csv line in fields, fields can be quoted with double quotes.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+
+    fields.push(field);
+
+    fields.iter().map(|f| f.trim().to_string()).collect()
+}
+
+fn csv_item(header: &[String], fields: &[String]) -> Result<Media, String> {
+    let value = |name: &str| -> Option<&str> {
+        header
+            .iter()
+            .position(|h| h == name)
+            .and_then(|i| fields.get(i))
+            .map(|f| f.as_str())
+            .filter(|f| !f.is_empty())
+    };
+    let number = |name: &str| -> Result<Option<f64>, String> {
+        value(name)
+            .map(|v| {
+                v.parse::<f64>()
+                    .map_err(|_| format!("Column '{name}' is not a number: {v}"))
+            })
+            .transpose()
+    };
+
+    let source = value("source").ok_or_else(|| "Source is empty".to_string())?;
+    let seek = number("in")?;
+    let out = number("out")?;
+    let duration = number("duration")?.or(out);
+    let mut media = media_item(source, duration)?;
+
+    media.seek = seek.unwrap_or_default();
+    media.out = out.unwrap_or(media.duration);
+    media.category = value("category").unwrap_or_default().to_string();
+    media.audio = value("audio").unwrap_or_default().to_string();
+    media.custom_filter = value("custom_filter").unwrap_or_default().to_string();
+    media.title = value("title").unwrap_or_default().to_string();
+
+    if media.out <= media.seek {
+        return Err(format!(
+            "Out point {} is not after in point {}",
+            media.out, media.seek
+        ));
+    }
+
+    if media.duration > 0.0 && media.out > media.duration {
+        return Err(format!(
+            "Out point {} is bigger then duration {}",
+            media.out, media.duration
+        ));
+    }
+
+    Ok(media)
+}
+
+fn parse_csv(lines: Vec<(usize, String)>, errors: &mut Vec<LineError>) -> Vec<Media> {
+    let mut program = vec![];
+    let mut lines = lines.into_iter().filter(|(_, l)| !l.trim().is_empty());
+
+    let header = match lines.next() {
+        Some((_, l)) => csv_fields(&l.to_lowercase()),
+        None => return program,
+    };
+
+    if !header.iter().any(|h| h == "source") {
+        errors.push(LineError {
+            line: 1,
+            message: "CSV header needs a 'source' column".to_string(),
+        });
+
+        return program;
+    }
+
+    for (number, line) in lines {
+        match csv_item(&header, &csv_fields(&line)) {
+            Ok(media) => program.push(media),
+            Err(message) => errors.push(LineError {
+                line: number,
+                message,
+            }),
+        }
+    }
+
+    program
+}
+
+fn decode_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Decode file uri from xspf location, like: `file:///media/my%20clip.mp4`
+fn decode_location(location: &str) -> String {
+    let path = location.strip_prefix("file://").unwrap_or(location);
+
+    if is_stream(path) {
+        return path.to_string();
+    }
+
+    let bytes = path.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(b) = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                decoded.push(b);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn xspf_tag(name: &str) -> Regex {
+    Regex::new(&format!(r"(?s)<{name}>(.*?)</{name}>")).unwrap()
+}
+
+/// Decoded text from the first tag in the track.
+fn tag(regex: &Regex, track: &str) -> Option<String> {
+    regex.captures(track).map(|c| decode_xml(c[1].trim()))
+}
+
+fn parse_xspf(content: &str, errors: &mut Vec<LineError>) -> Vec<Media> {
+    let mut program = vec![];
+
+    for track in TRACK_REGEX.captures_iter(content) {
+        let whole = track.get(0).unwrap();
+        let number = content[..whole.start()].lines().count().max(1);
+        let body = &track[1];
+
+        let location = match tag(&LOCATION_REGEX, body) {
+            Some(l) => decode_location(&l),
+            None => {
+                errors.push(LineError {
+                    line: number,
+                    message: "Track has no location".to_string(),
+                });
+                continue;
+            }
+        };
+
+        let duration = match tag(&DURATION_REGEX, body).map(|d| d.parse::<f64>()) {
+            Some(Ok(ms)) => Some(ms / 1000.0),
+            Some(Err(_)) => {
+                errors.push(LineError {
+                    line: number,
+                    message: "Track duration is not a number".to_string(),
+                });
+                continue;
+            }
+            None => None,
+        };
+
+        match media_item(&location, duration) {
+            Ok(mut media) => {
+                media.title = tag(&TITLE_REGEX, body).unwrap_or_default();
+                program.push(media);
+            }
+            Err(message) => errors.push(LineError {
+                line: number,
+                message,
+            }),
+        }
+    }
+
+    program
+}
 
 pub fn import_file(
     config: &PlayoutConfig,
     date: &str,
     channel_name: Option<String>,
     path: &str,
-) -> Result<String, Error> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
+) -> Result<ImportReport, Error> {
+    let mut report = ImportReport::default();
     let mut playlist = JsonPlaylist {
         channel: channel_name.unwrap_or_else(|| "Channel 1".to_string()),
         date: date.to_string(),
@@ -36,22 +392,49 @@ pub fn import_file(
         ));
     }
 
-    let d: Vec<&str> = date.split('-').collect();
-    let year = d[0];
-    let month = d[1];
-    let playlist_path = playlist_root.join(year).join(month);
-    let playlist_file = &playlist_path.join(format!("{date}.json"));
+    // the date is part of the playlist path
+    if NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|d| d.format("%Y-%m-%d").to_string() != date)
+        .unwrap_or(true)
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Wrong date format: {date}"),
+        ));
+    }
+
+    let playlist_file = &playlist_path(&config.playlist.path, date);
 
-    create_dir_all(playlist_path)?;
+    playlist.program = match ImportFormat::from_path(path) {
+        ImportFormat::Xspf => {
+            let mut bytes = vec![];
+            File::open(path)?.read_to_end(&mut bytes)?;
+            parse_xspf(&String::from_utf8_lossy(&bytes), &mut report.errors)
+        }
+        ImportFormat::Csv => {
+            let lines = read_lines(path, &mut report.errors)?;
+            parse_csv(lines, &mut report.errors)
+        }
+        ImportFormat::M3u | ImportFormat::Text => {
+            let lines = read_lines(path, &mut report.errors)?;
+            parse_m3u(lines, &mut report.errors)
+        }
+    };
 
-    for line in reader.lines() {
-        let line = line?;
+    report.items = playlist.program.len();
 
-        if !line.starts_with('#') {
-            let item = Media::new(0, &line, true);
+    if playlist.program.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "No valid items found, {} line(s) with errors!",
+                report.errors.len()
+            ),
+        ));
+    }
 
-            playlist.program.push(item);
-        }
+    if let Some(folder) = playlist_file.parent() {
+        create_dir_all(folder)?;
     }
 
     let mut file_exists = false;
@@ -61,18 +444,27 @@ pub fn import_file(
         let existing_data = json_reader(playlist_file)?;
 
         if playlist == existing_data {
-            return Ok(format!("Playlist from {date}, already exists!"));
+            report.message = format!("Playlist from {date}, already exists!");
+            return Ok(report);
         }
     };
 
-    let mut msg = format!("Write playlist from {date} success!");
+    report.message = format!("Write playlist from {date} success!");
 
     if file_exists {
-        msg = format!("Update playlist from {date} success!");
+        report.message = format!("Update playlist from {date} success!");
+    }
+
+    if !report.errors.is_empty() {
+        report.message = format!(
+            "{} {} line(s) skipped.",
+            report.message,
+            report.errors.len()
+        );
     }
 
     match json_writer(playlist_file, playlist) {
-        Ok(_) => Ok(msg),
+        Ok(_) => Ok(report),
         Err(e) => Err(Error::new(ErrorKind::Other, e)),
     }
 }
@@ -2,10 +2,10 @@ extern crate log;
 extern crate simplelog;
 
 use std::{
+    io::{self, Write},
     path::Path,
-    sync::{atomic::Ordering, Arc, Mutex},
-    thread::{self, sleep},
-    time::Duration,
+    sync::{Arc, Mutex},
+    thread,
 };
 
 use chrono::prelude::*;
@@ -19,13 +19,33 @@ use lettre::{
     Transport,
 };
 use log::{Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use simplelog::*;
 
-use crate::utils::{PlayoutConfig, ProcessControl};
+use crate::utils::{
+    alert::{alert_queue, AlertDispatcher, AlertLogger},
+    Media, PlayerControl, PlayoutConfig, ProcessControl,
+};
+
+// Log targets, which we don't want to have in our logs.
+pub const IGNORE_TARGETS: [&str; 4] = ["hyper", "sqlx", "reqwest", "rpc"];
+
+static COLOR_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x1b\[[0-9;]*[mGKF]").unwrap());
+
+static STYLE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"</>|</?(b|i|u|bold|italic|underline|dimmed|(on[ -])?(bright[ -])?(black|red|green|yellow|blue|magenta|cyan|white))>",
+    )
+    .unwrap()
+});
+
+// Process unit from the message prefix, like "[Decoder] ".
+static UNIT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[(\w+)\] ").unwrap());
 
 /// send log messages to mail recipient
-pub fn send_mail(cfg: &PlayoutConfig, msg: String) {
+pub fn send_mail(cfg: &PlayoutConfig, msg: String) -> Result<(), String> {
     let recip = cfg
         .mail
         .recipient
@@ -35,118 +55,160 @@ pub fn send_mail(cfg: &PlayoutConfig, msg: String) {
         .collect::<Vec<&str>>();
 
     let mut message = Message::builder()
-        .from(cfg.mail.sender_addr.parse().unwrap())
-        .subject(&cfg.mail.subject)
+        .from(
+            cfg.mail
+                .sender_addr
+                .parse()
+                .map_err(|e| format!("Sender: {e}"))?,
+        )
+        .subject(format!("{} - {}", cfg.mail.subject, cfg.general.channel))
         .header(header::ContentType::TEXT_PLAIN);
 
     for r in recip {
-        message = message.to(r.parse().unwrap());
+        message = message.to(r.parse().map_err(|e| format!("Recipient: {e}"))?);
     }
 
-    if let Ok(mail) = message.body(clean_string(&msg)) {
-        let credentials =
-            Credentials::new(cfg.mail.sender_addr.clone(), cfg.mail.sender_pass.clone());
+    let mail = message
+        .body(clean_string(&msg))
+        .map_err(|e| format!("Mail Message failed: {e}"))?;
+    let credentials = Credentials::new(cfg.mail.sender_addr.clone(), cfg.mail.sender_pass.clone());
 
-        let mut transporter = SmtpTransport::relay(cfg.mail.smtp_server.clone().as_str());
+    let mut transporter = SmtpTransport::relay(cfg.mail.smtp_server.clone().as_str());
 
-        if cfg.mail.starttls {
-            transporter = SmtpTransport::starttls_relay(cfg.mail.smtp_server.clone().as_str());
-        }
+    if cfg.mail.starttls {
+        transporter = SmtpTransport::starttls_relay(cfg.mail.smtp_server.clone().as_str());
+    }
 
-        let mailer = transporter.unwrap().credentials(credentials).build();
+    let mailer = transporter
+        .map_err(|e| e.to_string())?
+        .credentials(credentials)
+        .build();
 
-        // Send the mail
-        if let Err(e) = mailer.send(&mail) {
-            error!("Could not send mail: {:?}", e);
-        }
-    } else {
-        error!("Mail Message failed!");
-    }
+    // Send the mail
+    mailer
+        .send(&mail)
+        .map_err(|e| format!("Could not send mail: {e:?}"))?;
+
+    Ok(())
 }
 
-/// Basic Mail Queue
+/// Workaround to remove color information from log
 ///
-/// Check every give seconds for messages and send them.
-fn mail_queue(
-    cfg: PlayoutConfig,
-    proc_ctl: ProcessControl,
-    messages: Arc<Mutex<Vec<String>>>,
-    interval: u64,
-) {
-    while !(*proc_ctl.is_terminated).load(Ordering::SeqCst) {
-        let mut msg = messages.lock().unwrap();
-
-        if msg.len() > 0 {
-            send_mail(&cfg, msg.join("\n"));
-            msg.clear();
-        }
+/// ToDo: maybe in next version from simplelog this is not necessary anymore.
+pub fn clean_string(text: &str) -> String {
+    COLOR_REGEX.replace_all(text, "").to_string()
+}
 
-        drop(msg);
+/// Remove the paris style tags, like <b><magenta>, from log message.
+pub fn remove_style_tags(text: &str) -> String {
+    STYLE_REGEX.replace_all(text, "").to_string()
+}
 
-        sleep(Duration::from_secs(interval));
-    }
+/// One log line in JSON format.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsonLine {
+    pub timestamp: String,
+    pub level: String,
+    pub unit: Option<String>,
+    pub channel: String,
+    pub clip: Option<String>,
+    pub message: String,
 }
 
-/// Self made Mail Log struct, to extend simplelog.
-pub struct LogMailer {
+/// JSON Logger, writes every log record as a single JSON object per line.
+///
+/// The process unit is taken from the message prefix, like "[Decoder]",
+/// the current clip from the global player control.
+pub struct JsonLogger {
     level: LevelFilter,
-    pub config: Config,
-    messages: Arc<Mutex<Vec<String>>>,
-    last_messages: Arc<Mutex<Vec<String>>>,
+    channel: String,
+    local_time: bool,
+    current_media: Option<Arc<Mutex<Option<Media>>>>,
+    writer: Mutex<Box<dyn Write + Send>>,
 }
 
-impl LogMailer {
+impl JsonLogger {
     pub fn new(
         log_level: LevelFilter,
-        config: Config,
-        messages: Arc<Mutex<Vec<String>>>,
-    ) -> Box<LogMailer> {
-        Box::new(LogMailer {
+        config: &PlayoutConfig,
+        play_control: Option<PlayerControl>,
+        writer: Box<dyn Write + Send>,
+    ) -> Box<JsonLogger> {
+        Box::new(JsonLogger {
             level: log_level,
-            config,
-            messages,
-            last_messages: Arc::new(Mutex::new(vec![String::new()])),
+            channel: config.general.channel.clone(),
+            local_time: config.logging.local_time,
+            current_media: play_control.map(|p| p.current_media),
+            writer: Mutex::new(writer),
         })
     }
+
+    fn timestamp(&self) -> String {
+        if self.local_time {
+            Local::now().to_rfc3339_opts(SecondsFormat::Millis, false)
+        } else {
+            Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+        }
+    }
+
+    // Current media can be locked from the player, so we never block here.
+    fn current_clip(&self) -> Option<String> {
+        self.current_media
+            .as_ref()
+            .and_then(|m| m.try_lock().ok())
+            .and_then(|m| m.as_ref().map(|media| media.source.clone()))
+    }
 }
 
-impl Log for LogMailer {
+impl Log for JsonLogger {
     fn enabled(&self, metadata: &Metadata<'_>) -> bool {
         metadata.level() <= self.level
+            && !IGNORE_TARGETS
+                .iter()
+                .any(|t| metadata.target().starts_with(t))
     }
 
     fn log(&self, record: &Record<'_>) {
         if self.enabled(record.metadata()) {
-            let rec = record.args().to_string();
-            let mut last_msgs = self.last_messages.lock().unwrap();
-
-            // put message only to mail queue when it differs from last message
-            // this we do to prevent spamming the mail box
-            if !last_msgs.contains(&rec) {
-                if last_msgs.len() > 2 {
-                    last_msgs.clear()
-                }
-                last_msgs.push(rec.clone());
-                let local: DateTime<Local> = Local::now();
-                let time_stamp = local.format("[%Y-%m-%d %H:%M:%S%.3f]");
-                let level = record.level().to_string().to_uppercase();
-                let full_line = format!("{time_stamp} [{level: >5}] {rec}");
+            let mut message = clean_string(&remove_style_tags(&record.args().to_string()));
+            let mut unit = None;
 
-                self.messages.lock().unwrap().push(full_line);
+            if let Some(prefix) = UNIT_REGEX.captures(&message).and_then(|c| c.get(1)) {
+                unit = Some(prefix.as_str().to_string());
+                message = UNIT_REGEX.replace(&message, "").to_string();
+            }
+
+            let line = JsonLine {
+                timestamp: self.timestamp(),
+                level: record.level().to_string(),
+                unit,
+                channel: self.channel.clone(),
+                clip: self.current_clip(),
+                message,
+            };
+
+            if let Ok(json) = serde_json::to_string(&line) {
+                let mut writer = self.writer.lock().unwrap();
+
+                if let Err(e) = writeln!(writer, "{json}") {
+                    eprintln!("Unable to write JSON log line: {e}");
+                }
             }
         }
     }
 
-    fn flush(&self) {}
+    fn flush(&self) {
+        let _ = self.writer.lock().unwrap().flush();
+    }
 }
 
-impl SharedLogger for LogMailer {
+impl SharedLogger for JsonLogger {
     fn level(&self) -> LevelFilter {
         self.level
     }
 
     fn config(&self) -> Option<&Config> {
-        Some(&self.config)
+        None
     }
 
     fn as_log(self: Box<Self>) -> Box<dyn Log> {
@@ -154,26 +216,19 @@ impl SharedLogger for LogMailer {
     }
 }
 
-/// Workaround to remove color information from log
-///
-/// ToDo: maybe in next version from simplelog this is not necessary anymore.
-fn clean_string(text: &str) -> String {
-    let regex = Regex::new(r"\x1b\[[0-9;]*[mGKF]").unwrap();
-
-    regex.replace_all(text, "").to_string()
-}
-
 /// Initialize our logging, to have:
 ///
 /// - console logger
 /// - file logger
-/// - mail logger
+/// - alert logger
+///
+/// With `json_format` console and file logger write JSON lines instead of plain text.
 pub fn init_logging(
     config: &PlayoutConfig,
     proc_ctl: Option<ProcessControl>,
-    messages: Option<Arc<Mutex<Vec<String>>>>,
+    play_ctl: Option<PlayerControl>,
+    alerts: Option<AlertDispatcher>,
 ) -> Vec<Box<dyn SharedLogger>> {
-    let config_clone = config.clone();
     let app_config = config.logging.clone();
     let mut time_level = LevelFilter::Off;
     let mut app_logger: Vec<Box<dyn SharedLogger>> = vec![];
@@ -185,14 +240,14 @@ pub fn init_logging(
     let mut log_config = ConfigBuilder::new()
         .set_thread_level(LevelFilter::Off)
         .set_target_level(LevelFilter::Off)
-        .add_filter_ignore_str("hyper")
-        .add_filter_ignore_str("sqlx")
-        .add_filter_ignore_str("reqwest")
-        .add_filter_ignore_str("rpc")
         .set_level_padding(LevelPadding::Left)
         .set_time_level(time_level)
         .clone();
 
+    for target in IGNORE_TARGETS {
+        log_config.add_filter_ignore_str(target);
+    }
+
     if app_config.local_time {
         log_config = match log_config.set_time_offset_to_local() {
             Ok(local) => local.clone(),
@@ -233,7 +288,23 @@ pub fn init_logging(
             None,
         );
 
-        app_logger.push(WriteLogger::new(LevelFilter::Debug, file_config, log_file));
+        if app_config.json_format {
+            app_logger.push(JsonLogger::new(
+                LevelFilter::Debug,
+                config,
+                play_ctl,
+                Box::new(log_file),
+            ));
+        } else {
+            app_logger.push(WriteLogger::new(LevelFilter::Debug, file_config, log_file));
+        }
+    } else if app_config.json_format {
+        app_logger.push(JsonLogger::new(
+            LevelFilter::Debug,
+            config,
+            play_ctl,
+            Box::new(io::stdout()),
+        ));
     } else {
         let term_config = log_config
             .clone()
@@ -254,24 +325,15 @@ pub fn init_logging(
         ));
     }
 
-    // set mail logger only the recipient is set in config
-    if config.mail.recipient.contains('@') && config.mail.recipient.contains('.') {
-        let messages_clone = messages.clone().unwrap();
-        let interval = config.mail.interval;
-
-        thread::spawn(move || {
-            mail_queue(config_clone, proc_ctl.unwrap(), messages_clone, interval)
-        });
+    // set alert logger only when alert targets are defined
+    if let Some(dispatcher) = alerts.filter(|d| !d.is_empty()) {
+        let dispatcher_clone = dispatcher.clone();
 
-        let mail_config = log_config.build();
-
-        let filter = match config.mail.mail_level.to_lowercase().as_str() {
-            "info" => LevelFilter::Info,
-            "warning" => LevelFilter::Warn,
-            _ => LevelFilter::Error,
-        };
+        if let Some(proc_ctl) = proc_ctl {
+            thread::spawn(move || alert_queue(dispatcher_clone, proc_ctl));
+        }
 
-        app_logger.push(LogMailer::new(filter, mail_config, messages.unwrap()));
+        app_logger.push(AlertLogger::new(dispatcher));
     }
 
     app_logger
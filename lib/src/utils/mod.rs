@@ -1,6 +1,6 @@
 use std::{
     ffi::OsStr,
-    fs::{self, metadata, File},
+    fs::{metadata, File},
     io::{BufRead, BufReader, Error},
     net::TcpListener,
     path::{Path, PathBuf},
@@ -19,36 +19,48 @@ use rand::prelude::*;
 use regex::Regex;
 use reqwest::header;
 use serde::{de::Deserializer, Deserialize, Serialize};
-use serde_json::json;
 use simplelog::*;
 
+pub mod access;
+pub mod alert;
 pub mod config;
+pub mod conform;
 pub mod controller;
+pub mod epg;
+pub mod export;
 pub mod folder;
 mod generator;
 pub mod import;
 pub mod json_serializer;
 mod json_validate;
 mod logging;
+pub mod playlist_copy;
+pub mod playlist_edit;
+pub mod provision;
+pub mod restart;
+pub mod status;
+pub mod upload;
 
 #[cfg(windows)]
 mod windows;
 
+pub use alert::AlertDispatcher;
 pub use config::{
     self as playout_config,
     OutputMode::{self, *},
     PlayoutConfig,
     ProcessMode::{self, *},
-    DUMMY_LEN, FFMPEG_IGNORE_ERRORS, IMAGE_FORMAT,
+    ResumeMode, DUMMY_LEN, FFMPEG_IGNORE_ERRORS, IMAGE_FORMAT,
 };
 pub use controller::{
     PlayerControl, PlayoutStatus, ProcessControl,
     ProcessUnit::{self, *},
 };
-pub use generator::generate_playlist;
+pub use generator::{generate_playlist, generate_with_progress};
 pub use json_serializer::{read_json, JsonPlaylist};
 pub use json_validate::validate_playlist;
-pub use logging::{init_logging, send_mail};
+pub use logging::{init_logging, send_mail, JsonLine};
+pub use status::{read_status, state_saver, write_status, StatusData};
 
 use crate::{
     filter::{filter_chains, Filters},
@@ -77,6 +89,13 @@ pub struct Media {
     #[serde(deserialize_with = "null_string")]
     pub source: String,
 
+    #[serde(
+        default,
+        deserialize_with = "null_string",
+        skip_serializing_if = "is_empty_string"
+    )]
+    pub title: String,
+
     #[serde(
         default,
         deserialize_with = "null_string",
@@ -134,6 +153,7 @@ impl Media {
             duration,
             category: String::new(),
             source: src.to_string(),
+            title: String::new(),
             audio: String::new(),
             cmd: Some(vec_strings!["-i", src]),
             filter: None,
@@ -182,6 +202,7 @@ impl PartialEq for Media {
             && self.out == other.out
             && self.duration == other.duration
             && self.source == other.source
+            && self.title == other.title
             && self.category == other.category
             && self.audio == other.audio
             && self.custom_filter == other.custom_filter
@@ -297,24 +318,6 @@ pub fn get_filter_from_json(raw_text: String) -> String {
     filter
 }
 
-/// Write current status to status file in temp folder.
-///
-/// The status file is init in main function and mostly modified in RPC server.
-pub fn write_status(config: &PlayoutConfig, date: &str, shift: f64) {
-    let data = json!({
-        "time_shift": shift,
-        "date": date,
-    });
-
-    let status_data: String = serde_json::to_string(&data).expect("Serialize status data failed");
-    if let Err(e) = fs::write(&config.general.stat_file, &status_data) {
-        error!(
-            "Unable to write to status file <b><magenta>{}</></b>: {e}",
-            config.general.stat_file
-        )
-    };
-}
-
 // pub fn get_timestamp() -> i32 {
 //     let local: DateTime<Local> = time_now();
 
@@ -599,6 +602,14 @@ pub fn valid_source(source: &str) -> bool {
     Path::new(&source).is_file()
 }
 
+/// Path has a hidden folder or file inside of `root`, like `.thumbs` or a `.part` file.
+pub fn is_hidden(root: &str, path: &Path) -> bool {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+}
+
 /// Check if file can include or has to exclude.
 /// For example when a file is on given HLS output path, it should exclude.
 /// Or when the file extension is set under storage config it can be include.
@@ -0,0 +1,215 @@
+/// Copy, move and repeat playlists
+///
+/// The playlist from one date can be copied to a single date or to a date range,
+/// like: ffplayout --copy 2022-06-20 --target 2022-06-21 - 2022-06-26
+///
+/// Existing playlists are skipped, unless overwrite is set.
+/// The items can be shifted by an offset in seconds and the durations can be probed again,
+/// when the media files have changed.
+use std::{
+    fs::{create_dir_all, remove_file},
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use simplelog::*;
+
+use crate::utils::{json_reader, json_serializer::JsonPlaylist, json_writer, Media, PlayoutConfig};
+
+/// Upper limit for the number of target days.
+pub const MAX_COPY_DAYS: i64 = 366;
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct CopyOptions {
+    /// Replace existing playlists on target dates.
+    #[serde(default)]
+    pub overwrite: bool,
+    /// Shift items by seconds, positive values move them later.
+    #[serde(default)]
+    pub shift: f64,
+    /// Read the durations from the media files again.
+    #[serde(default)]
+    pub probe: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CopyReport {
+    pub written: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, Error> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("date format error in: {date}"),
+        )
+    })
+}
+
+/// Get all dates from a single date, or from a range like: `["2022-01-01", "-", "2022-01-10"]`.
+pub fn date_range(dates: &[String]) -> Result<Vec<String>, Error> {
+    let (start, end) = match dates {
+        [date] => (parse_date(date)?, parse_date(date)?),
+        [from, to] | [from, _, to] => (parse_date(from)?, parse_date(to)?),
+        _ => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Target needs a date or a date range!",
+            ))
+        }
+    };
+
+    let days = end.signed_duration_since(start).num_days() + 1;
+
+    if days < 1 || days > MAX_COPY_DAYS {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Date range must have between 1 and {MAX_COPY_DAYS} days!"),
+        ));
+    }
+
+    Ok((0..days)
+        .map(|d| (start + Duration::days(d)).format("%Y-%m-%d").to_string())
+        .collect())
+}
+
+/// Path from playlist in the YYYY/MM folder structure.
+pub fn playlist_path(root: &str, date: &str) -> PathBuf {
+    let d: Vec<&str> = date.split('-').collect();
+
+    Path::new(root)
+        .join(d[0])
+        .join(d.get(1).unwrap_or(&""))
+        .join(date)
+        .with_extension("json")
+}
+
+/// Shift items in time, what moves over the end comes to the beginning.
+///
+/// The item which crosses the cut gets split in two parts, so the total length stays the same.
+pub fn shift_program(program: &[Media], shift: f64) -> Vec<Media> {
+    let total: f64 = program.iter().map(|m| m.out - m.seek).sum();
+
+    if total <= 0.0 || shift == 0.0 {
+        return program.to_vec();
+    }
+
+    // position in the old program, where the new program begins
+    let cut = (total - shift).rem_euclid(total);
+    let mut position = 0.0;
+
+    for (i, item) in program.iter().enumerate() {
+        let length = item.out - item.seek;
+
+        if cut < position + length {
+            let mut head = program[..i].to_vec();
+            let mut shifted = vec![];
+            let split = cut - position;
+
+            if split > 0.0 {
+                let mut first = item.clone();
+                let mut second = item.clone();
+                first.out = item.seek + split;
+                second.seek = item.seek + split;
+
+                shifted.push(second);
+                head.push(first);
+            } else {
+                shifted.push(item.clone());
+            }
+
+            shifted.extend_from_slice(&program[i + 1..]);
+            shifted.append(&mut head);
+
+            return shifted;
+        }
+
+        position += length;
+    }
+
+    program.to_vec()
+}
+
+/// Read durations from the media files, missing files and streams keep their values.
+pub fn probe_program(program: &mut [Media]) {
+    for item in program.iter_mut() {
+        if !Path::new(&item.source).is_file() {
+            continue;
+        }
+
+        let duration = Media::new(0, &item.source, true).duration;
+
+        if duration <= 0.0 {
+            continue;
+        }
+
+        if item.out >= item.duration || item.out > duration {
+            item.out = duration;
+        }
+
+        if item.seek >= item.out {
+            item.seek = 0.0;
+        }
+
+        item.duration = duration;
+    }
+}
+
+/// Create the playlist for a target date, with shifted and probed items.
+pub fn copy_program(playlist: &JsonPlaylist, date: &str, options: &CopyOptions) -> JsonPlaylist {
+    let mut copy = playlist.clone();
+    copy.date = date.to_string();
+    copy.program = shift_program(&playlist.program, options.shift);
+
+    if options.probe {
+        probe_program(&mut copy.program);
+    }
+
+    copy
+}
+
+/// Copy playlist from source date to the target dates. With `remove_source` the source gets deleted,
+/// after it was written to all targets.
+pub fn copy_playlist(
+    config: &PlayoutConfig,
+    source: &str,
+    targets: &[String],
+    options: &CopyOptions,
+    remove_source: bool,
+) -> Result<CopyReport, Error> {
+    let source_path = playlist_path(&config.playlist.path, source);
+    let playlist = json_reader(&source_path)?;
+    let template = copy_program(&playlist, source, options);
+    let mut report = CopyReport::default();
+
+    for date in date_range(targets)? {
+        let path = playlist_path(&config.playlist.path, &date);
+
+        if date == source || (path.is_file() && !options.overwrite) {
+            report.skipped.push(date);
+            continue;
+        }
+
+        if let Some(p) = path.parent() {
+            create_dir_all(p)?;
+        }
+
+        let mut copy = template.clone();
+        copy.date = date.clone();
+        json_writer(&path, copy)?;
+
+        info!("Copy playlist from <b><magenta>{source}</></b> to <b><magenta>{date}</></b>");
+
+        report.written.push(date);
+    }
+
+    if remove_source && !report.written.is_empty() && !report.skipped.contains(&source.to_string())
+    {
+        remove_file(source_path)?;
+    }
+
+    Ok(report)
+}
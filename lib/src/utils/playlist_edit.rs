@@ -0,0 +1,175 @@
+/// Playlist Edits
+///
+/// Insert, move, replace, trim and delete single items from a playlist program,
+/// and the ETag from a playlist, to detect changes from other users.
+use sha2::{Digest, Sha256};
+
+use crate::utils::{json_serializer::JsonPlaylist, Media};
+
+#[derive(Debug)]
+pub enum PlaylistEdit {
+    Insert {
+        index: Option<usize>,
+        clip: Media,
+    },
+    Move {
+        index: usize,
+        to: usize,
+    },
+    Replace {
+        index: usize,
+        clip: Media,
+    },
+    Trim {
+        index: usize,
+        seek: Option<f64>,
+        out: Option<f64>,
+    },
+    Delete {
+        index: usize,
+    },
+}
+
+impl PlaylistEdit {
+    /// Short description for the audit trail.
+    pub fn summary(&self) -> String {
+        match self {
+            PlaylistEdit::Insert { index, clip } => match index {
+                Some(i) => format!("insert at {i}: {}", clip.source),
+                None => format!("append: {}", clip.source),
+            },
+            PlaylistEdit::Move { index, to } => format!("move {index} to {to}"),
+            PlaylistEdit::Replace { index, clip } => format!("replace {index}: {}", clip.source),
+            PlaylistEdit::Trim { index, seek, out } => {
+                format!("trim {index}: in {seek:?}, out {out:?}")
+            }
+            PlaylistEdit::Delete { index } => format!("delete {index}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EtagError {
+    /// If-Match header is needed, but missing.
+    Missing,
+    /// Playlist was changed in the meantime, or not exists anymore.
+    Changed,
+}
+
+/// ETag from the playlist content, it stays the same over program versions.
+pub fn playlist_etag(playlist: &JsonPlaylist) -> String {
+    let json = serde_json::to_string(playlist).unwrap_or_default();
+    let hash = Sha256::digest(json.as_bytes());
+
+    format!("\"{:x}\"", hash)
+}
+
+/// Compare the If-Match header with the ETag from the current playlist.
+///
+/// A missing header is only allowed when `required` is false.
+pub fn check_etag(
+    playlist: Option<&JsonPlaylist>,
+    if_match: Option<&str>,
+    required: bool,
+) -> Result<(), EtagError> {
+    let if_match = match if_match {
+        Some(i) => i,
+        None if required => return Err(EtagError::Missing),
+        None => return Ok(()),
+    };
+
+    let matches = match playlist {
+        Some(p) => {
+            let etag = playlist_etag(p);
+            if_match
+                .split(',')
+                .map(|t| t.trim().trim_start_matches("W/"))
+                .any(|t| t == "*" || t == etag)
+        }
+        None => false,
+    };
+
+    if !matches {
+        return Err(EtagError::Changed);
+    }
+
+    Ok(())
+}
+
+pub fn validate_clip(clip: &Media) -> Result<(), String> {
+    if clip.source.is_empty() {
+        return Err("Clip source is empty!".to_string());
+    }
+
+    if clip.seek < 0.0 || clip.out <= clip.seek {
+        return Err(format!("Wrong in/out points: {} - {}", clip.seek, clip.out));
+    }
+
+    if clip.duration > 0.0 && clip.out > clip.duration {
+        return Err(format!(
+            "Out point {} is bigger then duration {}",
+            clip.out, clip.duration
+        ));
+    }
+
+    Ok(())
+}
+
+fn check_index(program: &[Media], index: usize) -> Result<(), String> {
+    if index >= program.len() {
+        return Err(format!(
+            "Item {index} not exists, playlist has {} items!",
+            program.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Apply one edit to the program, on error the program stays unchanged.
+pub fn apply_edit(program: &mut Vec<Media>, edit: PlaylistEdit) -> Result<(), String> {
+    match edit {
+        PlaylistEdit::Insert { index, clip } => {
+            validate_clip(&clip)?;
+            let index = index.unwrap_or(program.len());
+
+            if index > program.len() {
+                return Err(format!("Insert position {index} is outside the playlist!"));
+            }
+
+            program.insert(index, clip);
+        }
+        PlaylistEdit::Move { index, to } => {
+            check_index(program, index)?;
+            check_index(program, to)?;
+            let clip = program.remove(index);
+            program.insert(to, clip);
+        }
+        PlaylistEdit::Replace { index, clip } => {
+            check_index(program, index)?;
+            validate_clip(&clip)?;
+            program[index] = clip;
+        }
+        PlaylistEdit::Trim { index, seek, out } => {
+            check_index(program, index)?;
+            let mut clip = program[index].clone();
+
+            if let Some(s) = seek {
+                clip.seek = s;
+            }
+
+            if let Some(o) = out {
+                clip.out = o;
+            }
+
+            validate_clip(&clip)?;
+            program[index] = clip;
+        }
+        PlaylistEdit::Delete { index } => {
+            check_index(program, index)?;
+            program.remove(index);
+        }
+    }
+
+    Ok(())
+}
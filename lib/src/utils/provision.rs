@@ -0,0 +1,158 @@
+/// Channel Provisioning
+///
+/// Config and folders for a new channel: the config comes from the template, with own ports for
+/// the RPC server, the ingest server and the text messages. Playlists and media go to
+/// `<template parent>/<channel>/`, HLS segments and logs to a `<channel>` subfolder of the
+/// template folders.
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use simplelog::*;
+
+use crate::utils::{OutputMode::HLS, PlayoutConfig};
+
+/// Channel name in lower case, for config file and service instance.
+pub fn channel_slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join("_")
+}
+
+/// Config file is inside of `config_dir`, without going up with `..`.
+pub fn in_config_dir(config_dir: &str, config_path: &str) -> bool {
+    Path::new(config_path)
+        .strip_prefix(config_dir)
+        .map_or(false, |rest| {
+            rest.components().count() > 0
+                && rest.components().all(|c| matches!(c, Component::Normal(_)))
+        })
+}
+
+pub fn socket_port(socket: &str) -> &str {
+    socket.rsplit(':').next().unwrap_or_default()
+}
+
+/// Host and port from the ingest url, like `127.0.0.1:1936`.
+pub fn ingest_socket(input_param: &str) -> Option<String> {
+    let url = input_param.split_whitespace().find(|p| p.contains("://"))?;
+    let host = url.split("://").nth(1)?.split('/').next()?;
+
+    host.contains(':').then(|| host.to_string())
+}
+
+/// Folder from the HLS playlist in the output parameters.
+pub fn hls_folder(config: &PlayoutConfig) -> Option<PathBuf> {
+    if config.out.mode != HLS {
+        return None;
+    }
+
+    config
+        .out
+        .output_param
+        .split_whitespace()
+        .rev()
+        .find(|p| p.ends_with(".m3u8"))
+        .and_then(|p| Path::new(p).parent().map(|d| d.to_path_buf()))
+}
+
+/// Config for the new channel from the template, `next_socket` gives a free socket on every call.
+pub fn channel_config<E>(
+    mut config: PlayoutConfig,
+    slug: &str,
+    next_socket: &mut dyn FnMut() -> Result<String, E>,
+) -> Result<PlayoutConfig, E> {
+    config.rpc_server.address = next_socket()?;
+
+    if let Some(socket) = ingest_socket(&config.ingest.input_param) {
+        let host = socket.rsplit_once(':').map(|(h, _)| h).unwrap_or_default();
+        let new_socket = format!("{host}:{}", socket_port(&next_socket()?));
+
+        config.ingest.input_param = config.ingest.input_param.replacen(&socket, &new_socket, 1);
+    }
+
+    config.text.zmq_stream_socket = Some(next_socket()?);
+    config.text.zmq_server_socket = Some(next_socket()?);
+
+    for path in [&mut config.playlist.path, &mut config.storage.path] {
+        let folder = Path::new(path.as_str());
+        let name = folder.file_name().unwrap_or_default();
+        let parent = folder.parent().unwrap_or(folder);
+
+        *path = parent.join(slug).join(name).to_string_lossy().to_string();
+    }
+
+    if let Some(folder) = hls_folder(&config) {
+        let old = format!("{}/", folder.display());
+        let new = format!("{}/", folder.join(slug).display());
+
+        config.out.output_param = config.out.output_param.replace(&old, &new);
+    }
+
+    config.logging.log_path = Path::new(&config.logging.log_path)
+        .join(slug)
+        .to_string_lossy()
+        .to_string();
+
+    Ok(config)
+}
+
+/// Folders which the channel needs: playlists, storage, HLS and logs.
+pub fn channel_folders(config: &PlayoutConfig) -> Vec<PathBuf> {
+    let mut folders = vec![
+        PathBuf::from(&config.playlist.path),
+        PathBuf::from(&config.storage.path),
+    ];
+
+    folders.extend(hls_folder(config));
+    folders.push(PathBuf::from(&config.logging.log_path));
+
+    folders
+}
+
+/// Paths to take back, when the channel gets deleted. Only what the provisioning has `created`
+/// is taken, and nothing which holds a folder from another channel. A file must be the config
+/// inside of `config_dir`.
+///
+/// Returns the `output` folders (HLS, logs), which go with their content, and the paths for
+/// `cleanup`, where playlists and media stay, when there are still files.
+pub fn deprovision_paths(
+    config_dir: &str,
+    created: &[PathBuf],
+    shared: &[PathBuf],
+    output: &[PathBuf],
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut outputs = vec![];
+    let mut paths = vec![];
+
+    for path in created {
+        if shared.iter().any(|s| s.starts_with(path)) {
+            continue;
+        }
+
+        if output.contains(path) && path.is_dir() {
+            outputs.push(path.clone());
+        } else if path.is_dir() || in_config_dir(config_dir, &path.to_string_lossy()) {
+            paths.push(path.clone());
+        }
+    }
+
+    (outputs, paths)
+}
+
+/// Remove files and folders in reverse order, folders only when they are empty.
+pub fn cleanup(paths: &[PathBuf]) {
+    for path in paths.iter().rev() {
+        let result = match path.is_dir() {
+            true => fs::remove_dir(path),
+            false => fs::remove_file(path),
+        };
+
+        if let Err(e) = result {
+            warn!("Keep <b><magenta>{}</></b>: {e}", path.display());
+        }
+    }
+}
@@ -0,0 +1,40 @@
+/// Restart Policy
+///
+/// When a process stops without a stop command, it gets started again. The wait before the
+/// restart doubles with every failure in a row, up to a maximum. A process which was running
+/// long enough counts as started, the next failure waits again only one second.
+use std::cmp::min;
+
+/// Longest wait between two restarts, when the process fails again and again.
+pub const MAX_BACKOFF: i64 = 60;
+
+/// Process which runs longer than this counts as started and resets the backoff.
+pub const STABLE_SECONDS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct RestartPolicy {
+    /// Restarts in a row, where the process stopped right after the start.
+    pub failures: u32,
+    /// Restarts since the last manual start.
+    pub restarts: u32,
+}
+
+impl RestartPolicy {
+    /// Process was started by hand, the backoff starts again.
+    pub fn reset(&mut self) {
+        self.failures = 0;
+    }
+
+    /// Process stopped after `runtime` seconds, returns the seconds to wait before the restart.
+    pub fn exited(&mut self, runtime: i64) -> i64 {
+        if runtime < STABLE_SECONDS {
+            self.failures += 1;
+        } else {
+            self.failures = 0;
+        }
+
+        self.restarts += 1;
+
+        min(2_i64.pow(min(self.failures, 6)), MAX_BACKOFF)
+    }
+}
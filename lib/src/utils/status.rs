@@ -0,0 +1,195 @@
+/// Playout State
+///
+/// The status file holds the time shift from skipping clips, and beside that the current
+/// playout position. With this we are able to resume after a crash or reboot,
+/// at the same clip and position where the playout has stopped.
+use std::{
+    fs::{self, File},
+    io::Error,
+    path::Path,
+    sync::{atomic::Ordering, Mutex},
+    thread::sleep,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use simplelog::*;
+
+use crate::utils::{
+    get_date, get_sec, time_now, PlayerControl, PlayoutConfig, PlayoutStatus, ProcessControl,
+    ProcessMode::*, ResumeMode,
+};
+
+/// Interval in seconds, in which the playout state is saved.
+pub const STATUS_INTERVAL: u64 = 2;
+
+/// When the playout was down longer than this, it starts at the current time and not resumes.
+pub const RESUME_MAX_GAP: i64 = 300;
+
+// Prevent parallel writes from RPC server and state saver.
+static STATUS_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct StatusData {
+    pub time_shift: f64,
+    pub date: String,
+
+    #[serde(default)]
+    pub source: String,
+    #[serde(default)]
+    pub index: Option<usize>,
+    #[serde(default)]
+    pub begin: Option<f64>,
+    #[serde(default)]
+    pub position: f64,
+    #[serde(default)]
+    pub folder_list: Vec<String>,
+    #[serde(default)]
+    pub timestamp: i64,
+}
+
+/// Read status file, when it is not readable we return the default values.
+pub fn read_status(stat_file: &str) -> StatusData {
+    if let Ok(file) = File::options().read(true).write(false).open(stat_file) {
+        match serde_json::from_reader(file) {
+            Ok(data) => return data,
+            Err(e) => error!("Status file <b><magenta>{stat_file}</></b> not readable: {e}"),
+        }
+    }
+
+    StatusData::default()
+}
+
+/// Write status data to a temporary file and move it then to the target,
+/// so a crash in the middle of writing can not leave a broken status file.
+fn write_atomic(stat_file: &str, data: &StatusData) -> Result<(), Error> {
+    let tmp_file = format!("{stat_file}.tmp");
+    let json = serde_json::to_string(data)?;
+
+    fs::write(&tmp_file, json)?;
+    fs::rename(tmp_file, stat_file)?;
+
+    Ok(())
+}
+
+/// Read the status file, let the given function modify it and save it back.
+pub fn update_status<F>(stat_file: &str, modify: F)
+where
+    F: FnOnce(&mut StatusData),
+{
+    let _lock = STATUS_LOCK.lock().unwrap();
+    let mut data = if Path::new(stat_file).is_file() {
+        read_status(stat_file)
+    } else {
+        StatusData::default()
+    };
+
+    modify(&mut data);
+
+    if let Err(e) = write_atomic(stat_file, &data) {
+        error!("Unable to write to status file <b><magenta>{stat_file}</></b>: {e}");
+    }
+}
+
+/// Write current time shift and date to status file.
+///
+/// The status file is init in main function and mostly modified in RPC server.
+pub fn write_status(config: &PlayoutConfig, date: &str, shift: f64) {
+    update_status(&config.general.stat_file, |data| {
+        data.time_shift = shift;
+        data.date = date.to_string();
+    });
+}
+
+/// Get the time in seconds the current clip is playing, counted from its begin.
+fn clip_position(config: &PlayoutConfig, begin: f64, time_shift: f64) -> f64 {
+    let mut time_sec = get_sec();
+
+    if config.processing.mode == Playlist {
+        if time_sec < config.playlist.start_sec.unwrap() {
+            time_sec += config.playlist.length_sec.unwrap()
+        }
+
+        time_sec += time_shift;
+    } else if time_sec < begin {
+        time_sec += 86400.0
+    }
+
+    time_sec - begin
+}
+
+/// Save current clip, index, position and in folder mode the file order.
+pub fn save_state(
+    config: &PlayoutConfig,
+    play_control: &PlayerControl,
+    playout_stat: &PlayoutStatus,
+) {
+    let media = match play_control.current_media.lock().unwrap().clone() {
+        Some(m) => m,
+        None => return,
+    };
+    let begin = media.begin.unwrap_or_else(get_sec);
+    let time_shift = *playout_stat.time_shift.lock().unwrap();
+    let position = clip_position(config, begin, time_shift);
+    let mut folder_list = vec![];
+
+    if config.processing.mode == Folder {
+        folder_list = play_control
+            .current_list
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|m| m.source.clone())
+            .collect();
+    }
+
+    update_status(&config.general.stat_file, |data| {
+        data.source = media.source;
+        data.index = media.index;
+        data.begin = Some(begin);
+        data.position = position;
+        data.folder_list = folder_list;
+        data.timestamp = time_now().timestamp();
+    });
+}
+
+/// Save playout state periodically, until playout is terminated.
+pub fn state_saver(
+    config: PlayoutConfig,
+    play_control: PlayerControl,
+    playout_stat: PlayoutStatus,
+    proc_control: ProcessControl,
+) {
+    while !proc_control.is_terminated.load(Ordering::SeqCst) {
+        sleep(Duration::from_secs(STATUS_INTERVAL));
+
+        save_state(&config, &play_control, &playout_stat);
+    }
+}
+
+/// Calculate time shift for resuming a playlist, at the position where it has stopped.
+///
+/// A state which is older then [`RESUME_MAX_GAP`], or from another playlist date, gets ignored.
+pub fn resume_shift(config: &PlayoutConfig, data: &StatusData) -> Option<f64> {
+    if config.general.resume_mode != ResumeMode::Resume || data.date.is_empty() {
+        return None;
+    }
+
+    let start_sec = config.playlist.start_sec.unwrap();
+    let gap = time_now().timestamp() - data.timestamp;
+
+    if !(0..=RESUME_MAX_GAP).contains(&gap) || data.date != get_date(true, start_sec, 0.0) {
+        info!("Saved playout state is outdated, start at current time");
+
+        return None;
+    }
+
+    let begin = data.begin?;
+    let mut time_sec = get_sec();
+
+    if time_sec < start_sec {
+        time_sec += config.playlist.length_sec.unwrap()
+    }
+
+    Some(begin + data.position - time_sec)
+}
@@ -0,0 +1,93 @@
+/// Upload Chunks
+///
+/// Session paths, offset and size checks and the checksum verification from resumable uploads.
+/// The API keeps the sessions and the requests, here is only the work on the files.
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+/// Hidden folder in storage for unfinished uploads.
+pub const UPLOAD_FOLDER: &str = ".uploads";
+
+/// Meta and data file from an upload session. The id is part of a path,
+/// so it must not contain anything else than the generated characters.
+pub fn session_paths(root: &str, id: &str) -> Result<(PathBuf, PathBuf), String> {
+    if id.len() != 20 || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err("Wrong upload id!".to_string());
+    }
+
+    let folder = Path::new(root).join(UPLOAD_FOLDER);
+
+    Ok((
+        folder.join(format!("{id}.json")),
+        folder.join(format!("{id}.part")),
+    ))
+}
+
+/// Checksum from the upload request, in lowercase hex.
+pub fn normalize_checksum(checksum: &str) -> Result<String, String> {
+    let checksum = checksum.trim().to_lowercase();
+
+    if checksum.len() != 64 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("Checksum must be a sha256 hex digest!".to_string());
+    }
+
+    Ok(checksum)
+}
+
+/// A chunk must continue exactly where the upload stands.
+pub fn check_offset(offset: u64, received: u64) -> Result<(), String> {
+    if offset != received {
+        return Err(format!("Offset mismatch, upload is at: {received}"));
+    }
+
+    Ok(())
+}
+
+/// Append chunk to the upload file and give the new size back.
+///
+/// A chunk which goes over the announced size is not written, the file gets cut back
+/// to `received`, so the client can continue from there.
+pub fn append_chunk(file: &mut File, received: u64, size: u64, chunk: &[u8]) -> io::Result<u64> {
+    if received + chunk.len() as u64 > size {
+        file.set_len(received)?;
+
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Chunk goes over the announced file size!",
+        ));
+    }
+
+    file.write_all(chunk)?;
+
+    Ok(received + chunk.len() as u64)
+}
+
+pub fn sha256(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Compare the complete upload with the expected checksum.
+/// On mismatch the session is useless, so its files are removed.
+pub fn verify_checksum(meta: &Path, part: &Path, expected: &str) -> Result<(), String> {
+    let checksum = sha256(part).map_err(|e| e.to_string())?;
+
+    if checksum != expected {
+        fs::remove_file(part).map_err(|e| e.to_string())?;
+        fs::remove_file(meta).map_err(|e| e.to_string())?;
+
+        return Err(format!(
+            "Checksum mismatch, got: {checksum}, upload must start again!"
+        ));
+    }
+
+    Ok(())
+}
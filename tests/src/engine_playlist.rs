@@ -29,7 +29,7 @@ fn playlist_change_at_midnight() {
     let proc_control = ProcessControl::new();
     let proc_ctl = proc_control.clone();
 
-    let logging = init_logging(&config, None, None);
+    let logging = init_logging(&config, None, None, None);
     CombinedLogger::init(logging).unwrap();
 
     mock_time::set_mock_time("2022-05-09T23:59:45");
@@ -54,7 +54,7 @@ fn playlist_change_at_six() {
     let proc_control = ProcessControl::new();
     let proc_ctl = proc_control.clone();
 
-    let logging = init_logging(&config, None, None);
+    let logging = init_logging(&config, None, None, None);
     CombinedLogger::init(logging).unwrap();
 
     mock_time::set_mock_time("2022-05-09T05:59:45");
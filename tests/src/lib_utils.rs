@@ -50,3 +50,882 @@ fn test_delta() {
 
     assert!(delta < 2.0);
 }
+
+#[test]
+fn status_keep_playout_state() {
+    let mut config = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    config.general.stat_file = std::env::temp_dir()
+        .join("ffp_status_test")
+        .display()
+        .to_string();
+
+    status::update_status(&config.general.stat_file, |data| {
+        data.source = "./assets/with_audio.mp4".into();
+        data.position = 12.5;
+    });
+    write_status(&config, "2022-05-20", 3.0);
+
+    let data = read_status(&config.general.stat_file);
+
+    assert_eq!(data.date, "2022-05-20".to_string());
+    assert_eq!(data.time_shift, 3.0);
+    assert_eq!(data.source, "./assets/with_audio.mp4".to_string());
+    assert_eq!(data.position, 12.5);
+}
+
+#[test]
+fn status_resume_only_fresh_state() {
+    let mut config = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    config.general.resume_mode = ResumeMode::Resume;
+    config.playlist.start_sec = Some(0.0);
+    config.playlist.length_sec = Some(86400.0);
+
+    mock_time::set_mock_time("2022-05-20T12:00:00");
+
+    let mut data = StatusData {
+        date: "2022-05-20".into(),
+        begin: Some(43000.0),
+        position: 10.0,
+        timestamp: time_now().timestamp() - 10,
+        ..Default::default()
+    };
+
+    let shift = status::resume_shift(&config, &data).unwrap();
+    assert!((shift + 190.0).abs() < 1.0);
+
+    data.timestamp -= 3 * 3600;
+    assert!(status::resume_shift(&config, &data).is_none());
+
+    data.timestamp = time_now().timestamp();
+    data.date = "2022-05-19".into();
+    assert!(status::resume_shift(&config, &data).is_none());
+}
+
+#[test]
+fn copy_date_range() {
+    let range = playlist_copy::date_range(&[
+        "2022-02-27".to_string(),
+        "-".to_string(),
+        "2022-03-02".to_string(),
+    ])
+    .unwrap();
+
+    assert_eq!(
+        range,
+        vec!["2022-02-27", "2022-02-28", "2022-03-01", "2022-03-02"]
+    );
+    assert!(
+        playlist_copy::date_range(&["2022-03-02".to_string(), "2022-03-01".to_string()]).is_err()
+    );
+}
+
+#[test]
+fn copy_shift_program() {
+    let mut program = vec![];
+
+    for src in ["a.mp4", "b.mp4", "c.mp4"] {
+        let mut media = Media::new(0, src, false);
+        media.out = 10.0;
+        media.duration = 10.0;
+        program.push(media);
+    }
+
+    let shifted = playlist_copy::shift_program(&program, 15.0);
+    let sources: Vec<&str> = shifted.iter().map(|m| m.source.as_str()).collect();
+    let total: f64 = shifted.iter().map(|m| m.out - m.seek).sum();
+
+    assert_eq!(sources, vec!["b.mp4", "c.mp4", "a.mp4", "b.mp4"]);
+    assert_eq!((shifted[0].seek, shifted[0].out), (5.0, 10.0));
+    assert_eq!((shifted[3].seek, shifted[3].out), (0.0, 5.0));
+    assert_eq!(total, 30.0);
+}
+
+#[test]
+fn import_csv_with_line_errors() {
+    let mut config = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    let root = std::env::temp_dir().join("ffplayout_import_test");
+    let csv = root.join("list.csv");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(
+        &csv,
+        "source,in,out,title\n\
+        http://example.org/live.m3u8,0,120,\"Live, News\"\n\
+        /not/existing/clip.mp4,,,\n\
+        http://example.org/show.m3u8,10,abc,\n",
+    )
+    .unwrap();
+    config.playlist.path = root.to_string_lossy().to_string();
+
+    let report = import::import_file(&config, "2022-06-20", None, &csv.to_string_lossy()).unwrap();
+    let playlist = json_reader(&root.join("2022/06/2022-06-20.json")).unwrap();
+
+    assert_eq!(report.items, 1);
+    assert_eq!(
+        report.errors.iter().map(|e| e.line).collect::<Vec<usize>>(),
+        vec![3, 4]
+    );
+    assert_eq!(playlist.program[0].title, "Live, News".to_string());
+    assert_eq!(playlist.program[0].out, 120.0);
+}
+
+#[test]
+fn import_out_over_duration() {
+    let mut config = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    let root = std::env::temp_dir().join("ffplayout_import_out_test");
+    let csv = root.join("list.csv");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(
+        &csv,
+        "source,in,out,duration\n\
+        http://example.org/a.m3u8,0,120,100\n\
+        http://example.org/b.m3u8,0,90,100\n",
+    )
+    .unwrap();
+    config.playlist.path = root.to_string_lossy().to_string();
+
+    let report = import::import_file(&config, "2022-06-20", None, &csv.to_string_lossy()).unwrap();
+
+    assert_eq!(report.items, 1);
+    assert_eq!(report.errors[0].line, 2);
+    assert!(report.errors[0].message.contains("bigger then duration"));
+}
+
+#[test]
+fn import_wrong_date() {
+    let mut config = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    let root = std::env::temp_dir().join("ffplayout_import_date_test");
+    let m3u = root.join("list.m3u");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(&m3u, "#EXTINF:60,News\nhttp://example.org/a.m3u8\n").unwrap();
+    config.playlist.path = root.to_string_lossy().to_string();
+
+    for date in [
+        "2022",
+        "2022-06-../../..",
+        "2022-6-20",
+        "2022-06-20/../x",
+        "",
+    ] {
+        let err = import::import_file(&config, date, None, &m3u.to_string_lossy()).unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    assert!(import::import_file(&config, "2022-06-20", None, &m3u.to_string_lossy()).is_ok());
+}
+
+#[test]
+fn import_xspf_tracks() {
+    let mut config = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    let root = std::env::temp_dir().join("ffplayout_import_xspf_test");
+    let xspf = root.join("list.xspf");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(
+        &xspf,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <trackList>
+    <track>
+      <location>http://example.org/live%20news.m3u8</location>
+      <title>News &amp; Weather</title>
+      <duration>60000</duration>
+    </track>
+    <track>
+      <title>No location</title>
+    </track>
+    <track>
+      <location>http://example.org/show.m3u8</location>
+      <duration>abc</duration>
+    </track>
+  </trackList>
+</playlist>
+"#,
+    )
+    .unwrap();
+    config.playlist.path = root.to_string_lossy().to_string();
+
+    let report = import::import_file(&config, "2022-06-21", None, &xspf.to_string_lossy()).unwrap();
+    let playlist = json_reader(&root.join("2022/06/2022-06-21.json")).unwrap();
+
+    assert_eq!(report.items, 1);
+    assert_eq!(
+        report.errors.iter().map(|e| e.line).collect::<Vec<usize>>(),
+        vec![9, 12]
+    );
+    assert_eq!(playlist.program[0].title, "News & Weather".to_string());
+    assert_eq!(playlist.program[0].out, 60.0);
+}
+
+#[test]
+fn conform_loudness_and_target() {
+    let mut config = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    let log = "[Parsed_loudnorm_0 @ 0x5580] \n{\n\t\"input_i\" : \"-27.61\",\n\t\"input_tp\" : \"-4.47\",\n\
+        \t\"input_lra\" : \"18.06\",\n\t\"input_thresh\" : \"-39.20\",\n\t\"output_i\" : \"-16.58\",\n\
+        \t\"target_offset\" : \"0.58\"\n}\n";
+    let loudness = conform::loudness_from_log(log).unwrap();
+
+    assert_eq!(loudness.input_i, "-27.61".to_string());
+    assert_eq!(loudness.target_offset, "0.58".to_string());
+    assert!(conform::loudness_from_log("no values").is_none());
+
+    let source = std::path::Path::new("/not/existing/show.mp4");
+    config.conform.extension = "mp4".to_string();
+    config.conform.keep_original = true;
+
+    assert_eq!(
+        conform::conform_target(&config, source),
+        std::path::PathBuf::from("/not/existing/show_conform.mp4")
+    );
+
+    config.conform.keep_original = false;
+
+    assert_eq!(
+        conform::conform_target(&config, source),
+        source.to_path_buf()
+    );
+
+    // an unrelated file with the target name is never overwritten
+    let root = std::env::temp_dir().join("ffplayout_conform_target_test");
+    let master = root.join("show.mov");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(&master, "").unwrap();
+    std::fs::write(root.join("show.mp4"), "").unwrap();
+    let _ = std::fs::remove_file(root.join("show_conform.mp4"));
+
+    assert_eq!(
+        conform::conform_target(&config, &master),
+        root.join("show_conform.mp4")
+    );
+
+    std::fs::write(root.join("show_conform.mp4"), "").unwrap();
+
+    assert_eq!(
+        conform::conform_target(&config, &master),
+        root.join("show_conform_2.mp4")
+    );
+}
+
+#[test]
+fn generate_without_exit() {
+    let mut config = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    let root = std::env::temp_dir().join("ffplayout_generate_test");
+    let storage = root.join("storage");
+    std::fs::create_dir_all(&storage).unwrap();
+    std::fs::write(storage.join("clip.mp4"), "").unwrap();
+    config.playlist.path = root.join("missing").to_string_lossy().to_string();
+    config.storage.path = storage.to_string_lossy().to_string();
+    config.general.generate = Some(vec!["2022-06-20".to_string()]);
+
+    assert!(generate_playlist(&config, None).is_err());
+
+    config.playlist.path = root.to_string_lossy().to_string();
+    config.general.generate = Some(vec![
+        "2022-06-20".to_string(),
+        "-".to_string(),
+        "2022-06-2x".to_string(),
+    ]);
+
+    assert!(generate_playlist(&config, None).is_err());
+
+    config.general.generate = Some(vec!["2022-06-20".to_string()]);
+    let mut steps = vec![];
+    let playlists = generate_with_progress(&config, None, &mut |p| {
+        steps.push(p);
+        false
+    })
+    .unwrap();
+
+    assert!(playlists.is_empty());
+    assert_eq!(steps, vec![0.0]);
+}
+
+#[test]
+fn fixed_zmq_sockets_from_config() {
+    let config = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    let stream_socket = config.text.zmq_stream_socket.clone().unwrap();
+
+    let path = std::env::temp_dir().join("ffplayout_zmq_test.yml");
+    std::fs::write(&path, serde_yaml::to_string(&config).unwrap()).unwrap();
+    let fixed = PlayoutConfig::new(Some(path.to_string_lossy().to_string()));
+
+    assert_eq!(fixed.text.zmq_stream_socket, Some(stream_socket));
+    assert_eq!(fixed.text.zmq_server_socket, config.text.zmq_server_socket);
+}
+
+#[test]
+fn hidden_storage_paths() {
+    use std::path::Path;
+
+    let root = "/tv-media";
+
+    assert!(is_hidden(root, Path::new("/tv-media/.trash/clip.mp4")));
+    assert!(is_hidden(
+        root,
+        Path::new("/tv-media/folder/.clip.mp4.part")
+    ));
+    assert!(!is_hidden(root, Path::new("/tv-media/folder/clip.mp4")));
+    assert!(!is_hidden(
+        "/home/user/.media",
+        Path::new("/home/user/.media/clip.mp4")
+    ));
+}
+
+#[test]
+fn folder_source_skips_trash() {
+    use std::sync::{atomic::AtomicUsize, Arc, Mutex};
+
+    let mut config = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    let storage = std::env::temp_dir().join("ffplayout_trash_test");
+    std::fs::create_dir_all(storage.join(".trash")).unwrap();
+    std::fs::write(storage.join("clip.mp4"), "").unwrap();
+    std::fs::write(storage.join(".trash").join("deleted.mp4"), "").unwrap();
+    config.storage.path = storage.to_string_lossy().to_string();
+    config.storage.shuffle = false;
+
+    let nodes = Arc::new(Mutex::new(vec![]));
+    folder::FolderSource::try_new(&config, None, nodes.clone(), Arc::new(AtomicUsize::new(0)))
+        .unwrap();
+    let sources: Vec<String> = nodes
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|m| m.source.clone())
+        .collect();
+
+    assert_eq!(
+        sources,
+        vec![storage.join("clip.mp4").to_string_lossy().to_string()]
+    );
+}
+
+#[test]
+fn restart_backoff() {
+    use restart::{RestartPolicy, MAX_BACKOFF, STABLE_SECONDS};
+
+    let mut policy = RestartPolicy::default();
+    let waits: Vec<i64> = (0..8).map(|_| policy.exited(1)).collect();
+
+    assert_eq!(
+        waits,
+        vec![2, 4, 8, 16, 32, MAX_BACKOFF, MAX_BACKOFF, MAX_BACKOFF]
+    );
+    assert_eq!(policy.restarts, 8);
+
+    // a process which was running long enough starts the backoff again
+    assert_eq!(policy.exited(STABLE_SECONDS), 1);
+    assert_eq!(policy.failures, 0);
+    assert_eq!(policy.exited(1), 2);
+
+    policy.reset();
+
+    assert_eq!(policy.exited(1), 2);
+    assert_eq!(policy.restarts, 11);
+}
+
+#[test]
+fn provision_channel_config() {
+    use provision::*;
+
+    assert_eq!(channel_slug("News 24 / HD"), "news_24_hd".to_string());
+    assert_eq!(channel_slug("--"), String::new());
+
+    assert!(in_config_dir("/etc/ffplayout", "/etc/ffplayout/news.yml"));
+    assert!(!in_config_dir("/etc/ffplayout", "/etc/ffplayout/../passwd"));
+    assert!(!in_config_dir(
+        "/etc/ffplayout",
+        "/etc/ffplayout_other/news.yml"
+    ));
+    assert!(!in_config_dir("/etc/ffplayout", "/etc/ffplayout"));
+
+    let template = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    let mut port = 4000;
+    let config = channel_config::<()>(template, "news", &mut || {
+        port += 1;
+        Ok(format!("127.0.0.1:{port}"))
+    })
+    .unwrap();
+
+    assert_eq!(config.rpc_server.address, "127.0.0.1:4001".to_string());
+    assert!(config
+        .ingest
+        .input_param
+        .contains("rtmp://127.0.0.1:4002/live/stream"));
+    assert_eq!(
+        config.text.zmq_stream_socket,
+        Some("127.0.0.1:4003".to_string())
+    );
+    assert_eq!(
+        config.text.zmq_server_socket,
+        Some("127.0.0.1:4004".to_string())
+    );
+    assert_eq!(config.playlist.path, "/var/lib/ffplayout/news/playlists");
+    assert_eq!(config.storage.path, "/var/lib/ffplayout/news/tv-media");
+    assert_eq!(config.logging.log_path, "/var/log/ffplayout/news");
+    assert!(config
+        .out
+        .output_param
+        .contains("/usr/share/ffplayout/public/live/news/stream-%d.ts"));
+    assert_eq!(
+        hls_folder(&config),
+        Some(std::path::PathBuf::from(
+            "/usr/share/ffplayout/public/live/news"
+        ))
+    );
+}
+
+#[test]
+fn provision_cleanup() {
+    let root = std::env::temp_dir().join("ffplayout_cleanup_test");
+    let empty = root.join("empty");
+    let full = root.join("full");
+    let config = root.join("news.yml");
+    std::fs::create_dir_all(&empty).unwrap();
+    std::fs::create_dir_all(&full).unwrap();
+    std::fs::write(full.join("clip.mp4"), "").unwrap();
+    std::fs::write(&config, "").unwrap();
+
+    provision::cleanup(&[config.clone(), empty.clone(), full.clone()]);
+
+    assert!(!config.exists());
+    assert!(!empty.exists());
+    // folders with content stay
+    assert!(full.join("clip.mp4").is_file());
+}
+
+#[test]
+fn provision_delete_only_created() {
+    let root = std::env::temp_dir().join("ffplayout_deprovision_test");
+    let config_dir = root.join("etc");
+    let config = config_dir.join("news.yml");
+    let logs = root.join("log");
+    let news_logs = logs.join("news");
+    let media = root.join("news").join("media");
+    let shared = root.join("shared");
+    let other = root.join("other.yml");
+    let _ = std::fs::remove_dir_all(&root);
+
+    for folder in [&config_dir, &news_logs, &media, &shared] {
+        std::fs::create_dir_all(folder).unwrap();
+    }
+
+    std::fs::write(&config, "").unwrap();
+    std::fs::write(&other, "").unwrap();
+    let created = vec![
+        config.clone(),
+        other.clone(),
+        root.join("news"),
+        media.clone(),
+        news_logs.clone(),
+        shared.clone(),
+    ];
+
+    let (outputs, paths) = provision::deprovision_paths(
+        &config_dir.to_string_lossy(),
+        &created,
+        &[shared.join("media")],
+        &[news_logs.clone()],
+    );
+
+    assert_eq!(outputs, vec![news_logs]);
+    // no file outside of the config folder and no folder from another channel
+    assert_eq!(paths, vec![config, root.join("news"), media]);
+
+    // the shared log folder is not created by the provisioning, so it stays
+    let (outputs, paths) =
+        provision::deprovision_paths(&config_dir.to_string_lossy(), &[], &[], &[logs]);
+
+    assert!(outputs.is_empty());
+    assert!(paths.is_empty());
+}
+
+#[test]
+fn channel_role_order() {
+    use access::ChannelRole;
+
+    assert!(ChannelRole::Viewer < ChannelRole::Editor);
+    assert!(ChannelRole::Editor < ChannelRole::Operator);
+    assert!(ChannelRole::Operator < ChannelRole::Admin);
+
+    assert!(ChannelRole::allows("admin", ChannelRole::Viewer));
+    assert!(ChannelRole::allows("operator", ChannelRole::Operator));
+    assert!(!ChannelRole::allows("editor", ChannelRole::Operator));
+    assert!(!ChannelRole::allows("viewer", ChannelRole::Editor));
+    assert!(!ChannelRole::allows("Admin", ChannelRole::Viewer));
+    assert!(!ChannelRole::allows("", ChannelRole::Viewer));
+}
+
+#[test]
+fn config_redact_secrets() {
+    let mut config = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    config.rpc_server.authorization = "secret_token".into();
+    config.mail.sender_pass = "secret_pass".into();
+    config.alert.targets = serde_yaml::from_str(
+        "[{type: webhook, target: 'https://hook/secret'}, {type: mail, target: 'a@b.c'}]",
+    )
+    .unwrap();
+
+    access::redact_secrets(&mut config);
+
+    assert!(config.rpc_server.authorization.is_empty());
+    assert!(config.mail.sender_pass.is_empty());
+    assert!(config.alert.targets[0].target.is_empty());
+    assert_eq!(config.alert.targets[1].target, "a@b.c");
+}
+
+#[test]
+fn token_revocation() {
+    use access::{is_revoked, TokenState};
+
+    let stored = TokenState {
+        stored: true,
+        valid_after: 100,
+        ..Default::default()
+    };
+
+    assert!(!is_revoked("access", 150, &TokenState::default()));
+    assert!(is_revoked(
+        "access",
+        150,
+        &TokenState {
+            revoked: true,
+            ..Default::default()
+        }
+    ));
+    assert!(!is_revoked("refresh", 150, &stored));
+    assert!(!is_revoked("api", 100, &stored));
+    assert!(is_revoked("api", 99, &stored));
+    assert!(is_revoked("refresh", 150, &TokenState::default()));
+    assert!(is_revoked("access", 50, &stored));
+}
+
+#[test]
+fn token_read_scope() {
+    use access::scope_allows;
+
+    assert!(scope_allows(None, "DELETE", "/api/playlist/1/2022-05-20"));
+    assert!(scope_allows(Some("write"), "POST", "/api/file/1/browse/"));
+    assert!(scope_allows(Some("read"), "GET", "/api/playlist/1"));
+    assert!(!scope_allows(Some("read"), "POST", "/api/file/1/browse/"));
+    assert!(!scope_allows(Some("read"), "GET", "/api/file/1/thumb"));
+    assert!(!scope_allows(
+        Some("read"),
+        "POST",
+        "/api/playlist/1/generate/2022-05-20"
+    ));
+}
+
+#[cfg(test)]
+fn edit_program() -> Vec<Media> {
+    ["a.mp4", "b.mp4", "c.mp4"]
+        .iter()
+        .map(|src| {
+            let mut media = Media::new(0, src, false);
+            media.out = 10.0;
+            media.duration = 10.0;
+            media
+        })
+        .collect()
+}
+
+#[test]
+fn playlist_edit_move_and_bounds() {
+    use playlist_edit::{apply_edit, PlaylistEdit};
+
+    let mut program = edit_program();
+
+    apply_edit(&mut program, PlaylistEdit::Move { index: 0, to: 2 }).unwrap();
+    let sources: Vec<&str> = program.iter().map(|m| m.source.as_str()).collect();
+    assert_eq!(sources, vec!["b.mp4", "c.mp4", "a.mp4"]);
+
+    apply_edit(&mut program, PlaylistEdit::Move { index: 2, to: 0 }).unwrap();
+    assert_eq!(program, edit_program());
+
+    assert!(apply_edit(&mut program, PlaylistEdit::Move { index: 3, to: 0 }).is_err());
+    assert!(apply_edit(&mut program, PlaylistEdit::Move { index: 0, to: 3 }).is_err());
+    assert!(apply_edit(&mut program, PlaylistEdit::Delete { index: 3 }).is_err());
+    assert!(apply_edit(
+        &mut program,
+        PlaylistEdit::Insert {
+            index: Some(4),
+            clip: edit_program()[0].clone(),
+        }
+    )
+    .is_err());
+    assert_eq!(program, edit_program());
+
+    apply_edit(
+        &mut program,
+        PlaylistEdit::Insert {
+            index: None,
+            clip: edit_program()[0].clone(),
+        },
+    )
+    .unwrap();
+    apply_edit(&mut program, PlaylistEdit::Delete { index: 0 }).unwrap();
+    assert_eq!(program.len(), 3);
+    assert_eq!(program[2].source, "a.mp4");
+}
+
+#[test]
+fn playlist_edit_trim() {
+    use playlist_edit::{apply_edit, PlaylistEdit};
+
+    let mut program = edit_program();
+    let trim = |seek, out| PlaylistEdit::Trim {
+        index: 1,
+        seek,
+        out,
+    };
+
+    apply_edit(&mut program, trim(Some(2.0), Some(8.0))).unwrap();
+    assert_eq!((program[1].seek, program[1].out), (2.0, 8.0));
+
+    apply_edit(&mut program, trim(None, Some(9.0))).unwrap();
+    assert_eq!((program[1].seek, program[1].out), (2.0, 9.0));
+
+    // out over duration, in after out and negative in are rejected and change nothing
+    assert!(apply_edit(&mut program, trim(None, Some(11.0))).is_err());
+    assert!(apply_edit(&mut program, trim(Some(9.5), None)).is_err());
+    assert!(apply_edit(&mut program, trim(Some(-1.0), None)).is_err());
+    assert_eq!((program[1].seek, program[1].out), (2.0, 9.0));
+
+    assert!(apply_edit(
+        &mut program,
+        PlaylistEdit::Trim {
+            index: 3,
+            seek: None,
+            out: Some(5.0),
+        }
+    )
+    .is_err());
+}
+
+#[test]
+fn playlist_edit_etag() {
+    use playlist_edit::{check_etag, playlist_etag, EtagError};
+
+    let playlist = JsonPlaylist {
+        channel: "Channel 1".into(),
+        date: "2022-05-20".into(),
+        start_sec: None,
+        current_file: None,
+        modified: None,
+        program: edit_program(),
+    };
+    let mut changed = playlist.clone();
+    changed.program.pop();
+
+    let etag = playlist_etag(&playlist);
+
+    assert_eq!(etag, playlist_etag(&playlist.clone()));
+    assert_ne!(etag, playlist_etag(&changed));
+    assert!(etag.starts_with('"') && etag.ends_with('"') && etag.len() == 66);
+
+    assert_eq!(check_etag(Some(&playlist), Some(&etag), true), Ok(()));
+    assert_eq!(
+        check_etag(Some(&playlist), Some(&format!("W/{etag}")), true),
+        Ok(())
+    );
+    assert_eq!(
+        check_etag(Some(&playlist), Some(&format!("\"old\", {etag}")), true),
+        Ok(())
+    );
+    assert_eq!(check_etag(Some(&playlist), Some("*"), true), Ok(()));
+    assert_eq!(
+        check_etag(Some(&changed), Some(&etag), true),
+        Err(EtagError::Changed)
+    );
+    assert_eq!(check_etag(None, Some("*"), false), Err(EtagError::Changed));
+    assert_eq!(
+        check_etag(Some(&playlist), None, true),
+        Err(EtagError::Missing)
+    );
+    assert_eq!(check_etag(None, None, false), Ok(()));
+}
+
+#[cfg(test)]
+fn export_config(name: &str) -> PlayoutConfig {
+    let mut config = PlayoutConfig::new(Some("../assets/ffplayout.yml".to_string()));
+    config.playlist.path = std::env::temp_dir().join(name).display().to_string();
+    config.playlist.start_sec = Some(0.0);
+    config.text.regex = r"^.+[/\\](.*)(.mp4|.mkv)$".into();
+
+    let path = playlist_copy::playlist_path(&config.playlist.path, "2022-05-20");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(
+        path,
+        r#"{"channel": "Test", "date": "2022-05-20", "program": [
+            {"in": 0, "out": 1800, "duration": 1800, "source": "/media/Show One.mp4"},
+            {"in": 0, "out": 60, "duration": 60, "source": "/media/ad.mp4", "category": "advertisement"},
+            {"in": 10, "out": 610, "duration": 900, "source": "/media/clip.mkv", "title": "News & Weather"}
+        ]}"#,
+    )
+    .unwrap();
+
+    config
+}
+
+#[test]
+fn epg_escape_xml() {
+    assert_eq!(
+        epg::escape_xml(r#"<a href="x">Tom & Jerry's</a>"#),
+        "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;"
+    );
+    assert_eq!(epg::escape_xml("plain text"), "plain text");
+    assert_eq!(epg::channel_id("Channel 1 & News"), "channel-1---news");
+}
+
+#[test]
+fn epg_xmltv_output() {
+    let config = export_config("ffp_epg_test");
+    let xml = epg::xmltv(
+        &config,
+        "Channel <1>",
+        &["2022-05-20".to_string(), "2022-05-21".to_string()],
+    );
+
+    assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<tv "));
+    assert!(xml.contains("<channel id=\"channel--1-\">"));
+    assert!(xml.contains("<display-name>Channel &lt;1&gt;</display-name>"));
+    assert_eq!(xml.matches("<programme ").count(), 2);
+
+    // the advertisement extends the program before
+    assert!(xml.contains("start=\"20220520000000 "));
+    assert!(xml.contains("stop=\"20220520003100 "));
+    assert!(xml.contains("<title>Show One</title>"));
+    assert!(xml.contains("start=\"20220520003100 "));
+    assert!(xml.contains("stop=\"20220520004100 "));
+    assert!(xml.contains("<title>News &amp; Weather</title>"));
+    assert!(!xml.contains("<category>"));
+    assert!(xml.ends_with("</tv>\n"));
+}
+
+#[test]
+fn export_playlist_formats() {
+    use export::{export_playlist, ExportFormat};
+
+    let config = export_config("ffp_export_test");
+
+    assert_eq!(
+        export_playlist(&config, "2022-05-20", ExportFormat::M3u).unwrap(),
+        "#EXTM3U\n\
+        #EXTINF:1800,Show One\n/media/Show One.mp4\n\
+        #EXTINF:60,ad\n/media/ad.mp4\n\
+        #EXTINF:600,News & Weather\n/media/clip.mkv\n"
+    );
+
+    assert_eq!(
+        export_playlist(&config, "2022-05-20", ExportFormat::Csv).unwrap(),
+        "begin,duration,in,out,source,category\n\
+        00:00:00.000,1800.000,0.000,1800.000,/media/Show One.mp4,\n\
+        00:30:00.000,60.000,0.000,60.000,/media/ad.mp4,advertisement\n\
+        00:31:00.000,600.000,10.000,610.000,/media/clip.mkv,\n"
+    );
+
+    let html = export_playlist(&config, "2022-05-20", ExportFormat::Html).unwrap();
+
+    assert!(html.contains("<title>Test - 2022-05-20</title>"));
+    assert_eq!(html.matches("<tr><td>").count(), 3);
+    assert!(html.contains(
+        "<tr><td>00:31:00.000</td><td>00:10:00.000</td><td>News &amp; Weather</td>\
+        <td></td><td>/media/clip.mkv</td></tr>"
+    ));
+
+    assert!(export_playlist(&config, "2022-05-21", ExportFormat::M3u).is_err());
+    assert_eq!("csv".parse::<ExportFormat>(), Ok(ExportFormat::Csv));
+    assert!("pdf".parse::<ExportFormat>().is_err());
+}
+
+#[test]
+fn upload_session_paths() {
+    let (meta, part) = upload::session_paths("/media", "abcdefghij0123456789").unwrap();
+
+    assert_eq!(
+        meta,
+        std::path::Path::new("/media/.uploads/abcdefghij0123456789.json")
+    );
+    assert_eq!(
+        part,
+        std::path::Path::new("/media/.uploads/abcdefghij0123456789.part")
+    );
+
+    assert!(upload::session_paths("/media", "abcdefghij012345678").is_err());
+    assert!(upload::session_paths("/media", "../../etc/passwd/abcd").is_err());
+    assert!(upload::session_paths("/media", "abcdefghij012345678.").is_err());
+    assert!(upload::session_paths("/media", "").is_err());
+}
+
+#[test]
+fn upload_chunk_offsets() {
+    let root = std::env::temp_dir().join("ffplayout_upload_chunk_test");
+    let part = root.join("chunk.part");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(&part, "").unwrap();
+
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&part)
+        .unwrap();
+
+    assert!(upload::check_offset(0, 0).is_ok());
+    assert_eq!(upload::append_chunk(&mut file, 0, 10, b"0123").unwrap(), 4);
+    assert_eq!(upload::append_chunk(&mut file, 4, 10, b"4567").unwrap(), 8);
+    assert_eq!(
+        upload::check_offset(4, 8),
+        Err("Offset mismatch, upload is at: 8".to_string())
+    );
+
+    // over the announced size: nothing is written, the client continues from the old offset
+    let err = upload::append_chunk(&mut file, 8, 10, b"89ab").unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!(std::fs::read(&part).unwrap(), b"01234567");
+
+    assert_eq!(upload::append_chunk(&mut file, 8, 10, b"89").unwrap(), 10);
+    assert_eq!(std::fs::read(&part).unwrap(), b"0123456789");
+}
+
+#[test]
+fn upload_chunk_truncate() {
+    let root = std::env::temp_dir().join("ffplayout_upload_truncate_test");
+    let part = root.join("chunk.part");
+    std::fs::create_dir_all(&root).unwrap();
+    // a broken request left more data than it reported
+    std::fs::write(&part, "0123456789").unwrap();
+
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&part)
+        .unwrap();
+
+    assert!(upload::append_chunk(&mut file, 6, 8, b"abcd").is_err());
+    assert_eq!(std::fs::read(&part).unwrap(), b"012345");
+}
+
+#[test]
+fn upload_checksum() {
+    let root = std::env::temp_dir().join("ffplayout_upload_checksum_test");
+    let meta = root.join("upload.json");
+    let part = root.join("upload.part");
+    // sha256 from "hello"
+    let hello = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(&meta, "{}").unwrap();
+    std::fs::write(&part, "hello").unwrap();
+
+    assert_eq!(
+        upload::normalize_checksum(&format!(" {} ", hello.to_uppercase())),
+        Ok(hello.to_string())
+    );
+    assert!(upload::normalize_checksum("abc").is_err());
+    assert!(upload::normalize_checksum(&hello.replace('2', "g")).is_err());
+
+    assert!(upload::verify_checksum(&meta, &part, hello).is_ok());
+    assert!(part.is_file());
+
+    let err = upload::verify_checksum(&meta, &part, &hello.replace('2', "3")).unwrap_err();
+
+    assert!(err.starts_with(&format!("Checksum mismatch, got: {hello}")));
+    assert!(!part.exists());
+    assert!(!meta.exists());
+}